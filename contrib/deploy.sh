@@ -118,7 +118,12 @@ sed "s/^version = \"$OLD_VERSION\"$/version = \"$NEW_VERSION\"/" \
     sed "s/^fend-core = { version = \"$OLD_VERSION\"/fend-core = { version = \"$NEW_VERSION\"/" >temp
 mv temp Cargo.toml
 
-gitdiff "" 3 3
+# fend-core release date
+sed "s/^const RELEASE_DATE: &str = \".*\";$/const RELEASE_DATE: \&str = \"$(date +%Y-%m-%d)\";/" \
+    core/src/lib.rs >temp
+mv temp core/src/lib.rs
+
+gitdiff "" 5 5
 
 manualstep "Add changelog to CHANGELOG.md"
 
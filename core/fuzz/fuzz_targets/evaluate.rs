@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::time::{Duration, Instant};
+
+// Some inputs (e.g. `1000000!`) legitimately take a long time, so stop
+// evaluating after a short timeout instead of reporting them as hangs
+struct TimeoutInterrupt {
+    start: Instant,
+}
+
+impl fend_core::Interrupt for TimeoutInterrupt {
+    fn should_interrupt(&self) -> bool {
+        self.start.elapsed() > Duration::from_millis(500)
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let input = match std::str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+    let mut context = fend_core::Context::new();
+    let interrupt = TimeoutInterrupt {
+        start: Instant::now(),
+    };
+    // any input is allowed to fail, but it must never panic
+    let _ = fend_core::evaluate_with_interrupt(input, &mut context, &interrupt);
+});
@@ -2,11 +2,12 @@ use crate::error::{FendError, Interrupt};
 use crate::eval::evaluate_to_value;
 use crate::ident::Ident;
 use crate::interrupt::test_int;
-use crate::num::{Base, FormattingStyle, Number};
+use crate::num::{Base, FormattingStyle, Number, Rounding};
 use crate::scope::Scope;
-use crate::serialize::{deserialize_u8, serialize_u8};
+use crate::serialize::{deserialize_u8, deserialize_usize, serialize_u8, serialize_usize};
 use crate::value::{built_in_function::BuiltInFunction, ApplyMulHandling, Value};
 use crate::Attrs;
+use std::cmp::Ordering;
 use std::sync::Arc;
 use std::{fmt, io};
 
@@ -19,6 +20,29 @@ pub(crate) enum BitwiseBop {
     RightShift,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ComparisonOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl ComparisonOp {
+    fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Equal => ordering.is_eq(),
+            Self::NotEqual => ordering.is_ne(),
+            Self::Less => ordering.is_lt(),
+            Self::LessOrEqual => ordering.is_le(),
+            Self::Greater => ordering.is_gt(),
+            Self::GreaterOrEqual => ordering.is_ge(),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub(crate) enum Bop {
     Plus,
@@ -26,10 +50,12 @@ pub(crate) enum Bop {
     Minus,
     Mul,
     Div,
+    IntDiv,
     Mod,
     Pow,
     Bitwise(BitwiseBop),
     Combination,
+    Comparison(ComparisonOp),
 }
 
 impl Bop {
@@ -48,6 +74,13 @@ impl Bop {
             Self::Bitwise(BitwiseBop::LeftShift) => 10,
             Self::Bitwise(BitwiseBop::RightShift) => 11,
             Self::Combination => 12,
+            Self::Comparison(ComparisonOp::Equal) => 13,
+            Self::Comparison(ComparisonOp::NotEqual) => 14,
+            Self::Comparison(ComparisonOp::Less) => 15,
+            Self::Comparison(ComparisonOp::LessOrEqual) => 16,
+            Self::Comparison(ComparisonOp::Greater) => 17,
+            Self::Comparison(ComparisonOp::GreaterOrEqual) => 18,
+            Self::IntDiv => 19,
         };
         serialize_u8(n, write)?;
         Ok(())
@@ -68,6 +101,13 @@ impl Bop {
             10 => Self::Bitwise(BitwiseBop::LeftShift),
             11 => Self::Bitwise(BitwiseBop::RightShift),
             12 => Self::Combination,
+            13 => Self::Comparison(ComparisonOp::Equal),
+            14 => Self::Comparison(ComparisonOp::NotEqual),
+            15 => Self::Comparison(ComparisonOp::Less),
+            16 => Self::Comparison(ComparisonOp::LessOrEqual),
+            17 => Self::Comparison(ComparisonOp::Greater),
+            18 => Self::Comparison(ComparisonOp::GreaterOrEqual),
+            19 => Self::IntDiv,
             _ => return Err(FendError::DeserializationError),
         })
     }
@@ -81,6 +121,7 @@ impl fmt::Display for Bop {
             Self::Minus => "-",
             Self::Mul => "*",
             Self::Div => "/",
+            Self::IntDiv => "//",
             Self::Mod => " mod ",
             Self::Pow => "^",
             Self::Bitwise(BitwiseBop::And) => "&",
@@ -89,6 +130,12 @@ impl fmt::Display for Bop {
             Self::Bitwise(BitwiseBop::LeftShift) => "<<",
             Self::Bitwise(BitwiseBop::RightShift) => ">>",
             Self::Combination => "nCr",
+            Self::Comparison(ComparisonOp::Equal) => "==",
+            Self::Comparison(ComparisonOp::NotEqual) => "!=",
+            Self::Comparison(ComparisonOp::Less) => "<",
+            Self::Comparison(ComparisonOp::LessOrEqual) => "<=",
+            Self::Comparison(ComparisonOp::Greater) => ">",
+            Self::Comparison(ComparisonOp::GreaterOrEqual) => ">=",
         };
         write!(f, "{s}")
     }
@@ -118,6 +165,10 @@ pub(crate) enum Expr {
 
     Assign(Ident, Box<Expr>),
     Statements(Box<Expr>, Box<Expr>),
+    // comma-separated list, e.g. `(1, 2)`
+    List(Vec<Self>),
+    // `if <condition> then <a> else <b>`
+    If(Box<Self>, Box<Self>, Box<Self>),
 }
 
 impl Expr {
@@ -197,6 +248,19 @@ impl Expr {
                 a.serialize(write)?;
                 b.serialize(write)?;
             }
+            Self::List(items) => {
+                serialize_u8(16, write)?;
+                serialize_usize(items.len(), write)?;
+                for item in items {
+                    item.serialize(write)?;
+                }
+            }
+            Self::If(a, b, c) => {
+                serialize_u8(17, write)?;
+                a.serialize(write)?;
+                b.serialize(write)?;
+                c.serialize(write)?;
+            }
         }
         Ok(())
     }
@@ -247,6 +311,19 @@ impl Expr {
                 Box::new(Self::deserialize(read)?),
                 Box::new(Self::deserialize(read)?),
             ),
+            16 => {
+                let len = deserialize_usize(read)?;
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(Self::deserialize(read)?);
+                }
+                Self::List(items)
+            }
+            17 => Self::If(
+                Box::new(Self::deserialize(read)?),
+                Box::new(Self::deserialize(read)?),
+                Box::new(Self::deserialize(read)?),
+            ),
             _ => return Err(FendError::DeserializationError),
         })
     }
@@ -304,6 +381,84 @@ impl Expr {
                 a.format(attrs, ctx, int)?,
                 b.format(attrs, ctx, int)?
             ),
+            Self::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.format(attrs, ctx, int))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("({})", items.join(", "))
+            }
+            Self::If(a, b, c) => format!(
+                "(if {} then {} else {})",
+                a.format(attrs, ctx, int)?,
+                b.format(attrs, ctx, int)?,
+                c.format(attrs, ctx, int)?
+            ),
+        })
+    }
+}
+
+impl Expr {
+    /// Formats this expression with spaces around operators, and with
+    /// parentheses around every operand that isn't a single value, e.g.
+    /// `1 + (2 * 3)`. Unlike `format`, the outermost expression is never
+    /// wrapped in parentheses.
+    pub(crate) fn format_canonical<I: Interrupt>(
+        &self,
+        attrs: Attrs,
+        ctx: &crate::Context,
+        int: &I,
+    ) -> Result<String, FendError> {
+        let operand = |x: &Self| -> Result<String, FendError> {
+            let s = x.format_canonical(attrs, ctx, int)?;
+            Ok(match x {
+                Self::Literal(_)
+                | Self::Ident(_)
+                | Self::Parens(_)
+                | Self::Factorial(_)
+                | Self::List(_) => s,
+                _ => format!("({s})"),
+            })
+        };
+        Ok(match self {
+            Self::Literal(_) | Self::Ident(_) => self.format(attrs, ctx, int)?,
+            Self::Parens(x) => format!("({})", x.format_canonical(attrs, ctx, int)?),
+            Self::UnaryMinus(x) => format!("-{}", operand(x)?),
+            Self::UnaryPlus(x) => format!("+{}", operand(x)?),
+            Self::UnaryDiv(x) => format!("/{}", operand(x)?),
+            Self::Factorial(x) => format!("{}!", operand(x)?),
+            Self::Bop(op, a, b) => {
+                let op = op.to_string();
+                match op.trim() {
+                    "" => format!("{} {}", operand(a)?, operand(b)?),
+                    op => format!("{} {op} {}", operand(a)?, operand(b)?),
+                }
+            }
+            Self::Apply(a, b) | Self::ApplyFunctionCall(a, b) | Self::ApplyMul(a, b) => {
+                format!("{} {}", operand(a)?, operand(b)?)
+            }
+            Self::As(a, b) => format!("{} to {}", operand(a)?, operand(b)?),
+            Self::Fn(a, b) => format!("{a}: {}", b.format_canonical(attrs, ctx, int)?),
+            Self::Of(a, b) => format!("{a} of {}", operand(b)?),
+            Self::Assign(a, b) => format!("{a} = {}", b.format_canonical(attrs, ctx, int)?),
+            Self::Statements(a, b) => format!(
+                "{}; {}",
+                a.format_canonical(attrs, ctx, int)?,
+                b.format_canonical(attrs, ctx, int)?
+            ),
+            Self::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.format_canonical(attrs, ctx, int))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("({})", items.join(", "))
+            }
+            Self::If(a, b, c) => format!(
+                "if {} then {} else {}",
+                operand(a)?,
+                operand(b)?,
+                operand(c)?
+            ),
         })
     }
 }
@@ -341,25 +496,47 @@ pub(crate) fn evaluate<I: Interrupt>(
             evaluate($e, scope.clone(), attrs, context, int)
         };
     }
+    // evaluates an operand of an arithmetic operator
+    macro_rules! eval_operand {
+        ($e:expr) => {
+            eval!($e)?.bool_as_num(context)
+        };
+    }
     test_int(int)?;
     Ok(match expr {
+        Expr::Bop(bop, a, b) if bop != Bop::Pow && matches!(*a, Expr::Bop(..)) => {
+            evaluate_bop_chain(bop, *a, *b, scope.as_ref(), attrs, context, int)?
+        }
         Expr::Literal(v) => v,
         Expr::Ident(ident) => resolve_identifier(&ident, scope, attrs, context, int)?,
         Expr::Parens(x) => eval!(*x)?,
-        Expr::UnaryMinus(x) => eval!(*x)?.handle_num(|x| Ok(-x), Expr::UnaryMinus, scope)?,
-        Expr::UnaryPlus(x) => eval!(*x)?.handle_num(Ok, Expr::UnaryPlus, scope)?,
+        Expr::UnaryMinus(x) => eval_operand!(*x).handle_num(|x| Ok(-x), Expr::UnaryMinus, scope)?,
+        Expr::UnaryPlus(x) => eval_operand!(*x).handle_num(Ok, Expr::UnaryPlus, scope)?,
         Expr::UnaryDiv(x) => {
-            eval!(*x)?.handle_num(|x| Number::from(1).div(x, int), Expr::UnaryDiv, scope)?
+            eval_operand!(*x).handle_num(|x| Number::from(1).div(x, int), Expr::UnaryDiv, scope)?
         }
         Expr::Factorial(x) => {
-            eval!(*x)?.handle_num(|x| x.factorial(int), Expr::Factorial, scope)?
+            eval_operand!(*x).handle_num(|x| x.factorial(int), Expr::Factorial, scope)?
+        }
+        Expr::Bop(bop, a, b) if context.explanation.is_some() => {
+            evaluate_explained_bop(bop, *a, *b, scope, attrs, context, int)?
+        }
+        Expr::Bop(Bop::Plus, a, b) => {
+            let a = eval_operand!(*a);
+            let b = eval_operand!(*b);
+            evaluate_add(a, b, scope, int)?
         }
-        Expr::Bop(Bop::Plus, a, b) => evaluate_add(eval!(*a)?, eval!(*b)?, scope, int)?,
         Expr::Bop(Bop::Minus, a, b) => {
-            let a = eval!(*a)?;
+            let a = eval_operand!(*a);
             match a {
-                Value::Num(a) => Value::Num(Box::new(a.sub(eval!(*b)?.expect_num()?, int)?)),
-                f @ (Value::BuiltInFunction(_) | Value::Fn(_, _, _)) => f.apply(
+                Value::Num(a) => {
+                    let b = eval_operand!(*b).expect_num()?;
+                    Value::Num(Box::new(a.sub(b, int)?))
+                }
+                Value::Bool(_) => return Err(FendError::BoolUsedAsNumber),
+                f @ (Value::BuiltInFunction(_)
+                | Value::RegisteredFunction(_)
+                | Value::Fn(_, _, _)) => f.apply(
                     Expr::UnaryMinus(b),
                     ApplyMulHandling::OnlyApply,
                     scope,
@@ -371,11 +548,13 @@ pub(crate) fn evaluate<I: Interrupt>(
             }
         }
         Expr::Bop(Bop::Pow, a, b) => {
-            let lhs = eval!(*a)?;
+            let lhs = eval_operand!(*a);
             if should_compute_inverse(&b, int)? {
                 let result = match &lhs {
                     Value::BuiltInFunction(f) => Some(f.invert()?),
-                    Value::Fn(_, _, _) => return Err(FendError::InversesOfLambdasUnsupported),
+                    Value::Fn(_, _, _) | Value::RegisteredFunction(_) => {
+                        return Err(FendError::InversesOfLambdasUnsupported)
+                    }
                     _ => None,
                 };
                 if let Some(res) = result {
@@ -383,7 +562,7 @@ pub(crate) fn evaluate<I: Interrupt>(
                 }
             }
             lhs.handle_two_nums(
-                eval!(*b)?,
+                eval_operand!(*b),
                 |a, b| a.pow(b, int),
                 |a| {
                     |f| {
@@ -406,13 +585,39 @@ pub(crate) fn evaluate<I: Interrupt>(
                 scope,
             )?
         }
-        Expr::Bop(bop, a, b) => eval!(*a)?.handle_two_nums(
-            eval!(*b)?,
-            |a, b| a.bop(bop, b, attrs, context, int),
-            |a| |f| Expr::Bop(bop, f, Box::new(Expr::Literal(Value::Num(Box::new(a))))),
-            |a| |f| Expr::Bop(bop, Box::new(Expr::Literal(Value::Num(Box::new(a)))), f),
-            scope,
-        )?,
+        Expr::Bop(Bop::Comparison(op), a, b) => {
+            let (a, b) = (eval!(*a)?, eval!(*b)?);
+            let (a, b) = match (&a, &b) {
+                (Value::Bool(_), Value::Num(_)) | (Value::Num(_), Value::Bool(_)) => {
+                    (a.bool_as_num(context), b.bool_as_num(context))
+                }
+                _ => (a, b),
+            };
+            evaluate_comparison(op, a, b, int)?
+        }
+        Expr::Bop(bop, a, b) => match (bop, eval!(*a)?) {
+            // `and`, `or` and `xor` also work as logical operators
+            (Bop::Bitwise(op), Value::Bool(a)) => Value::Bool(match op {
+                BitwiseBop::And => a && eval!(*b)?.as_bool()?,
+                BitwiseBop::Or => a || eval!(*b)?.as_bool()?,
+                BitwiseBop::Xor => a != eval!(*b)?.as_bool()?,
+                BitwiseBop::LeftShift | BitwiseBop::RightShift => {
+                    return Err(FendError::ExpectedANumber)
+                }
+            }),
+            // `3 sin 2` is parsed like `3 feet 2`, but since `3 sin` is a
+            // function it's applied to the number instead, i.e. `3 * sin(2)`
+            (Bop::ImplicitPlus, f) if f.is_fn() => {
+                f.apply(*b, ApplyMulHandling::OnlyApply, scope, attrs, context, int)?
+            }
+            (_, a) => a.bool_as_num(context).handle_two_nums(
+                eval_operand!(*b),
+                |a, b| a.bop(bop, b, attrs, context, int),
+                |a| |f| Expr::Bop(bop, f, Box::new(Expr::Literal(Value::Num(Box::new(a))))),
+                |a| |f| Expr::Bop(bop, Box::new(Expr::Literal(Value::Num(Box::new(a)))), f),
+                scope,
+            )?,
+        },
         Expr::Apply(a, b) | Expr::ApplyMul(a, b) => {
             if let (Expr::Ident(a), Expr::Ident(b)) = (&*a, &*b) {
                 let ident = format!("{a}_{b}");
@@ -429,17 +634,235 @@ pub(crate) fn evaluate<I: Interrupt>(
         Expr::Fn(a, b) => Value::Fn(a, b, scope),
         Expr::Of(a, b) => eval!(*b)?.get_object_member(&a)?,
         Expr::Assign(a, b) => {
+            if context.constants.contains_key(a.as_str()) {
+                return Err(FendError::CannotReassignConstant(a.to_string()));
+            }
             let rhs = evaluate(*b, scope, attrs, context, int)?;
-            context.variables.insert(a.to_string(), rhs.clone());
+            if context.checking_if_branch {
+                return Ok(rhs);
+            }
+            context.result_is_cacheable = false;
+            context.result_cache.clear();
+            context
+                .variables
+                .insert(a.as_str().to_string(), rhs.clone());
             rhs
         }
         Expr::Statements(a, b) => {
-            let _lhs = evaluate(*a, scope.clone(), attrs, context, int)?;
-            evaluate(*b, scope, attrs, context, int)?
+            // evaluate long lists of statements without recursing
+            let mut statements = vec![*b];
+            let mut first = *a;
+            while let Expr::Statements(a, b) = first {
+                statements.push(*b);
+                first = *a;
+            }
+            let mut result = evaluate(first, scope.clone(), attrs, context, int)?;
+            for statement in statements.into_iter().rev() {
+                result = evaluate(statement, scope.clone(), attrs, context, int)?;
+            }
+            result
         }
+        Expr::List(items) => Value::List(
+            items
+                .into_iter()
+                .map(|item| evaluate(item, scope.clone(), attrs, context, int))
+                .collect::<Result<_, _>>()?,
+        ),
+        Expr::If(condition, a, b) => evaluate_if(*condition, *a, *b, scope, attrs, context, int)?,
     })
 }
 
+/// Evaluates an `if` expression. The result only comes from the branch
+/// that's taken, but the other branch is still checked for compatibility.
+fn evaluate_if<I: Interrupt>(
+    condition: Expr,
+    a: Expr,
+    b: Expr,
+    scope: Option<Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Value, FendError> {
+    let (taken, other) = if evaluate(condition, scope.clone(), attrs, context, int)?.as_bool()? {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let result = evaluate(taken, scope.clone(), attrs, context, int)?;
+    if !context.checking_if_branch {
+        check_if_branch(&result, other, scope, attrs, context, int)?;
+    }
+    Ok(result)
+}
+
+/// Evaluates the branch of an `if` expression that wasn't taken, and returns
+/// an error if its value isn't compatible with the `result` of the other
+/// branch. Errors from this branch (e.g. a division by zero) are ignored, as
+/// are any variables it assigns. `if` expressions nested inside it only
+/// evaluate their taken branch, so recursive functions still terminate.
+fn check_if_branch<I: Interrupt>(
+    result: &Value,
+    branch: Expr,
+    scope: Option<Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<(), FendError> {
+    let explanation = context.explanation.take();
+    let result_is_cacheable = context.result_is_cacheable;
+    context.checking_if_branch = true;
+    let other = evaluate(branch, scope, attrs, context, int);
+    context.checking_if_branch = false;
+    context.explanation = explanation;
+    context.result_is_cacheable = result_is_cacheable;
+    let other = match other {
+        Ok(other) => other,
+        Err(FendError::Interrupted) => return Err(FendError::Interrupted),
+        Err(_) => return Ok(()),
+    };
+    let compatible = match (result, &other) {
+        (Value::Num(a), Value::Num(b)) => a.is_compatible_with(b, int)?,
+        (a, b) => a.type_name() == b.type_name(),
+    };
+    if !compatible {
+        return Err(FendError::IncompatibleIfBranches);
+    }
+    Ok(())
+}
+
+/// Evaluates a chain of left-associative operators like `1 + 2 + 3`
+/// iteratively, by evaluating the innermost left operand first and then
+/// substituting each intermediate result as a literal
+fn evaluate_bop_chain<I: Interrupt>(
+    bop: Bop,
+    a: Expr,
+    b: Expr,
+    scope: Option<&Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Value, FendError> {
+    let mut operations = vec![(bop, b)];
+    let mut first = a;
+    loop {
+        match first {
+            Expr::Bop(bop, a, b) if bop != Bop::Pow => {
+                operations.push((bop, *b));
+                first = *a;
+            }
+            _ => break,
+        }
+    }
+    let mut result = evaluate(first, scope.cloned(), attrs, context, int)?;
+    for (bop, b) in operations.into_iter().rev() {
+        let expr = Expr::Bop(bop, Box::new(Expr::Literal(result)), Box::new(b));
+        result = evaluate(expr, scope.cloned(), attrs, context, int)?;
+    }
+    Ok(result)
+}
+
+/// Records a step for `Context::explain`
+fn record_step(context: &mut crate::Context, step: String) {
+    if let Some(steps) = &mut context.explanation {
+        steps.push(step);
+    }
+}
+
+fn format_num<I: Interrupt>(
+    n: &Number,
+    attrs: Attrs,
+    context: &crate::Context,
+    int: &I,
+) -> Result<String, FendError> {
+    Value::Num(Box::new(n.clone())).format_to_plain_string(0, attrs, context, int)
+}
+
+/// Evaluates a binary operator while explaining an evaluation, recording
+/// the operation (and any unit conversion it needs) as a step
+fn evaluate_explained_bop<I: Interrupt>(
+    bop: Bop,
+    a: Expr,
+    b: Expr,
+    scope: Option<Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Value, FendError> {
+    let a = evaluate(a, scope.clone(), attrs, context, int)?;
+    // only operations on numbers are explained, so that e.g. `and`
+    // can still short-circuit
+    let b = match a {
+        Value::Num(_) => Expr::Literal(evaluate(b, scope.clone(), attrs, context, int)?),
+        _ => b,
+    };
+    let expr = Expr::Bop(bop, Box::new(Expr::Literal(a.clone())), Box::new(b.clone()));
+    let steps = context.explanation.take();
+    let result = evaluate(expr, scope, attrs, context, int);
+    context.explanation = steps;
+    let result = result?;
+    if let (Value::Num(a), Expr::Literal(Value::Num(b))) = (&a, &b) {
+        if matches!(bop, Bop::Plus | Bop::Minus | Bop::ImplicitPlus) {
+            if let Ok(converted) = b.clone().convert_to_unit_of(a, int) {
+                let from = format_num(b, attrs, context, int)?;
+                let to = format_num(&converted, attrs, context, int)?;
+                if from != to {
+                    record_step(context, format!("{from} = {to}"));
+                }
+            }
+        }
+        let op = match bop {
+            Bop::ImplicitPlus => "+".to_string(),
+            bop => bop.to_string().trim().to_string(),
+        };
+        let step = format!(
+            "{} {op} {} = {}",
+            format_num(a, attrs, context, int)?,
+            format_num(b, attrs, context, int)?,
+            result.format_to_plain_string(0, attrs, context, int)?
+        );
+        record_step(context, step);
+    }
+    Ok(result)
+}
+
+fn evaluate_num<I: Interrupt>(
+    expr: Expr,
+    scope: Option<Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Number, FendError> {
+    evaluate(expr, scope, attrs, context, int)?
+        .bool_as_num(context)
+        .expect_num()
+}
+
+fn evaluate_comparison<I: Interrupt>(
+    op: ComparisonOp,
+    a: Value,
+    b: Value,
+    int: &I,
+) -> Result<Value, FendError> {
+    Ok(Value::Bool(match (a, b) {
+        (Value::Num(a), Value::Num(b)) => match op {
+            // complex numbers can be compared for equality, but not ordered
+            ComparisonOp::Equal => a.equals(&b, int)?,
+            ComparisonOp::NotEqual => !a.equals(&b, int)?,
+            _ => op.matches(a.compare(&b, int)?),
+        },
+        (Value::Bool(a), Value::Bool(b))
+            if matches!(op, ComparisonOp::Equal | ComparisonOp::NotEqual) =>
+        {
+            op.matches(a.cmp(&b))
+        }
+        (Value::String(a), Value::String(b)) => op.matches(a.as_ref().cmp(b.as_ref())),
+        (Value::Bool(_), Value::Num(_)) | (Value::Num(_), Value::Bool(_)) => {
+            return Err(FendError::BoolComparedWithNumber)
+        }
+        _ => return Err(FendError::InvalidOperandsForComparison),
+    }))
+}
+
 fn evaluate_add<I: Interrupt>(
     a: Value,
     b: Value,
@@ -478,10 +901,196 @@ fn evaluate_add<I: Interrupt>(
             scope,
         ),
         (Value::Date(d), b) => d.add(b)?,
+        (Value::Bool(_), _) | (_, Value::Bool(_)) => return Err(FendError::BoolUsedAsNumber),
         _ => return Err(FendError::ExpectedANumber),
     })
 }
 
+/// Splits a conversion target like `binary 8dp` or `hex float` into
+/// its first term and the remaining formatting style
+fn split_conversion_target(b: &Expr) -> Option<(&Expr, Expr)> {
+    match b {
+        Expr::Apply(lhs, rhs) | Expr::ApplyMul(lhs, rhs) => match &**lhs {
+            Expr::Ident(_) => Some((lhs, (**rhs).clone())),
+            Expr::ApplyFunctionCall(first, num) if matches!(**first, Expr::Ident(_)) => Some((
+                first,
+                Expr::ApplyMul(Box::new((**num).clone()), Box::new((**rhs).clone())),
+            )),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Splits e.g. `ceil 17 minutes` into `ceil` and `17 minutes`, if the
+/// expression starts with one of the given identifiers
+fn split_leading_ident<'a>(expr: &Expr, names: &[&'a str]) -> Option<(&'a str, Expr)> {
+    match expr {
+        Expr::Apply(lhs, rhs) | Expr::ApplyFunctionCall(lhs, rhs) | Expr::ApplyMul(lhs, rhs) => {
+            if let Expr::Ident(ident) = &**lhs {
+                return names
+                    .iter()
+                    .find(|&&name| name == ident.as_str())
+                    .map(|&name| (name, (**rhs).clone()));
+            }
+            let (name, inner) = split_leading_ident(lhs, names)?;
+            Some((name, Expr::ApplyMul(Box::new(inner), rhs.clone())))
+        }
+        _ => None,
+    }
+}
+
+/// Splits a leading `round`, `floor` or `ceil` off the given expression
+fn split_rounding(expr: &Expr, mode: crate::RoundingMode) -> Option<(Rounding, Expr)> {
+    Some(
+        match split_leading_ident(expr, &["round", "floor", "ceil"])? {
+            ("floor", value) => (Rounding::Floor, value),
+            ("ceil", value) => (Rounding::Ceil, value),
+            (_, value) => (Rounding::Round(mode), value),
+        },
+    )
+}
+
+/// Matches `<n> sf`, `<n> sig figs` or `<n> significant figures`, returning
+/// `<n>` along with the last word
+fn split_sig_figs(expr: &Expr) -> Option<(Expr, &str)> {
+    let (Expr::Apply(lhs, rhs) | Expr::ApplyFunctionCall(lhs, rhs) | Expr::ApplyMul(lhs, rhs)) =
+        expr
+    else {
+        return None;
+    };
+    let Expr::Ident(word) = &**rhs else {
+        return None;
+    };
+    match word.as_str() {
+        "sf" => Some(((**lhs).clone(), word.as_str())),
+        "fig" | "figs" | "figure" | "figures" => match &**lhs {
+            Expr::Apply(n, sig) | Expr::ApplyFunctionCall(n, sig) | Expr::ApplyMul(n, sig) => {
+                match &**sig {
+                    Expr::Ident(sig) if matches!(sig.as_str(), "sig" | "significant") => {
+                        Some(((**n).clone(), word.as_str()))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Converts `a` to the unit of `b`, recording the conversion as a step
+/// if an evaluation is being explained
+fn convert_units<I: Interrupt>(
+    a: &Number,
+    b: Number,
+    target: Option<String>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Number, FendError> {
+    let converted = a.clone().convert_to_explicit(b, int)?;
+    if let Some(target) = target {
+        let step = format!(
+            "{} to {target} = {}",
+            format_num(a, attrs, context, int)?,
+            format_num(&converted, attrs, context, int)?
+        );
+        record_step(context, step);
+    }
+    Ok(converted)
+}
+
+/// Handles rounding to an increment or to a number of significant figures,
+/// returning `None` if `b` isn't a rounding target
+fn evaluate_rounding<I: Interrupt>(
+    a: &Expr,
+    b: &Expr,
+    scope: Option<Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Option<Value>, FendError> {
+    // support rounding to an increment, e.g. `ceil 17 minutes to nearest 15 minutes`
+    if let Some((_, increment)) = split_leading_ident(b, &["nearest"]) {
+        let (rounding, a) = split_rounding(a, context.rounding_mode)
+            .unwrap_or((Rounding::Round(context.rounding_mode), a.clone()));
+        let value = evaluate(a, scope.clone(), attrs, context, int)?.expect_num()?;
+        let increment = evaluate(increment, scope, attrs, context, int)?.expect_num()?;
+        return Ok(Some(Value::Num(Box::new(
+            value.round_to_increment(increment, rounding, int)?,
+        ))));
+    }
+    // support rounding to significant figures, e.g. `round 12345 m to 2 sig figs`,
+    // while `12345 m to 2 sig figs` only changes how the number is shown
+    if let Some((sig_figs, word)) = split_sig_figs(b) {
+        if let Some((rounding, a)) = split_rounding(a, context.rounding_mode) {
+            let value = evaluate(a, scope.clone(), attrs, context, int)?.expect_num()?;
+            let sig_figs = evaluate(sig_figs, scope, attrs, context, int)?
+                .expect_num()?
+                .try_as_usize(int)?;
+            if sig_figs == 0 {
+                return Err(FendError::CannotFormatWithZeroSf);
+            }
+            return Ok(Some(Value::Num(Box::new(
+                value.round_to_sig_figs(sig_figs, rounding, int)?,
+            ))));
+        }
+        if word != "sf" {
+            let b = Expr::ApplyMul(Box::new(sig_figs), Box::new(Expr::Ident("sf".into())));
+            return evaluate_as(a.clone(), b, scope, attrs, context, int).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Formats a duration as hours, minutes and seconds, e.g. `1:01:01` for
+/// 3661 seconds. Hours don't wrap around to days, and fractional seconds
+/// are kept (e.g. `0:01:30.5`).
+fn format_hms<I: Interrupt>(
+    duration: Number,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<String, FendError> {
+    let seconds = crate::units::query_unit_static("s", attrs, context, int)?.expect_num()?;
+    let total = duration
+        .convert_to(seconds.clone(), int)?
+        .div(seconds, int)?
+        .convert_to(1.into(), int)?;
+    let negative = total.compare(&0.into(), int)? == Ordering::Less;
+    let total = total.abs(int)?;
+    let hours = total
+        .clone()
+        .round_to_increment(3600.into(), Rounding::Floor, int)?;
+    let rest = total.sub(hours.clone(), int)?;
+    let minutes = rest
+        .clone()
+        .round_to_increment(60.into(), Rounding::Floor, int)?;
+    let secs = rest.sub(minutes.clone(), int)?;
+    let hours = hours
+        .div(3600.into(), int)?
+        .make_exact()?
+        .try_as_usize(int)
+        .map_err(|e| match e {
+            FendError::OutOfRange { .. } => FendError::DurationTooLargeForHms,
+            e => e,
+        })?;
+    let minutes = minutes
+        .div(60.into(), int)?
+        .make_exact()?
+        .try_as_usize(int)?;
+    let pad = secs.compare(&10.into(), int)? == Ordering::Less;
+    let secs = Value::Num(Box::new(secs)).format_to_plain_string(0, attrs, context, int)?;
+    let (approx, secs) = match secs.strip_prefix("approx. ") {
+        Some(secs) => ("approx. ", secs),
+        None => ("", secs.as_str()),
+    };
+    let sign = if negative { "-" } else { "" };
+    let pad = if pad { "0" } else { "" };
+    Ok(format!("{approx}{sign}{hours}:{minutes:02}:{pad}{secs}"))
+}
+
 fn evaluate_as<I: Interrupt>(
     a: Expr,
     b: Expr,
@@ -490,11 +1099,24 @@ fn evaluate_as<I: Interrupt>(
     context: &mut crate::Context,
     int: &I,
 ) -> Result<Value, FendError> {
+    if let Some(value) = evaluate_rounding(&a, &b, scope.clone(), attrs, context, int)? {
+        return Ok(value);
+    }
+    // support combining a base with a formatting style, e.g. `to binary 8dp`
+    if let Some((base, format)) = split_conversion_target(&b) {
+        if let Ok(Value::Base(_)) = evaluate(base.clone(), scope.clone(), attrs, context, int) {
+            let a = Expr::As(Box::new(a), Box::new(base.clone()));
+            return evaluate_as(a, format, scope, attrs, context, int);
+        }
+    }
     if let Expr::Ident(ident) = &b {
         match ident.as_str() {
             "bool" | "boolean" => {
-                let num = evaluate(a, scope, attrs, context, int)?.expect_num()?;
-                return Ok(Value::Bool(!num.is_zero()));
+                let value = evaluate(a, scope, attrs, context, int)?;
+                if let Value::Bool(b) = value {
+                    return Ok(Value::Bool(b));
+                }
+                return Ok(Value::Bool(!value.expect_num()?.is_zero()));
             }
             "date" => {
                 let a = evaluate(a, scope, attrs, context, int)?;
@@ -504,6 +1126,17 @@ fn evaluate_as<I: Interrupt>(
                     Err(FendError::ExpectedAString)
                 };
             }
+            "dec" | "decimal" => {
+                let a = evaluate_num(a, scope, attrs, context, int)?;
+                return Ok(Value::Num(Box::new(
+                    a.with_base(Base::from_plain_base(10)?)
+                        .with_decimal_format(),
+                )));
+            }
+            "hms" => {
+                let a = evaluate_num(a, scope, attrs, context, int)?;
+                return Ok(Value::String(format_hms(a, attrs, context, int)?.into()));
+            }
             "string" => {
                 return Ok(Value::String(
                     evaluate(a, scope, attrs, context, int)?
@@ -532,16 +1165,19 @@ fn evaluate_as<I: Interrupt>(
             _ => (),
         }
     }
+    let target = match context.explanation {
+        Some(_) => Some(b.format(attrs, context, int)?),
+        None => None,
+    };
     Ok(match evaluate(b, scope.clone(), attrs, context, int)? {
-        Value::Num(b) => Value::Num(Box::new(
-            evaluate(a, scope, attrs, context, int)?
-                .expect_num()?
-                .convert_to(*b, int)?,
-        )),
+        Value::Num(b) => {
+            let a = evaluate_num(a, scope, attrs, context, int)?;
+            Value::Num(Box::new(convert_units(
+                &a, *b, target, attrs, context, int,
+            )?))
+        }
         Value::Format(fmt) => Value::Num(Box::new(
-            evaluate(a, scope, attrs, context, int)?
-                .expect_num()?
-                .with_format(fmt),
+            evaluate_num(a, scope, attrs, context, int)?.with_format(fmt),
         )),
         Value::Dp => {
             return Err(FendError::SpecifyNumDp);
@@ -550,9 +1186,7 @@ fn evaluate_as<I: Interrupt>(
             return Err(FendError::SpecifyNumSf);
         }
         Value::Base(base) => Value::Num(Box::new(
-            evaluate(a, scope, attrs, context, int)?
-                .expect_num()?
-                .with_base(base),
+            evaluate_num(a, scope, attrs, context, int)?.with_base(base),
         )),
         other => {
             return Err(FendError::CannotConvertValueTo(other.type_name()));
@@ -560,6 +1194,58 @@ fn evaluate_as<I: Interrupt>(
     })
 }
 
+/// Built-in function and constant names, used for autocompletion. Each of
+/// these must be handled by [`resolve_builtin`].
+pub(crate) const BUILTIN_FUNCTION_NAMES: &[&str] = &[
+    "abs",
+    "acos",
+    "acosh",
+    "approx",
+    "asin",
+    "asinh",
+    "atan",
+    "atanh",
+    "avg",
+    "base",
+    "cbrt",
+    "ceil",
+    "cis",
+    "conjugate",
+    "cos",
+    "cosh",
+    "cubic",
+    "exp",
+    "filter",
+    "floor",
+    "geometric_mean",
+    "harmonic_mean",
+    "inv",
+    "is_compatible",
+    "ln",
+    "log",
+    "log10",
+    "log2",
+    "map",
+    "mean",
+    "mod",
+    "not",
+    "phi",
+    "pi",
+    "range",
+    "reciprocal",
+    "roll",
+    "round",
+    "sample",
+    "sin",
+    "sinh",
+    "sort",
+    "sqrt",
+    "square",
+    "tan",
+    "tanh",
+    "tau",
+];
+
 pub(crate) fn resolve_identifier<I: Interrupt>(
     ident: &Ident,
     scope: Option<Arc<Scope>>,
@@ -567,6 +1253,47 @@ pub(crate) fn resolve_identifier<I: Interrupt>(
     context: &mut crate::Context,
     int: &I,
 ) -> Result<Value, FendError> {
+    if let Some(scope) = scope.clone() {
+        if let Some(val) = scope.get(ident, attrs, context, int)? {
+            return Ok(val);
+        }
+    }
+    if let Some(val) = context.constants.get(ident.as_str()) {
+        return Ok(val.clone());
+    }
+    if let Some(val) = context.variables.get(ident.as_str()) {
+        if crate::is_builtin_variable(ident.as_str()) {
+            context.result_is_cacheable = false;
+        }
+        return Ok(val.clone());
+    }
+    if context.registered_functions.contains_key(ident.as_str()) {
+        return Ok(Value::RegisteredFunction(ident.as_str().to_string()));
+    }
+    if let Some(val) = resolve_builtin(ident.as_str(), scope, attrs, context, int)? {
+        return Ok(val);
+    }
+    match crate::units::query_unit(ident.as_str(), attrs, context, int) {
+        Err(FendError::IdentifierNotFound(_)) if context.symbolic_identifiers => {
+            Ok(Value::Num(Box::new(Number::new_base_unit(
+                ident.as_str().to_string().into(),
+                ident.as_str().to_string().into(),
+            ))))
+        }
+        result => result,
+    }
+}
+
+/// Resolves built-in functions, constants and keywords, returning `None`
+/// if `name` isn't built in
+#[allow(clippy::too_many_lines)]
+fn resolve_builtin<I: Interrupt>(
+    name: &str,
+    scope: Option<Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Option<Value>, FendError> {
     macro_rules! eval_box {
         ($input:expr) => {
             Box::new(evaluate_to_value(
@@ -578,27 +1305,34 @@ pub(crate) fn resolve_identifier<I: Interrupt>(
             )?)
         };
     }
-    if let Some(scope) = scope.clone() {
-        if let Some(val) = scope.get(ident, attrs, context, int)? {
-            return Ok(val);
-        }
-    }
-    if let Some(val) = context.variables.get(ident.as_str()) {
-        return Ok(val.clone());
-    }
-    Ok(match ident.as_str() {
+    Ok(Some(match name {
         "pi" | "\u{3c0}" => Value::Num(Box::new(Number::pi())),
         "tau" | "\u{3c4}" => Value::Num(Box::new(Number::pi().mul(2.into(), int)?)),
         "e" => evaluate_to_value("approx. 2.718281828459045235", scope, attrs, context, int)?,
         "phi" => evaluate_to_value("(1 + sqrt(5))/2", scope, attrs, context, int)?,
         "i" => Value::Num(Box::new(Number::i())),
-        "true" => Value::Bool(true),
-        "false" => Value::Bool(false),
+        "j" if context.imaginary_unit == crate::ImaginaryUnitSymbol::J => {
+            Value::Num(Box::new(Number::i()))
+        }
         "sample" | "roll" => Value::BuiltInFunction(BuiltInFunction::Sample),
         "sqrt" => evaluate_to_value("x: x^(1/2)", scope, attrs, context, int)?,
         "cbrt" => evaluate_to_value("x: x^(1/3)", scope, attrs, context, int)?,
         "conjugate" => Value::BuiltInFunction(BuiltInFunction::Conjugate),
+        "is_compatible" => Value::BuiltInFunction(BuiltInFunction::IsCompatible),
         "abs" => Value::BuiltInFunction(BuiltInFunction::Abs),
+        "round" => Value::BuiltInFunction(BuiltInFunction::Round),
+        "floor" => Value::BuiltInFunction(BuiltInFunction::Floor),
+        "ceil" => Value::BuiltInFunction(BuiltInFunction::Ceil),
+        "mod" => Value::BuiltInFunction(BuiltInFunction::Mod),
+        "approx" => Value::BuiltInFunction(BuiltInFunction::Approx),
+        "range" => Value::BuiltInFunction(BuiltInFunction::Range),
+        "map" => Value::BuiltInFunction(BuiltInFunction::Map),
+        "filter" => Value::BuiltInFunction(BuiltInFunction::Filter),
+        "mean" | "avg" => Value::BuiltInFunction(BuiltInFunction::Mean),
+        "geometric_mean" => Value::BuiltInFunction(BuiltInFunction::GeometricMean),
+        "harmonic_mean" => Value::BuiltInFunction(BuiltInFunction::HarmonicMean),
+        "sort" => Value::BuiltInFunction(BuiltInFunction::Sort),
+        "inv" | "reciprocal" => Value::BuiltInFunction(BuiltInFunction::Reciprocal),
         "sin" => Value::BuiltInFunction(BuiltInFunction::Sin),
         "cos" => Value::BuiltInFunction(BuiltInFunction::Cos),
         "tan" => Value::BuiltInFunction(BuiltInFunction::Tan),
@@ -629,6 +1363,7 @@ pub(crate) fn resolve_identifier<I: Interrupt>(
         "frac" | "fraction" => Value::Format(FormattingStyle::ImproperFraction),
         "mixed_frac" | "mixed_fraction" => Value::Format(FormattingStyle::MixedFraction),
         "float" => Value::Format(FormattingStyle::ExactFloat),
+        "sci" | "scientific" => Value::Format(FormattingStyle::Scientific),
         "dp" => Value::Dp,
         "sf" => Value::Sf,
         "base" => Value::BuiltInFunction(BuiltInFunction::Base),
@@ -638,7 +1373,7 @@ pub(crate) fn resolve_identifier<I: Interrupt>(
         "ternary" => Value::Base(Base::from_plain_base(3)?),
         "senary" | "seximal" => Value::Base(Base::from_plain_base(6)?),
         "oct" | "octal" => Value::Base(Base::from_plain_base(8)?),
-        "version" => Value::String(crate::get_version_as_str().into()),
+        "version" => Value::String(crate::get_version().into()),
         "square" => evaluate_to_value("x: x^2", scope, attrs, context, int)?,
         "cubic" => evaluate_to_value("x: x^3", scope, attrs, context, int)?,
         "earth" => Value::Object(vec![
@@ -652,6 +1387,24 @@ pub(crate) fn resolve_identifier<I: Interrupt>(
         "today" => Value::Date(crate::date::Date::today(context)?),
         "tomorrow" => Value::Date(crate::date::Date::today(context)?.next()),
         "yesterday" => Value::Date(crate::date::Date::today(context)?.prev()),
-        _ => return crate::units::query_unit(ident.as_str(), attrs, context, int),
-    })
+        _ => return Ok(None),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_function_names_resolve() {
+        let mut context = crate::Context::new();
+        let int = crate::interrupt::Never::default();
+        for &name in BUILTIN_FUNCTION_NAMES {
+            let value = resolve_builtin(name, None, Attrs::default(), &mut context, &int);
+            assert!(
+                matches!(value, Ok(Some(_))),
+                "built-in function `{name}` is not resolved by `resolve_builtin`"
+            );
+        }
+    }
 }
@@ -22,6 +22,7 @@ pub(crate) struct Date {
 
 impl Date {
     pub(crate) fn today(context: &mut crate::Context) -> Result<Self, FendError> {
+        context.result_is_cacheable = false;
         let current_time_info = if let Some(t) = &context.current_time {
             t
         } else {
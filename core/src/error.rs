@@ -12,7 +12,10 @@ pub(crate) enum FendError {
     UnableToConvertToBase,
     DivideByZero,
     ExponentTooLarge,
-    ZeroToThePowerOfZero,
+    DurationTooLargeForHms,
+    NonIntegerDimension,
+    NumberTooLargeToApproximate,
+    UndefinedResult,
     FactorialComplex,
     DeserializationError,
     Wrap(Box<dyn error::Error + Send + Sync + 'static>),
@@ -25,20 +28,34 @@ pub(crate) enum FendError {
     ProbabilityDistributionsNotAllowed,
     FractionToInteger,
     ModuloByZero,
+    RoundingToZeroIncrement,
+    ZeroMaxDenominator,
+    ExpectedAList,
+    EmptyMean(&'static str),
+    ExpectedNumbersToSort,
+    IncompatibleIfBranches,
+    ExpectedPositiveNumbers(&'static str),
+    ListTooLong(usize),
+    CannotMakeExact,
     RandomNumbersNotAvailable,
     MustBeAnInteger(Box<dyn crate::format::DisplayDebug>),
     ExpectedARationalNumber,
     CannotConvertToInteger,
     ComplexToInteger,
     NumberWithUnitToInt,
+    NumberTooLargeForInteger,
     InexactNumberToInt,
     ExpectedANumber,
+    BoolUsedAsNumber,
     ExpectedABool(&'static str),
     InvalidDiceSyntax,
     SpecifyNumDp,
     SpecifyNumSf,
     UnableToInvertFunction(&'static str),
     InvalidOperandsForSubtraction,
+    InvalidOperandsForComparison,
+    BoolComparedWithNumber,
+    ComplexComparison,
     InversesOfLambdasUnsupported,
     CouldNotFindKeyInObject,
     CouldNotFindKey(String),
@@ -57,6 +74,7 @@ pub(crate) enum FendError {
     DigitSeparatorsOnlyBetweenDigits,
     InvalidCharAtBeginningOfIdent(char),
     UnexpectedChar(char),
+    InfinityNotSupported,
     UnterminatedStringLiteral,
     UnknownBackslashEscapeSequence(char),
     BackslashXOutOfRange,
@@ -72,10 +90,13 @@ pub(crate) enum FendError {
     ExpComplex,
     ExpectedARealNumber,
     ConversionRhsNumerical,
+    SubtractAbsoluteTemperatureFromDifference,
+    ConvertTemperatureDifferenceToAbsolute,
     FactorialUnitless,
     RootsComplex,
     ModuloForPositiveInts,
     ExpUnitless,
+    FractionalUnitExponent,
     IncompatibleConversion {
         from: String,
         to: String,
@@ -86,6 +107,28 @@ pub(crate) enum FendError {
     RootsOfNegativeNumbers,
     NonIntegerNegRoots,
     CannotConvertValueTo(&'static str),
+    InvalidUnitDefinition,
+    PrefixAlreadyDefined(String),
+    CannotReassignConstant(String),
+    RecursiveUnitDefinition(String),
+    InvalidPrefix {
+        name: String,
+        error: Box<Self>,
+    },
+    InvalidDigitSeparator(char),
+    InvalidDecimalSeparator(char),
+    SameDigitAndDecimalSeparator(char),
+    ZeroIntegerDigitLimit,
+    NonFiniteFunctionResult(String),
+    WrongNumberOfArguments {
+        function: &'static str,
+        expected: usize,
+    },
+    // a syntax error, along with its byte offset in the input
+    AtPosition {
+        error: Box<Self>,
+        position: usize,
+    },
 }
 
 impl fmt::Display for FendError {
@@ -94,6 +137,7 @@ impl fmt::Display for FendError {
         match self {
             Self::Interrupted => write!(f, "interrupted"),
             Self::ParseError(e) => write!(f, "{e}"),
+            Self::AtPosition { error, .. } => write!(f, "{error}"),
             Self::FactorialUnitless => {
                 write!(f, "factorial is only supported for unitless numbers")
             }
@@ -103,6 +147,9 @@ impl fmt::Display for FendError {
             Self::RootsComplex => write!(f, "roots are currently unsupported for complex numbers"),
             Self::ExpComplex => write!(f, "exponentiation is not supported for complex numbers"),
             Self::ExpUnitless => write!(f, "exponentiation is only supported for unitless numbers"),
+            Self::FractionalUnitExponent => {
+                write!(f, "units can only be raised to integer powers")
+            }
             Self::IoError(_) => write!(f, "I/O error"),
             Self::InvalidBasePrefix => write!(
                 f,
@@ -128,20 +175,86 @@ impl fmt::Display for FendError {
                 write!(f, "modulo is only supported for positive integers")
             }
             Self::CannotConvertValueTo(ty) => write!(f, "cannot convert value to {ty}"),
+            Self::InvalidUnitDefinition => write!(
+                f,
+                "invalid unit definition, expected e.g. `unit smoot smoots = 1.7018 m`"
+            ),
+            Self::PrefixAlreadyDefined(name) => {
+                write!(f, "'{name}' is already defined as a unit or prefix")
+            }
+            Self::CannotReassignConstant(name) => {
+                write!(f, "cannot assign to '{name}' because it is a constant")
+            }
+            Self::InvalidPrefix { name, error } => {
+                write!(f, "the prefix '{name}' could not be evaluated: {error}")
+            }
+            Self::RecursiveUnitDefinition(name) => {
+                write!(f, "the unit '{name}' is defined in terms of itself")
+            }
+            Self::InvalidDigitSeparator(ch) => {
+                write!(f, "'{ch}' cannot be used as a digit separator")
+            }
+            Self::InvalidDecimalSeparator(ch) => {
+                write!(f, "'{ch}' cannot be used as a decimal separator")
+            }
+            Self::SameDigitAndDecimalSeparator(ch) => write!(
+                f,
+                "'{ch}' cannot be used as both the digit separator and the decimal separator"
+            ),
+            Self::ZeroIntegerDigitLimit => {
+                write!(f, "the integer digit limit must be at least 1")
+            }
+            Self::NonFiniteFunctionResult(name) => {
+                write!(f, "function '{name}' returned a non-finite value")
+            }
+            Self::WrongNumberOfArguments { function, expected } => {
+                write!(f, "'{function}' expects {expected} arguments")
+            }
             Self::BaseTooSmall => write!(f, "base must be at least 2"),
             Self::ConversionRhsNumerical => write!(
                 f,
                 "right-hand side of unit conversion has a numerical value"
             ),
+            Self::SubtractAbsoluteTemperatureFromDifference => write!(
+                f,
+                "cannot subtract an absolute temperature from a temperature difference"
+            ),
+            Self::ConvertTemperatureDifferenceToAbsolute => write!(
+                f,
+                "cannot convert a temperature difference to an absolute temperature (use e.g. \u{394}\u{b0}F or K instead)"
+            ),
             Self::BaseTooLarge => write!(f, "base cannot be larger than 36"),
             Self::UnableToConvertToBase => write!(f, "unable to convert number to a valid base"),
             Self::DivideByZero => write!(f, "division by zero"),
             Self::ExponentTooLarge => write!(f, "exponent too large"),
-            Self::ZeroToThePowerOfZero => write!(f, "zero to the power of zero is undefined"),
+            Self::DurationTooLargeForHms => {
+                write!(f, "duration is too large to be shown as hours, minutes and seconds")
+            }
+            Self::NonIntegerDimension => {
+                write!(f, "dimensions with non-integer exponents are not supported")
+            }
+            Self::NumberTooLargeToApproximate => write!(f, "number is too large to approximate"),
+            Self::UndefinedResult => write!(f, "result is undefined"),
             Self::OutOfRange { range, value } => {
                 write!(f, "{value} must lie in the interval {range}")
             }
             Self::ModuloByZero => write!(f, "modulo by zero"),
+            Self::RoundingToZeroIncrement => write!(f, "cannot round to an increment of zero"),
+            Self::ZeroMaxDenominator => write!(f, "the maximum denominator must be at least 1"),
+            Self::ExpectedAList => write!(f, "expected a list"),
+            Self::EmptyMean(func) => write!(f, "cannot take the {func} of an empty list"),
+            Self::ExpectedNumbersToSort => write!(f, "sort expects one or more numbers"),
+            Self::IncompatibleIfBranches => write!(
+                f,
+                "both branches of an if expression must have compatible types and units"
+            ),
+            Self::ExpectedPositiveNumbers(func) => {
+                write!(f, "{func} is only defined for positive numbers")
+            }
+            Self::ListTooLong(max) => write!(f, "lists can contain at most {max} items"),
+            Self::CannotMakeExact => {
+                write!(f, "cannot make an approximate value exact")
+            }
             Self::SpecifyNumDp => write!(
                 f,
                 "you need to specify what number of decimal places to use, e.g. '10 dp'"
@@ -179,10 +292,15 @@ impl fmt::Display for FendError {
             Self::CannotConvertToInteger => write!(f, "number cannot be converted to an integer"),
             Self::ComplexToInteger => write!(f, "cannot convert complex number to integer"),
             Self::NumberWithUnitToInt => write!(f, "cannot convert number with unit to integer"),
+            Self::NumberTooLargeForInteger => write!(f, "number is too large for an integer"),
             Self::InexactNumberToInt => write!(f, "cannot convert inexact number to integer"),
             Self::ExpectedANumber => write!(f, "expected a number"),
+            Self::BoolUsedAsNumber => write!(f, "expected a number (found bool)"),
             Self::InvalidDiceSyntax => write!(f, "invalid dice syntax, try e.g. `4d6`"),
             Self::InvalidOperandsForSubtraction => write!(f, "invalid operands for subtraction"),
+            Self::InvalidOperandsForComparison => write!(f, "invalid operands for comparison"),
+            Self::BoolComparedWithNumber => write!(f, "cannot compare a bool with a number"),
+            Self::ComplexComparison => write!(f, "complex numbers cannot be ordered"),
             Self::CannotFormatWithZeroSf => {
                 write!(f, "cannot format a number with zero significant figures")
             }
@@ -203,6 +321,7 @@ impl fmt::Display for FendError {
                 write!(f, "'{ch}' is not valid at the beginning of an identifier")
             }
             Self::UnexpectedChar(ch) => write!(f, "unexpected character '{ch}'"),
+            Self::InfinityNotSupported => write!(f, "infinity is not a representable value"),
             Self::UnterminatedStringLiteral => write!(f, "unterminated string literal"),
             Self::UnknownBackslashEscapeSequence(ch) => {
                 write!(f, "unknown escape sequence: \\{ch}")
@@ -229,6 +348,32 @@ impl fmt::Display for FendError {
     }
 }
 
+impl FendError {
+    pub(crate) fn at_position(self, position: usize) -> Self {
+        match self {
+            Self::Interrupted | Self::AtPosition { .. } => self,
+            error => Self::AtPosition {
+                error: Box::new(error),
+                position,
+            },
+        }
+    }
+
+    pub(crate) fn position(&self) -> Option<usize> {
+        match self {
+            Self::AtPosition { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn without_position(self) -> Self {
+        match self {
+            Self::AtPosition { error, .. } => *error,
+            error => error,
+        }
+    }
+}
+
 impl error::Error for FendError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
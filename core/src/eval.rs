@@ -1,37 +1,130 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::{
-    ast,
+    ast::{self, Expr},
     error::{FendError, Interrupt},
     lexer, parser,
     scope::Scope,
     value::Value,
-    Span,
+    Diagnostics, Span,
 };
 
-pub(crate) fn evaluate_to_value<'a, I: Interrupt>(
-    input: &'a str,
-    scope: Option<Arc<Scope>>,
-    attrs: Attrs,
-    context: &mut crate::Context,
+/// Returns the tokens along with their byte offsets in the input
+fn lex<I: Interrupt>(
+    input: &str,
+    context: &crate::Context,
     int: &I,
-) -> Result<Value, FendError> {
-    let lex = lexer::lex(input, int);
+) -> Result<(Vec<lexer::Token>, Vec<usize>), FendError> {
+    let lex = lexer::lex(input, context.space_digit_separator, int);
     let mut tokens = vec![];
+    let mut positions = vec![];
     let mut missing_open_parens: i32 = 0;
+    let mut open_parens: i32 = 0;
     for token in lex {
-        let token = token?;
-        if let lexer::Token::Symbol(lexer::Symbol::CloseParens) = token {
-            missing_open_parens += 1;
+        let (token, position) = token?;
+        match token {
+            lexer::Token::Symbol(lexer::Symbol::OpenParens) => open_parens += 1,
+            lexer::Token::Symbol(lexer::Symbol::CloseParens) if open_parens > 0 => {
+                open_parens -= 1;
+            }
+            lexer::Token::Symbol(lexer::Symbol::CloseParens) => missing_open_parens += 1,
+            _ => (),
         }
         tokens.push(token);
+        positions.push(position);
     }
     for _ in 0..missing_open_parens {
         tokens.insert(0, lexer::Token::Symbol(lexer::Symbol::OpenParens));
+        positions.insert(0, 0);
+    }
+    Ok((tokens, positions))
+}
+
+fn parse<I: Interrupt>(
+    tokens: &[lexer::Token],
+    positions: &[usize],
+    input: &str,
+    int: &I,
+) -> Result<Expr, FendError> {
+    parser::parse_tokens(tokens, int).map_err(|e| match e.remaining_tokens() {
+        Some(remaining) => {
+            let position = positions
+                .get(tokens.len() - remaining)
+                .copied()
+                .unwrap_or(input.len());
+            FendError::from(e).at_position(position)
+        }
+        None => e.into(),
+    })
+}
+
+pub(crate) fn evaluate_to_value<'a, I: Interrupt>(
+    input: &'a str,
+    scope: Option<Arc<Scope>>,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Value, FendError> {
+    // error positions would refer to `input` rather than what the user
+    // entered, so they're removed here
+    let (tokens, positions) = lex(input, context, int).map_err(FendError::without_position)?;
+    let parsed = parse(&tokens, &positions, input, int).map_err(FendError::without_position)?;
+    // this is used for built-in definitions, which shouldn't show up
+    // as steps when explaining an evaluation
+    let steps = context.explanation.take();
+    let result = ast::evaluate(parsed, scope, attrs, context, int);
+    context.explanation = steps;
+    result
+}
+
+/// Parses `input` and formats it again (see `Context::canonical_input`)
+pub(crate) fn canonical_input<I: Interrupt>(
+    input: &str,
+    context: &crate::Context,
+    int: &I,
+) -> Result<String, FendError> {
+    let (attrs, expr) = parse_attrs(input);
+    let (tokens, positions) = lex(expr, context, int)?;
+    parse(&tokens, &positions, expr, int)?.format_canonical(attrs, context, int)
+}
+
+/// Evaluates `input` while recording the steps taken (see `Context::explain`)
+pub(crate) fn explain<I: Interrupt>(
+    input: &str,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<crate::Explanation, FendError> {
+    let (attrs, expr) = parse_attrs(input);
+    let (tokens, positions) = lex(expr, context, int)?;
+    let parsed = parse(&tokens, &positions, expr, int)?.format(attrs, context, int)?;
+    context.explanation = Some(vec![]);
+    let result = evaluate_to_spans(input, None, context, int, None);
+    let steps = context.explanation.take().unwrap_or_default();
+    let result = result?.0.into_iter().map(|span| span.string).collect();
+    Ok(crate::Explanation {
+        parsed,
+        steps,
+        result,
+    })
+}
+
+/// Runs `f`, adding the elapsed time to the selected field of `diagnostics`
+/// (if diagnostics are being collected)
+fn measure<T>(
+    diagnostics: &mut Option<&mut Diagnostics>,
+    field: impl FnOnce(&mut Diagnostics) -> &mut Duration,
+    f: impl FnOnce() -> T,
+) -> T {
+    match diagnostics {
+        None => f(),
+        Some(diagnostics) => {
+            let start = Instant::now();
+            let result = f();
+            *field(diagnostics) += start.elapsed();
+            result
+        }
     }
-    let parsed = parser::parse_tokens(&tokens)?;
-    let result = ast::evaluate(parsed, scope, attrs, context, int)?;
-    Ok(result)
 }
 
 #[derive(Clone, Copy)]
@@ -41,12 +134,18 @@ pub(crate) struct Attrs {
     pub(crate) plain_number: bool,
 }
 
+impl Default for Attrs {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            show_approx: true,
+            plain_number: false,
+        }
+    }
+}
+
 fn parse_attrs(mut input: &str) -> (Attrs, &str) {
-    let mut attrs = Attrs {
-        debug: false,
-        show_approx: true,
-        plain_number: false,
-    };
+    let mut attrs = Attrs::default();
     while input.starts_with('@') {
         if let Some(remaining) = input.strip_prefix("@debug ") {
             attrs.debug = true;
@@ -64,25 +163,87 @@ fn parse_attrs(mut input: &str) -> (Attrs, &str) {
     (attrs, input)
 }
 
+/// Evaluates the input only for its side effects (e.g. variable
+/// assignments). Unlike `evaluate_to_spans`, this does not update `_` or `ans`.
+pub(crate) fn evaluate_for_side_effects<I: Interrupt>(
+    input: &str,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<(), FendError> {
+    let (attrs, input) = parse_attrs(input);
+    evaluate_to_value(input, None, attrs, context, int)?;
+    Ok(())
+}
+
+/// Converts a result to its preferred unit (e.g. `N m` to `J`), unless
+/// automatic simplification is disabled.
+pub(crate) fn simplify_result<I: Interrupt>(
+    value: Value,
+    attrs: Attrs,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Value, FendError> {
+    Ok(match value {
+        Value::Num(n) if context.auto_simplify => {
+            Value::Num(Box::new(n.use_preferred_unit(attrs, context, int)?))
+        }
+        value => value,
+    })
+}
+
 /// This also saves the calculation result in a variable `_` and `ans`
 pub(crate) fn evaluate_to_spans<'a, I: Interrupt>(
     input: &'a str,
     scope: Option<Arc<Scope>>,
     context: &mut crate::Context,
     int: &I,
-) -> Result<(Vec<Span>, bool), FendError> {
+    mut diagnostics: Option<&mut Diagnostics>,
+) -> Result<(Vec<Span>, bool, bool), FendError> {
+    let input_len = input.len();
     let (attrs, input) = parse_attrs(input);
-    let value = evaluate_to_value(input, scope, attrs, context, int)?;
+    // error positions need to include the length of any attributes
+    let adjust_position = |e: FendError| match e.position() {
+        Some(position) => {
+            let attrs_len = input_len - input.len();
+            e.without_position().at_position(attrs_len + position)
+        }
+        None => e,
+    };
+    let (tokens, positions) = measure(
+        &mut diagnostics,
+        |d| &mut d.lex_time,
+        || lex(input, context, int),
+    )
+    .map_err(adjust_position)?;
+    if let Some(diagnostics) = &mut diagnostics {
+        diagnostics.token_count = tokens.len();
+    }
+    let parsed = measure(
+        &mut diagnostics,
+        |d| &mut d.parse_time,
+        || parse(&tokens, &positions, input, int),
+    )
+    .map_err(adjust_position)?;
+    let value = measure(
+        &mut diagnostics,
+        |d| &mut d.eval_time,
+        || ast::evaluate(parsed, scope, attrs, context, int),
+    )?;
+    let value = simplify_result(value, attrs, context, int)?;
     context.variables.insert("_".to_string(), value.clone());
     context.variables.insert("ans".to_string(), value.clone());
-    Ok((
-        if attrs.debug {
-            vec![Span::from_string(format!("{:?}", value))]
-        } else {
-            let mut spans = vec![];
-            value.format(0, &mut spans, attrs, context, int)?;
-            spans
+    let (spans, is_approximate) = measure(
+        &mut diagnostics,
+        |d| &mut d.format_time,
+        || -> Result<_, FendError> {
+            Ok(if attrs.debug {
+                (vec![Span::from_string(format!("{:?}", value))], false)
+            } else {
+                let mut spans = vec![];
+                let is_approximate = value.format(0, &mut spans, attrs, context, int)?;
+                (spans, is_approximate)
+            })
         },
-        value.is_unit(),
-    ))
+    )?;
+    Ok((spans, value.is_unit(), is_approximate))
 }
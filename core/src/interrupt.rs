@@ -2,6 +2,48 @@ use crate::error::FendError;
 
 pub trait Interrupt {
     fn should_interrupt(&self) -> bool;
+
+    /// Called periodically during long computations, such as printing many
+    /// digits or computing large factorials, so that frontends can e.g.
+    /// show a progress bar. This may be called very frequently, so it
+    /// should return quickly. The default implementation does nothing.
+    fn report_progress(&self, progress: Progress) {
+        let _ = progress;
+    }
+}
+
+/// How far along a long computation is, as reported to
+/// [`Interrupt::report_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    step: u64,
+    total: Option<u64>,
+}
+
+impl Progress {
+    /// The number of steps completed so far
+    #[must_use]
+    pub fn step(self) -> u64 {
+        self.step
+    }
+
+    /// The total number of steps, if known in advance
+    #[must_use]
+    pub fn total(self) -> Option<u64> {
+        self.total
+    }
+
+    /// The fraction of steps completed so far (between 0 and 1), if the
+    /// total number of steps is known
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fraction(self) -> Option<f64> {
+        let total = self.total?;
+        if total == 0 {
+            return Some(1.0);
+        }
+        Some((self.step as f64 / total as f64).min(1.0))
+    }
 }
 
 pub(crate) fn test_int<I: crate::error::Interrupt>(int: &I) -> Result<(), FendError> {
@@ -12,6 +54,16 @@ pub(crate) fn test_int<I: crate::error::Interrupt>(int: &I) -> Result<(), FendEr
     }
 }
 
+/// Checks for interrupts like [`test_int`], and also reports progress
+pub(crate) fn test_int_with_progress<I: crate::error::Interrupt>(
+    int: &I,
+    step: u64,
+    total: Option<u64>,
+) -> Result<(), FendError> {
+    int.report_progress(Progress { step, total });
+    test_int(int)
+}
+
 #[derive(Default)]
 pub(crate) struct Never {}
 impl Interrupt for Never {
@@ -19,6 +19,7 @@ pub(crate) enum Symbol {
     Sub,
     Mul,
     Div,
+    IntDiv,
     Mod,
     Pow,
     BitwiseAnd,
@@ -35,6 +36,20 @@ pub(crate) enum Symbol {
     Semicolon,
     Equals, // used for assignment
     Combination,
+    Comma,
+    Sqrt,
+    CubeRoot,
+    FourthRoot,
+    Per,
+    DoubleEquals,
+    NotEquals,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    If,
+    Then,
+    Else,
 }
 
 impl fmt::Display for Symbol {
@@ -46,6 +61,7 @@ impl fmt::Display for Symbol {
             Self::Sub => "-",
             Self::Mul => "*",
             Self::Div => "/",
+            Self::IntDiv => "//",
             Self::Mod => "mod",
             Self::Pow => "^",
             Self::BitwiseAnd => "&",
@@ -62,6 +78,20 @@ impl fmt::Display for Symbol {
             Self::Semicolon => ";",
             Self::Equals => "=",
             Self::Combination => "nCr",
+            Self::Comma => ",",
+            Self::Sqrt => "\u{221a}",
+            Self::CubeRoot => "\u{221b}",
+            Self::FourthRoot => "\u{221c}",
+            Self::Per => "per",
+            Self::DoubleEquals => "==",
+            Self::NotEquals => "!=",
+            Self::LessThan => "<",
+            Self::GreaterThan => ">",
+            Self::LessThanOrEqual => "<=",
+            Self::GreaterThanOrEqual => ">=",
+            Self::If => "if",
+            Self::Then => "then",
+            Self::Else => "else",
         };
         write!(f, "{s}")?;
         Ok(())
@@ -97,30 +127,74 @@ fn parse_fixed_char(input: &str, ch: char) -> Result<((), &str), FendError> {
     }
 }
 
-fn parse_digit_separator(input: &str) -> Result<((), &str), FendError> {
+fn parse_digit_separator(input: &str) -> Result<(char, &str), FendError> {
     let (parsed_ch, input) = parse_char(input)?;
     if parsed_ch == '_' || parsed_ch == ',' {
-        Ok(((), input))
+        Ok((parsed_ch, input))
     } else {
         Err(FendError::ExpectedDigitSeparator(parsed_ch))
     }
 }
 
-// Parses a plain integer with no whitespace and no base prefix.
-// Leading minus sign is not allowed.
+// Parses a space between digit groups, as in `1 000 000`. The space
+// needs to be followed by a group of exactly three digits, so that e.g.
+// `1 2` is still parsed as two separate numbers. This is only supported
+// in base 10.
+fn parse_space_digit_separator(input: &str, base: Base) -> Option<&str> {
+    if base.base_as_u8() != 10 {
+        return None;
+    }
+    let (ch, remaining) = parse_char(input).ok()?;
+    if ch != ' ' && ch != '\u{a0}' && ch != '\u{202f}' {
+        return None;
+    }
+    if !is_digit_group(remaining, base) {
+        return None;
+    }
+    Some(remaining)
+}
+
+// Returns true if the input starts with exactly three digits
+fn is_digit_group(input: &str, base: Base) -> bool {
+    let mut group = input;
+    for _ in 0..3 {
+        match parse_ascii_digit(group, base) {
+            Ok((_, remaining)) => group = remaining,
+            Err(_) => return false,
+        }
+    }
+    parse_ascii_digit(group, base).is_err()
+}
+
+// Parses a plain integer with no whitespace (unless `allow_space_separator`
+// is set) and no base prefix. Leading minus sign is not allowed.
 fn parse_integer<'a, E: From<FendError>>(
     input: &'a str,
     allow_digit_separator: bool,
+    allow_space_separator: bool,
     base: Base,
     process_digit: &mut impl FnMut(u8) -> Result<(), E>,
 ) -> Result<((), &'a str), E> {
     let (digit, mut input) = parse_ascii_digit(input, base)?;
     process_digit(digit)?;
     let mut parsed_digit_separator;
+    let mut group_len = 1;
     loop {
-        if let Ok((_, remaining)) = parse_digit_separator(input) {
+        if let Some(remaining) = parse_space_digit_separator(input, base)
+            .filter(|_| allow_space_separator && group_len <= 3)
+        {
             input = remaining;
             parsed_digit_separator = true;
+            group_len = 0;
+        } else if let Ok((separator, remaining)) = parse_digit_separator(input) {
+            // a comma that isn't followed by a group of three digits
+            // separates function arguments, as in `mean(1,2,3)`
+            if separator == ',' && !is_digit_group(remaining, base) {
+                break;
+            }
+            input = remaining;
+            parsed_digit_separator = true;
+            group_len = 0;
             if !allow_digit_separator {
                 return Err(FendError::DigitSeparatorsNotAllowed.into());
             }
@@ -137,6 +211,7 @@ fn parse_integer<'a, E: From<FendError>>(
             Ok((digit, next_input)) => {
                 process_digit(digit)?;
                 input = next_input;
+                group_len += 1;
             }
         }
     }
@@ -154,20 +229,23 @@ fn parse_base_prefix(input: &str) -> Result<(Base, &str), FendError> {
         Ok((Base::from_zero_based_prefix_char(ch)?, input))
     } else {
         let mut custom_base: u8 = 0;
-        let (_, input) = parse_integer(input, false, Base::default(), &mut |digit| -> Result<
-            (),
-            FendError,
-        > {
-            let error = FendError::BaseTooLarge;
-            if custom_base > 3 {
-                return Err(error);
-            }
-            custom_base = 10 * custom_base + digit;
-            if custom_base > 36 {
-                return Err(error);
-            }
-            Ok(())
-        })?;
+        let (_, input) = parse_integer(
+            input,
+            false,
+            false,
+            Base::default(),
+            &mut |digit| -> Result<(), FendError> {
+                let error = FendError::BaseTooLarge;
+                if custom_base > 3 {
+                    return Err(error);
+                }
+                custom_base = 10 * custom_base + digit;
+                if custom_base > 36 {
+                    return Err(error);
+                }
+                Ok(())
+            },
+        )?;
         if custom_base < 2 {
             return Err(FendError::BaseTooSmall);
         }
@@ -201,15 +279,21 @@ fn parse_recurring_digits<'a, I: Interrupt>(
     let mut recurring_number_num = Number::from(0);
     let mut recurring_number_den = Number::from(1);
     let base_as_u64 = u64::from(base.base_as_u8());
-    let (_, input) = parse_integer(input, true, base, &mut |digit| -> Result<(), FendError> {
-        let digit_as_u64 = u64::from(digit);
-        recurring_number_num = recurring_number_num
-            .clone()
-            .mul(base_as_u64.into(), int)?
-            .add(digit_as_u64.into(), int)?;
-        recurring_number_den = recurring_number_den.clone().mul(base_as_u64.into(), int)?;
-        Ok(())
-    })?;
+    let (_, input) = parse_integer(
+        input,
+        true,
+        false,
+        base,
+        &mut |digit| -> Result<(), FendError> {
+            let digit_as_u64 = u64::from(digit);
+            recurring_number_num = recurring_number_num
+                .clone()
+                .mul(base_as_u64.into(), int)?
+                .add(digit_as_u64.into(), int)?;
+            recurring_number_den = recurring_number_den.clone().mul(base_as_u64.into(), int)?;
+            Ok(())
+        },
+    )?;
     recurring_number_den = recurring_number_den.clone().sub(1.into(), int)?;
     for _ in 0..num_nonrec_digits {
         recurring_number_den = recurring_number_den.clone().mul(base_as_u64.into(), int)?;
@@ -226,6 +310,7 @@ fn parse_recurring_digits<'a, I: Interrupt>(
 fn parse_basic_number<'a, I: Interrupt>(
     mut input: &'a str,
     base: Base,
+    space_digit_separator: bool,
     int: &I,
 ) -> Result<(Number, &'a str), FendError> {
     let mut is_dice_with_no_count = false;
@@ -244,14 +329,19 @@ fn parse_basic_number<'a, I: Interrupt>(
     let mut is_integer = true;
 
     if parse_fixed_char(input, '.').is_err() && !is_dice_with_no_count {
-        let (_, remaining) =
-            parse_integer(input, true, base, &mut |digit| -> Result<(), FendError> {
+        let (_, remaining) = parse_integer(
+            input,
+            true,
+            space_digit_separator,
+            base,
+            &mut |digit| -> Result<(), FendError> {
                 res = res
                     .clone()
                     .mul(base_as_u64.into(), int)?
                     .add(u64::from(digit).into(), int)?;
                 Ok(())
-            })?;
+            },
+        )?;
         input = remaining;
     }
 
@@ -262,18 +352,19 @@ fn parse_basic_number<'a, I: Interrupt>(
         let mut numerator = Number::zero_with_base(base);
         let mut denominator = Number::zero_with_base(base).add(1.into(), int)?;
         if parse_fixed_char(remaining, '(').is_err() {
-            let (_, remaining) = parse_integer(remaining, true, base, &mut |digit| -> Result<
-                (),
-                FendError,
-            > {
-                numerator = numerator
-                    .clone()
-                    .mul(base_as_u64.into(), int)?
-                    .add(u64::from(digit).into(), int)?;
-                denominator = denominator.clone().mul(base_as_u64.into(), int)?;
-                num_nonrec_digits += 1;
-                Ok(())
-            })?;
+            let (_, remaining) =
+                parse_integer(remaining, true, false, base, &mut |digit| -> Result<
+                    (),
+                    FendError,
+                > {
+                    numerator = numerator
+                        .clone()
+                        .mul(base_as_u64.into(), int)?
+                        .add(u64::from(digit).into(), int)?;
+                    denominator = denominator.clone().mul(base_as_u64.into(), int)?;
+                    num_nonrec_digits += 1;
+                    Ok(())
+                })?;
             input = remaining;
         } else {
             input = remaining;
@@ -297,19 +388,18 @@ fn parse_basic_number<'a, I: Interrupt>(
                         .map_err(|_| FendError::InvalidDiceSyntax)?
                 };
                 let mut face_count = 0_u32;
-                let (_, remaining2) = parse_integer(
-                    remaining,
-                    false,
-                    base,
-                    &mut |digit| -> Result<(), FendError> {
+                let (_, remaining2) =
+                    parse_integer(remaining, false, false, base, &mut |digit| -> Result<
+                        (),
+                        FendError,
+                    > {
                         face_count = face_count
                             .checked_mul(base.base_as_u8().into())
                             .ok_or(FendError::InvalidDiceSyntax)?
                             .checked_add(digit.into())
                             .ok_or(FendError::InvalidDiceSyntax)?;
                         Ok(())
-                    },
-                )?;
+                    })?;
                 if dice_count == 0 || face_count == 0 {
                     return Err(FendError::InvalidDiceSyntax);
                 }
@@ -354,12 +444,17 @@ fn parse_basic_number<'a, I: Interrupt>(
                 }
                 let mut exp = Number::zero_with_base(base);
                 let base_num = Number::from(u64::from(base.base_as_u8()));
-                let (_, remaining2) =
-                    parse_integer(input, true, base, &mut |digit| -> Result<(), FendError> {
+                let (_, remaining2) = parse_integer(
+                    input,
+                    true,
+                    false,
+                    base,
+                    &mut |digit| -> Result<(), FendError> {
                         exp = (exp.clone().mul(base_num.clone(), int)?)
                             .add(u64::from(digit).into(), int)?;
                         Ok(())
-                    })?;
+                    },
+                )?;
                 if negative_exponent {
                     exp = -exp;
                 }
@@ -373,23 +468,27 @@ fn parse_basic_number<'a, I: Interrupt>(
     Ok((res, input))
 }
 
-fn parse_number<'a, I: Interrupt>(input: &'a str, int: &I) -> Result<(Number, &'a str), FendError> {
+fn parse_number<'a, I: Interrupt>(
+    input: &'a str,
+    space_digit_separator: bool,
+    int: &I,
+) -> Result<(Number, &'a str), FendError> {
     let (base, input) = parse_base_prefix(input).unwrap_or((Base::default(), input));
-    let (res, input) = parse_basic_number(input, base, int)?;
+    let (res, input) = parse_basic_number(input, base, space_digit_separator, int)?;
     Ok((res, input))
 }
 
 fn is_valid_in_ident(ch: char, prev: Option<char>) -> bool {
     let allowed_chars = [
-        ',', '_', '⅛', '¼', '⅜', '½', '⅝', '¾', '⅞', '⅙', '⅓', '⅔', '⅚', '⅕', '⅖', '⅗', '⅘', '°',
-        '$', '℃', '℉', '℧', '℈', '℥', '℔', '¢', '£', '¥', '€', '₩', '₪', '₤', '₨', '฿', '₡', '₣',
-        '₦', '₧', '₫', '₭', '₮', '₯', '₱', '﷼', '﹩', '￠', '￡', '￥', '￦', '㍱', '㍲', '㍳',
-        '㍴', '㍶', '㎀', '㎁', '㎂', '㎃', '㎄', '㎅', '㎆', '㎇', '㎈', '㎉', '㎊', '㎋', '㎌',
-        '㎍', '㎎', '㎏', '㎐', '㎑', '㎒', '㎓', '㎔', '㎕', '㎖', '㎗', '㎘', '㎙', '㎚', '㎛',
-        '㎜', '㎝', '㎞', '㎟', '㎠', '㎡', '㎢', '㎣', '㎤', '㎥', '㎦', '㎧', '㎨', '㎩', '㎪',
-        '㎫', '㎬', '㎭', '㎮', '㎯', '㎰', '㎱', '㎲', '㎳', '㎴', '㎵', '㎶', '㎷', '㎸', '㎹',
-        '㎺', '㎻', '㎼', '㎽', '㎾', '㎿', '㏀', '㏁', '㏃', '㏄', '㏅', '㏆', '㏈', '㏉', '㏊',
-        '㏌', '㏏', '㏐', '㏓', '㏔', '㏕', '㏖', '㏗', '㏙', '㏛', '㏜', '㏝',
+        '_', '⅛', '¼', '⅜', '½', '⅝', '¾', '⅞', '⅙', '⅓', '⅔', '⅚', '⅕', '⅖', '⅗', '⅘', '°', '$',
+        '℃', '℉', '℧', '℈', '℥', '℔', '¢', '£', '¥', '€', '₩', '₪', '₤', '₨', '฿', '₡', '₣', '₦',
+        '₧', '₫', '₭', '₮', '₯', '₱', '﷼', '﹩', '￠', '￡', '￥', '￦', '㍱', '㍲', '㍳', '㍴',
+        '㍶', '㎀', '㎁', '㎂', '㎃', '㎄', '㎅', '㎆', '㎇', '㎈', '㎉', '㎊', '㎋', '㎌', '㎍',
+        '㎎', '㎏', '㎐', '㎑', '㎒', '㎓', '㎔', '㎕', '㎖', '㎗', '㎘', '㎙', '㎚', '㎛', '㎜',
+        '㎝', '㎞', '㎟', '㎠', '㎡', '㎢', '㎣', '㎤', '㎥', '㎦', '㎧', '㎨', '㎩', '㎪', '㎫',
+        '㎬', '㎭', '㎮', '㎯', '㎰', '㎱', '㎲', '㎳', '㎴', '㎵', '㎶', '㎷', '㎸', '㎹', '㎺',
+        '㎻', '㎼', '㎽', '㎾', '㎿', '㏀', '㏁', '㏃', '㏄', '㏅', '㏆', '㏈', '㏉', '㏊', '㏌',
+        '㏏', '㏐', '㏓', '㏔', '㏕', '㏖', '㏗', '㏙', '㏛', '㏜', '㏝',
     ];
     let only_valid_by_themselves = ['%', '‰', '‱', '′', '″', '’', '”', 'π'];
     let split_on_subsequent_digit = ['$', '£'];
@@ -433,19 +532,60 @@ fn parse_ident(input: &str, allow_dots: bool) -> Result<(Token, &str), FendError
     Ok((
         match ident {
             "to" | "as" | "in" => Token::Symbol(Symbol::UnitConversion),
-            "per" => Token::Symbol(Symbol::Div),
+            "per" => Token::Symbol(Symbol::Per),
             "of" => Token::Symbol(Symbol::Of),
             "mod" => Token::Symbol(Symbol::Mod),
             "xor" | "XOR" => Token::Symbol(Symbol::BitwiseXor),
             "and" | "AND" => Token::Symbol(Symbol::BitwiseAnd),
             "or" | "OR" => Token::Symbol(Symbol::BitwiseOr),
             "nCr" | "choose" => Token::Symbol(Symbol::Combination),
+            "if" => Token::Symbol(Symbol::If),
+            "then" => Token::Symbol(Symbol::Then),
+            "else" => Token::Symbol(Symbol::Else),
             _ => Token::Ident(Ident::new_string(ident.to_string())),
         },
         input,
     ))
 }
 
+fn superscript_digit(ch: char) -> Option<u64> {
+    Some(match ch {
+        '\u{2070}' => 0,
+        '\u{b9}' => 1,
+        '\u{b2}' => 2,
+        '\u{b3}' => 3,
+        '\u{2074}'..='\u{2079}' => u64::from(ch) - 0x2070,
+        _ => return None,
+    })
+}
+
+fn is_superscript(ch: char) -> bool {
+    // superscript plus and minus signs
+    ch == '\u{207a}' || ch == '\u{207b}' || superscript_digit(ch).is_some()
+}
+
+// Lexes one token of a superscript exponent like `⁻¹²`, which is treated
+// like `^-12`. The implicit `^` has already been emitted by the caller.
+fn parse_superscript(input: &str) -> Result<(Token, &str), FendError> {
+    let mut chars = input.chars();
+    match chars.next() {
+        Some('\u{207a}') => return Ok((Token::Symbol(Symbol::Add), chars.as_str())),
+        Some('\u{207b}') => return Ok((Token::Symbol(Symbol::Sub), chars.as_str())),
+        _ => (),
+    }
+    let mut value: u64 = 0;
+    let mut remaining = input;
+    let mut chars = input.chars();
+    while let Some(digit) = chars.next().and_then(superscript_digit) {
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add(digit))
+            .ok_or(FendError::ExponentTooLarge)?;
+        remaining = chars.as_str();
+    }
+    Ok((Token::Num(Number::from(value)), remaining))
+}
+
 fn parse_symbol(ch: char, input: &mut &str) -> Result<Token, FendError> {
     let mut test_next = |next: char| {
         if input.starts_with(next) {
@@ -460,7 +600,13 @@ fn parse_symbol(ch: char, input: &mut &str) -> Result<Token, FendError> {
         '(' => Symbol::OpenParens,
         ')' => Symbol::CloseParens,
         '+' => Symbol::Add,
-        '!' => Symbol::Factorial,
+        '!' => {
+            if test_next('=') {
+                Symbol::NotEquals
+            } else {
+                Symbol::Factorial
+            }
+        }
         // unicode minus sign
         '-' | '\u{2212}' => Symbol::Sub,
         '*' | '\u{d7}' | '\u{2715}' => {
@@ -470,7 +616,14 @@ fn parse_symbol(ch: char, input: &mut &str) -> Result<Token, FendError> {
                 Symbol::Mul
             }
         }
-        '/' | '\u{f7}' | '\u{2215}' => Symbol::Div, // unicode division symbol and slash
+        '/' => {
+            if test_next('/') {
+                Symbol::IntDiv
+            } else {
+                Symbol::Div
+            }
+        }
+        '\u{f7}' | '\u{2215}' => Symbol::Div, // unicode division symbol and slash
         '^' => Symbol::Pow,
         '&' => Symbol::BitwiseAnd,
         '|' => Symbol::BitwiseOr,
@@ -478,6 +631,8 @@ fn parse_symbol(ch: char, input: &mut &str) -> Result<Token, FendError> {
         '=' => {
             if test_next('>') {
                 Symbol::Fn
+            } else if test_next('=') {
+                Symbol::DoubleEquals
             } else {
                 Symbol::Equals
             }
@@ -487,18 +642,31 @@ fn parse_symbol(ch: char, input: &mut &str) -> Result<Token, FendError> {
         '<' => {
             if test_next('<') {
                 Symbol::ShiftLeft
+            } else if test_next('=') {
+                Symbol::LessThanOrEqual
             } else {
-                return Err(FendError::UnexpectedChar(ch));
+                Symbol::LessThan
             }
         }
         '>' => {
             if test_next('>') {
                 Symbol::ShiftRight
+            } else if test_next('=') {
+                Symbol::GreaterThanOrEqual
             } else {
-                return Err(FendError::UnexpectedChar(ch));
+                Symbol::GreaterThan
             }
         }
+        // unicode comparison operators
+        '\u{2260}' => Symbol::NotEquals,
+        '\u{2264}' => Symbol::LessThanOrEqual,
+        '\u{2265}' => Symbol::GreaterThanOrEqual,
         ';' => Symbol::Semicolon,
+        ',' => Symbol::Comma,
+        '\u{221a}' => Symbol::Sqrt,
+        '\u{221b}' => Symbol::CubeRoot,
+        '\u{221c}' => Symbol::FourthRoot,
+        '\u{221e}' => return Err(FendError::InfinityNotSupported),
         _ => return Err(FendError::UnexpectedChar(ch)),
     }))
 }
@@ -653,9 +821,14 @@ fn parse_quote_unit(input: &str) -> (Token, &str) {
 
 pub(crate) struct Lexer<'a, 'b, I: Interrupt> {
     input: &'a str,
+    input_len: usize,
     // normally 0; 1 after backslash; 2 after ident after backslash
     after_backslash_state: u8,
     after_number_or_to: bool,
+    // treat spaces between digit groups as digit separators, e.g. `1 000`
+    space_digit_separator: bool,
+    // set after emitting the implicit `^` before a superscript exponent
+    in_superscript: bool,
     int: &'b I,
 }
 
@@ -682,7 +855,6 @@ fn skip_whitespace_and_comments(input: &mut &str) {
 
 impl<'a, 'b, I: Interrupt> Lexer<'a, 'b, I> {
     fn next_token(&mut self) -> Result<Option<Token>, FendError> {
-        skip_whitespace_and_comments(&mut self.input);
         let (ch, following) = {
             let mut chars = self.input.chars();
             let ch = chars.next();
@@ -691,11 +863,27 @@ impl<'a, 'b, I: Interrupt> Lexer<'a, 'b, I> {
         };
         Ok(Some(match ch {
             Some(ch) => {
-                if ch.is_ascii_digit()
+                if is_superscript(ch) {
+                    if !self.in_superscript {
+                        self.in_superscript = true;
+                        return Ok(Some(Token::Symbol(Symbol::Pow)));
+                    }
+                    let (token, remaining) = parse_superscript(self.input)?;
+                    if matches!(token, Token::Num(_)) {
+                        self.in_superscript = false;
+                    }
+                    self.input = remaining;
+                    token
+                } else if self.in_superscript {
+                    // a superscript sign wasn't followed by any digits
+                    self.in_superscript = false;
+                    return Err(FendError::ExpectedADigit(ch));
+                } else if ch.is_ascii_digit()
                     || (ch == '.' && self.after_backslash_state == 0)
                     || (ch == 'd' && following.is_some() && following.unwrap().is_ascii_digit())
                 {
-                    let (num, remaining) = parse_number(self.input, self.int)?;
+                    let (num, remaining) =
+                        parse_number(self.input, self.space_digit_separator, self.int)?;
                     self.input = remaining;
                     Token::Num(num)
                 } else if ch == '\'' || ch == '"' {
@@ -739,11 +927,14 @@ impl<'a, 'b, I: Interrupt> Lexer<'a, 'b, I> {
 }
 
 impl<'a, I: Interrupt> Iterator for Lexer<'a, '_, I> {
-    type Item = Result<Token, FendError>;
+    // each token is returned along with its byte offset in the input
+    type Item = Result<(Token, usize), FendError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        skip_whitespace_and_comments(&mut self.input);
+        let position = self.input_len - self.input.len();
         let res = match self.next_token() {
-            Err(e) => Some(Err(e)),
+            Err(e) => Some(Err(e.at_position(position))),
             Ok(None) => None,
             Ok(Some(t)) => Some(Ok(t)),
         };
@@ -766,15 +957,22 @@ impl<'a, I: Interrupt> Iterator for Lexer<'a, '_, I> {
         } else {
             self.after_backslash_state = 0;
         }
-        res
+        res.map(|res| res.map(|token| (token, position)))
     }
 }
 
-pub(crate) fn lex<'a, 'b, I: Interrupt>(input: &'a str, int: &'b I) -> Lexer<'a, 'b, I> {
+pub(crate) fn lex<'a, 'b, I: Interrupt>(
+    input: &'a str,
+    space_digit_separator: bool,
+    int: &'b I,
+) -> Lexer<'a, 'b, I> {
     Lexer {
         input,
+        input_len: input.len(),
         after_backslash_state: 0,
         after_number_or_to: false,
+        space_digit_separator,
+        in_superscript: false,
         int,
     }
 }
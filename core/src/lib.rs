@@ -33,24 +33,34 @@ pub mod json;
 mod lexer;
 mod num;
 mod parser;
+mod quantity;
 mod scope;
 mod serialize;
 mod units;
 mod value;
 
-use std::{collections::HashMap, fmt, io};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, io,
+    sync::Arc,
+    time::Duration,
+};
 
 use error::FendError;
 pub(crate) use eval::Attrs;
-pub use interrupt::Interrupt;
+pub use interrupt::{Interrupt, Progress};
+pub use quantity::Quantity;
 use serialize::{deserialize_string, deserialize_usize, serialize_string, serialize_usize};
 
 /// This contains the result of a computation.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct FendResult {
     plain_result: String,
     span_result: Vec<Span>,
     is_unit: bool, // is this the () type
+    is_approximate: bool,
+    other_info: Vec<String>,
+    newline_policy: NewlinePolicy,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -123,15 +133,214 @@ impl FendResult {
         self.is_unit
     }
 
+    /// Returns whether the displayed result is an approximation, e.g. for
+    /// `pi` or `sin 1`. This can be used to prefix results with `≈`.
+    #[must_use]
+    pub fn is_approximate(&self) -> bool {
+        self.is_approximate
+    }
+
+    /// Returns additional notes about the result, e.g. whether it was
+    /// truncated because it exceeded [`Context::set_max_digits`].
+    pub fn get_other_info(&self) -> impl Iterator<Item = &str> {
+        self.other_info.iter().map(String::as_str)
+    }
+
+    /// Returns the main result combined with any other info as a single
+    /// string, for frontends that can only show one. How they're combined
+    /// is set by [`Context::set_newline_policy`]: by default, this is the
+    /// same as [`FendResult::get_main_result`].
+    #[must_use]
+    pub fn get_combined_result(&self) -> String {
+        let separator = match self.newline_policy {
+            NewlinePolicy::MainResultOnly => return self.plain_result.clone(),
+            NewlinePolicy::Newlines => "\n",
+            NewlinePolicy::Semicolons => "; ",
+        };
+        let mut parts = vec![];
+        if !self.plain_result.is_empty() {
+            parts.push(self.plain_result.as_str());
+        }
+        parts.extend(self.get_other_info());
+        parts.join(separator)
+    }
+
+    /// Returns the numbers in the result along with their units, e.g.
+    /// `[("2", "km")]` for `2 km`
+    fn components(&self) -> Vec<(String, String)> {
+        let mut components: Vec<(String, String)> = vec![];
+        for span in &self.span_result {
+            match span.kind {
+                SpanKind::Number => components.push((span.string.clone(), String::new())),
+                SpanKind::Ident => {
+                    // skip prefixes like `approx. `
+                    if let Some((_, unit)) = components.last_mut() {
+                        unit.push_str(&span.string);
+                    }
+                }
+                _ => (),
+            }
+        }
+        for (_, unit) in &mut components {
+            *unit = unit.trim().to_string();
+        }
+        components
+    }
+
+    fn write_json(&self, out: &mut String) {
+        out.push_str("{\"ok\": true, \"result\": \"");
+        json::escape_string(self.get_main_result(), out);
+        out.push_str("\", \"approximate\": ");
+        out.push_str(if self.is_approximate { "true" } else { "false" });
+        out.push_str(", \"components\": [");
+        for (i, (number, unit)) in self.components().iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            out.push_str("{\"number\": \"");
+            json::escape_string(number, out);
+            out.push_str("\", \"unit\": \"");
+            json::escape_string(unit, out);
+            out.push_str("\"}");
+        }
+        out.push_str("], \"other_info\": [");
+        for (i, info) in self.other_info.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            out.push('"');
+            json::escape_string(info, out);
+            out.push('"');
+        }
+        out.push_str("], \"error\": null}");
+    }
+
     fn empty() -> Self {
         Self {
             plain_result: String::new(),
             span_result: vec![],
             is_unit: true,
+            is_approximate: false,
+            other_info: vec![],
+            newline_policy: NewlinePolicy::MainResultOnly,
         }
     }
 }
 
+/// Timing information collected by [`evaluate_with_diagnostics`].
+///
+/// Allocation counts are not tracked, since that would require installing a
+/// custom global allocator.
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    lex_time: Duration,
+    parse_time: Duration,
+    eval_time: Duration,
+    format_time: Duration,
+    token_count: usize,
+}
+
+impl Diagnostics {
+    /// Time spent splitting the input into tokens
+    #[must_use]
+    pub fn lex_time(&self) -> Duration {
+        self.lex_time
+    }
+
+    /// Time spent parsing the tokens into an expression
+    #[must_use]
+    pub fn parse_time(&self) -> Duration {
+        self.parse_time
+    }
+
+    /// Time spent evaluating the parsed expression
+    #[must_use]
+    pub fn eval_time(&self) -> Duration {
+        self.eval_time
+    }
+
+    /// Time spent formatting the result
+    #[must_use]
+    pub fn format_time(&self) -> Duration {
+        self.format_time
+    }
+
+    /// Number of tokens in the input
+    #[must_use]
+    pub fn token_count(&self) -> usize {
+        self.token_count
+    }
+}
+
+/// A step-by-step explanation of an evaluation, returned by
+/// [`Context::explain`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    parsed: String,
+    steps: Vec<String>,
+    result: String,
+}
+
+impl Explanation {
+    /// The input as it was parsed, with parentheses showing the order in
+    /// which operators are applied, e.g. `((2 km)+(300 m))`
+    #[must_use]
+    pub fn parsed(&self) -> &str {
+        &self.parsed
+    }
+
+    /// The operations and unit conversions that were applied, in order,
+    /// e.g. `300 m = 0.3 km` and `2 km + 300 m = 2.3 km`
+    #[must_use]
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+
+    /// The final result, as it would be returned by [`evaluate`]
+    #[must_use]
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+}
+
+/// A result formatted in several bases at once, returned by
+/// [`Context::format_number_base`]. Only integers are formatted in binary,
+/// octal and hexadecimal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumberBases {
+    binary: Option<String>,
+    octal: Option<String>,
+    decimal: String,
+    hexadecimal: Option<String>,
+}
+
+impl NumberBases {
+    /// The result in base 2, e.g. `11111111`, or `None` if it isn't an
+    /// integer
+    #[must_use]
+    pub fn binary(&self) -> Option<&str> {
+        self.binary.as_deref()
+    }
+
+    /// The result in base 8, e.g. `377`, or `None` if it isn't an integer
+    #[must_use]
+    pub fn octal(&self) -> Option<&str> {
+        self.octal.as_deref()
+    }
+
+    /// The result in base 10, e.g. `255`
+    #[must_use]
+    pub fn decimal(&self) -> &str {
+        &self.decimal
+    }
+
+    /// The result in base 16, e.g. `ff`, or `None` if it isn't an integer
+    #[must_use]
+    pub fn hexadecimal(&self) -> Option<&str> {
+        self.hexadecimal.as_deref()
+    }
+}
+
 #[derive(Clone, Debug)]
 struct CurrentTimeInfo {
     elapsed_unix_time_ms: u64,
@@ -144,6 +353,177 @@ enum FCMode {
     CoulombFarad,
 }
 
+/// The system of units used to resolve unit names that have different
+/// definitions depending on the country, such as `gallon` or `pint`.
+/// Metric units are not affected by this setting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnitSystem {
+    /// US customary units: `gallon` is 231 cubic inches (about 3.785
+    /// litres), and a `hundredweight` is 100 pounds. This is the default.
+    #[default]
+    UsCustomary,
+    /// British imperial units: `gallon` is 4.54609 litres (so `quart`,
+    /// `pint` and `gill` are scaled accordingly), a `fluid_ounce` is 1/20
+    /// of a pint, and a `hundredweight` is 112 pounds.
+    Imperial,
+}
+
+/// The letter used to represent the imaginary unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImaginaryUnitSymbol {
+    /// `i`, as commonly used in mathematics. This is the default.
+    #[default]
+    I,
+    /// `j`, as commonly used in electrical engineering.
+    J,
+}
+
+/// How complex numbers are displayed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ComplexOutput {
+    /// Rectangular form, e.g. `1 + i`. This is the default.
+    #[default]
+    Rectangular,
+    /// Polar form, showing the magnitude and the angle in radians, e.g.
+    /// `approx. 1.4142135619∠0.7853981633` for `1 + i`. Real numbers
+    /// are still shown as usual.
+    Polar,
+}
+
+/// How the digits of large numbers are grouped in results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ThousandsStyle {
+    /// No grouping, e.g. `1234567`. This is the default.
+    #[default]
+    None,
+    /// Groups of three digits, e.g. `1,234,567`.
+    Western,
+    /// The Indian numbering system, with a group of three digits followed
+    /// by groups of two (lakh and crore), e.g. `12,34,567`.
+    Indian,
+}
+
+/// Whether results that are exactly zero keep their unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ZeroDisplay {
+    /// Keep the unit, e.g. `5 m - 5 m` is `0 m`. This is the default.
+    #[default]
+    KeepUnit,
+    /// Drop the unit, e.g. `5 m - 5 m` is `0`.
+    DropUnit,
+}
+
+/// How numbers are rounded by the `round` function and when displaying a
+/// fixed number of decimal places (e.g. `2/3 to 2dp`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RoundingMode {
+    /// Round to the nearest number, with ties rounded away from zero
+    /// (`2.5` becomes `3` and `-2.5` becomes `-3`).
+    HalfUp,
+    /// Round to the nearest number, with ties rounded to the nearest even
+    /// number (`2.5` becomes `2` and `3.5` becomes `4`). This is the
+    /// default.
+    #[default]
+    HalfEven,
+    /// Always round towards zero, i.e. truncate (`2.9` becomes `2`).
+    TowardZero,
+    /// Always round away from zero (`2.1` becomes `3`).
+    AwayFromZero,
+}
+
+/// How booleans are shown, and whether they can be used as numbers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BooleanStyle {
+    /// Booleans are shown as `true` or `false`, and using them as numbers
+    /// (e.g. `true + 1`) is an error. This is the default.
+    #[default]
+    Words,
+    /// Booleans are shown as `1` or `0` like in C, and they can be used in
+    /// arithmetic (`true + true` is `2`) and compared with numbers
+    /// (`true == 1` is `1`).
+    Numbers,
+}
+
+/// How [`FendResult::get_combined_result`] combines the main result with
+/// any other info (see [`FendResult::get_other_info`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NewlinePolicy {
+    /// Only the main result is returned, and other info is left out. This
+    /// is the default.
+    #[default]
+    MainResultOnly,
+    /// The main result and each piece of other info are put on separate
+    /// lines.
+    Newlines,
+    /// The main result and each piece of other info are separated by `; `.
+    Semicolons,
+}
+
+/// How much detail is included in error messages returned by functions
+/// like [`evaluate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorVerbosity {
+    /// Every error is reported as just `error`.
+    Terse,
+    /// Describe the problem, e.g. `unexpected character '#'`. This is the
+    /// default.
+    #[default]
+    Normal,
+    /// Like [`ErrorVerbosity::Normal`], but syntax errors also include the
+    /// byte offset in the input where the problem was found, e.g.
+    /// `unexpected character '#' (at position 4)`.
+    Verbose,
+}
+
+/// How unit names like `meter` and `liter` are spelled in results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnitSpelling {
+    /// American spellings, e.g. `meter` and `liter`. This is the default.
+    #[default]
+    American,
+    /// British spellings, e.g. `metre` and `litre`.
+    British,
+}
+
+/// A set of regional conventions, which can be applied with
+/// [`Context::set_locale`]. Setting any of the supported locales groups
+/// the digits of results as `1,234,567.5`, whereas a new [`Context`]
+/// doesn't group them at all (`1234567.5`, see [`ThousandsStyle`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Locale {
+    /// American English. This is the default.
+    #[default]
+    EnUs,
+    /// British English, with British unit spellings and imperial
+    /// definitions for units like `gallon` and `pint`.
+    EnGb,
+}
+
+impl Locale {
+    /// Find the locale for a language tag like `en-GB`, `en_US` or
+    /// `en_GB.UTF-8`. Unknown or unsupported tags fall back to the default
+    /// locale instead of returning an error.
+    #[must_use]
+    pub fn from_language_tag(tag: &str) -> Self {
+        let tag = tag.split('.').next().unwrap_or_default();
+        match tag.replace('_', "-").to_ascii_lowercase().as_str() {
+            "en-gb" | "en-uk" => Self::EnGb,
+            _ => Self::default(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum OutputMode {
     SimpleText,
@@ -152,16 +532,70 @@ enum OutputMode {
 
 type ExchangeRateFn = fn(&str) -> Result<f64, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+type RegisteredFn = Arc<
+    dyn Fn(f64) -> Result<f64, Box<dyn std::error::Error + Send + Sync + 'static>> + Send + Sync,
+>;
+
+const DEFAULT_MAX_DIGITS: usize = 100_000;
+
 /// This struct contains context used for `fend`. It should only be created once
 /// at startup.
 #[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Context {
     current_time: Option<CurrentTimeInfo>,
     variables: HashMap<String, value::Value>,
+    constants: HashMap<String, value::Value>,
     fc_mode: FCMode,
     random_u32: Option<fn() -> u32>,
     output_mode: OutputMode,
     get_exchange_rate: Option<ExchangeRateFn>,
+    symbolic_identifiers: bool,
+    boolean_style: BooleanStyle,
+    unit_system: UnitSystem,
+    unit_spelling: UnitSpelling,
+    imaginary_unit: ImaginaryUnitSymbol,
+    complex_output: ComplexOutput,
+    thousands_style: ThousandsStyle,
+    digit_separator: char,
+    decimal_separator: char,
+    zero_display: ZeroDisplay,
+    max_digits: usize,
+    integer_digit_limit: Option<usize>,
+    space_digit_separator: bool,
+    rounding_mode: RoundingMode,
+    error_verbosity: ErrorVerbosity,
+    newline_policy: NewlinePolicy,
+    auto_simplify: bool,
+    input_history: Vec<String>,
+    input_history_limit: usize,
+    registered_functions: HashMap<String, RegisteredFn>,
+    // singular name, plural name, definition
+    custom_units: Vec<(String, String, String)>,
+    // name, definition
+    custom_prefixes: Vec<(String, String)>,
+    // custom units whose definitions are currently being evaluated, used
+    // to detect recursive definitions
+    resolving_units: Vec<String>,
+    // steps recorded while explaining an evaluation
+    explanation: Option<Vec<String>>,
+    // maximum number of memoized results, or 0 if caching is disabled
+    result_cache_size: usize,
+    // least recently used results first
+    result_cache: Vec<CachedResult>,
+    // cleared while evaluating an input whose result depends on more than
+    // the input and the context, e.g. on random numbers or `ans`
+    result_is_cacheable: bool,
+    // set while checking the branch of an `if` expression that wasn't taken
+    checking_if_branch: bool,
+}
+
+#[derive(Clone)]
+struct CachedResult {
+    input: String,
+    result: FendResult,
+    // the value of `_` and `ans` after the evaluation
+    ans: Option<value::Value>,
 }
 
 impl fmt::Debug for Context {
@@ -169,9 +603,38 @@ impl fmt::Debug for Context {
         f.debug_struct("Context")
             .field("current_time", &self.current_time)
             .field("variables", &self.variables)
+            .field("constants", &self.constants)
             .field("fc_mode", &self.fc_mode)
             .field("random_u32", &self.random_u32)
             .field("output_mode", &self.output_mode)
+            .field("symbolic_identifiers", &self.symbolic_identifiers)
+            .field("boolean_style", &self.boolean_style)
+            .field("unit_system", &self.unit_system)
+            .field("unit_spelling", &self.unit_spelling)
+            .field("imaginary_unit", &self.imaginary_unit)
+            .field("complex_output", &self.complex_output)
+            .field("thousands_style", &self.thousands_style)
+            .field("digit_separator", &self.digit_separator)
+            .field("decimal_separator", &self.decimal_separator)
+            .field("zero_display", &self.zero_display)
+            .field("max_digits", &self.max_digits)
+            .field("integer_digit_limit", &self.integer_digit_limit)
+            .field("space_digit_separator", &self.space_digit_separator)
+            .field("rounding_mode", &self.rounding_mode)
+            .field("error_verbosity", &self.error_verbosity)
+            .field("newline_policy", &self.newline_policy)
+            .field("auto_simplify", &self.auto_simplify)
+            .field("input_history", &self.input_history)
+            .field("input_history_limit", &self.input_history_limit)
+            .field(
+                "registered_functions",
+                &self.registered_functions.keys().collect::<Vec<_>>(),
+            )
+            .field("custom_units", &self.custom_units)
+            .field("custom_prefixes", &self.custom_prefixes)
+            .field("resolving_units", &self.resolving_units)
+            .field("explanation", &self.explanation)
+            .field("result_cache_size", &self.result_cache_size)
             .finish_non_exhaustive()
     }
 }
@@ -190,10 +653,39 @@ impl Context {
         Self {
             current_time: None,
             variables: HashMap::new(),
+            constants: HashMap::new(),
             fc_mode: FCMode::CelsiusFahrenheit,
             random_u32: None,
             output_mode: OutputMode::SimpleText,
             get_exchange_rate: None,
+            symbolic_identifiers: false,
+            boolean_style: BooleanStyle::Words,
+            unit_system: UnitSystem::UsCustomary,
+            unit_spelling: UnitSpelling::American,
+            imaginary_unit: ImaginaryUnitSymbol::I,
+            complex_output: ComplexOutput::Rectangular,
+            thousands_style: ThousandsStyle::None,
+            digit_separator: ',',
+            decimal_separator: '.',
+            zero_display: ZeroDisplay::KeepUnit,
+            max_digits: DEFAULT_MAX_DIGITS,
+            integer_digit_limit: None,
+            space_digit_separator: false,
+            rounding_mode: RoundingMode::HalfEven,
+            error_verbosity: ErrorVerbosity::Normal,
+            newline_policy: NewlinePolicy::MainResultOnly,
+            auto_simplify: true,
+            input_history: vec![],
+            input_history_limit: 0,
+            registered_functions: HashMap::new(),
+            custom_units: vec![],
+            custom_prefixes: vec![],
+            resolving_units: vec![],
+            explanation: None,
+            result_cache_size: 0,
+            result_cache: vec![],
+            result_is_cacheable: true,
+            checking_if_branch: false,
         }
     }
 
@@ -213,12 +705,294 @@ impl Context {
         //     timezone_offset_secs: tz_offset_secs,
         // });
         self.current_time = None;
+        self.result_cache.clear();
     }
 
     /// Define the units `C` and `F` as coulomb and farad instead of degrees
     /// celsius and degrees fahrenheit.
     pub fn use_coulomb_and_farad(&mut self) {
         self.fc_mode = FCMode::CoulombFarad;
+        self.result_cache.clear();
+    }
+
+    /// Set the system of units used for unit names with country-specific
+    /// definitions. See [`UnitSystem`] for details.
+    pub fn set_unit_system(&mut self, unit_system: UnitSystem) {
+        self.unit_system = unit_system;
+        self.result_cache.clear();
+    }
+
+    /// Set how unit names are spelled in results. See [`UnitSpelling`] for
+    /// details. Both spellings are always accepted in calculations.
+    pub fn set_unit_spelling(&mut self, unit_spelling: UnitSpelling) {
+        self.unit_spelling = unit_spelling;
+        self.result_cache.clear();
+    }
+
+    /// Apply the conventions of the given locale. This sets the unit
+    /// system, the unit spelling, the decimal separator and how digits are
+    /// grouped in results, which can each be changed again afterwards with
+    /// their own setters. Other settings are left unchanged.
+    pub fn set_locale(&mut self, locale: Locale) {
+        match locale {
+            Locale::EnUs => {
+                self.unit_system = UnitSystem::UsCustomary;
+                self.unit_spelling = UnitSpelling::American;
+            }
+            Locale::EnGb => {
+                self.unit_system = UnitSystem::Imperial;
+                self.unit_spelling = UnitSpelling::British;
+            }
+        }
+        // both supported locales write numbers as `1,234,567.5`
+        self.decimal_separator = '.';
+        self.thousands_style = ThousandsStyle::Western;
+        self.digit_separator = ',';
+        self.result_cache.clear();
+    }
+
+    /// Set the letter used for the imaginary unit in results. When set to
+    /// [`ImaginaryUnitSymbol::J`], `j` can also be used in calculations
+    /// (e.g. `2 + 3j`) instead of referring to joules. `i` always refers
+    /// to the imaginary unit.
+    pub fn set_imaginary_unit_symbol(&mut self, symbol: ImaginaryUnitSymbol) {
+        self.imaginary_unit = symbol;
+        self.result_cache.clear();
+    }
+
+    /// Set whether complex results are shown in rectangular form (`1 + i`)
+    /// or in polar form (`r∠θ`, with the angle in radians).
+    pub fn set_complex_output(&mut self, complex_output: ComplexOutput) {
+        self.complex_output = complex_output;
+        self.result_cache.clear();
+    }
+
+    /// Set how the digits of decimal results are grouped, e.g. as
+    /// `1,234,567` or `12,34,567`. Only the integer part of a number is
+    /// grouped. Results aren't grouped by default.
+    pub fn set_thousands_style(&mut self, thousands_style: ThousandsStyle) {
+        self.thousands_style = thousands_style;
+        self.result_cache.clear();
+    }
+
+    /// Set the character used to separate groups of digits when a
+    /// [`ThousandsStyle`] is enabled, e.g. `'` for `1'234'567` or `_` for
+    /// `1_234_567`. The default is `,`.
+    ///
+    /// # Errors
+    /// Returns an error unless the separator is one of `,`, `.`, `'`, `_`,
+    /// a space, a thin space (U+2009) or a narrow no-break space (U+202F),
+    /// or if it's the same as the decimal separator.
+    pub fn set_digit_separator_char(&mut self, separator: char) -> Result<(), String> {
+        if !matches!(
+            separator,
+            ',' | '.' | '\'' | '_' | ' ' | '\u{2009}' | '\u{202f}'
+        ) {
+            return Err(FendError::InvalidDigitSeparator(separator).to_string());
+        }
+        if separator == self.decimal_separator {
+            return Err(FendError::SameDigitAndDecimalSeparator(separator).to_string());
+        }
+        self.digit_separator = separator;
+        self.result_cache.clear();
+        Ok(())
+    }
+
+    /// Set the character used as the decimal separator in decimal results,
+    /// e.g. `,` for `1.234.567,5`. The default is `.`. This only affects
+    /// results: calculations always use `.` as the decimal separator.
+    ///
+    /// # Errors
+    /// Returns an error unless the separator is `.` or `,`, or if it's the
+    /// same as the digit separator (see
+    /// [`Context::set_digit_separator_char`]).
+    pub fn set_decimal_separator(&mut self, separator: char) -> Result<(), String> {
+        if !matches!(separator, '.' | ',') {
+            return Err(FendError::InvalidDecimalSeparator(separator).to_string());
+        }
+        if separator == self.digit_separator {
+            return Err(FendError::SameDigitAndDecimalSeparator(separator).to_string());
+        }
+        self.decimal_separator = separator;
+        self.result_cache.clear();
+        Ok(())
+    }
+
+    /// Set whether results that are exactly zero keep their unit (`0 m`,
+    /// the default) or are shown without one (`0`).
+    pub fn set_zero_display(&mut self, zero_display: ZeroDisplay) {
+        self.zero_display = zero_display;
+        self.result_cache.clear();
+    }
+
+    /// Set the maximum number of characters in a result (100,000 by
+    /// default). Longer results (e.g. `1/7 to 5000 dp`) are truncated with
+    /// an ellipsis, and a note is added to [`FendResult::get_other_info`].
+    ///
+    /// This only limits the size of the output: the full result is still
+    /// computed, so use an [`Interrupt`] to limit how long that can take.
+    pub fn set_max_digits(&mut self, max_digits: usize) {
+        self.max_digits = max_digits;
+        self.result_cache.clear();
+    }
+
+    /// Show integers with more than `limit` digits in scientific notation,
+    /// e.g. with a limit of 20, `100!` is `approx. 9.3326215443e157`
+    /// instead of a 158-digit number. The exact value can still be shown
+    /// with `to exact` (e.g. `ans to exact`). This only applies to decimal
+    /// results without an explicitly requested format, and is disabled
+    /// (`None`) by default.
+    ///
+    /// # Errors
+    /// Returns an error if the limit is zero.
+    pub fn set_integer_digit_limit(&mut self, limit: Option<usize>) -> Result<(), String> {
+        if limit == Some(0) {
+            return Err(FendError::ZeroIntegerDigitLimit.to_string());
+        }
+        self.integer_digit_limit = limit;
+        self.result_cache.clear();
+        Ok(())
+    }
+
+    /// Allow spaces as digit separators, as in `1 000 000`. Each space
+    /// needs to be followed by a group of exactly three digits, so inputs
+    /// like `1 2` or `1 0000` are still rejected. This is disabled by
+    /// default.
+    pub fn set_space_digit_separator(&mut self, enabled: bool) {
+        self.space_digit_separator = enabled;
+        self.result_cache.clear();
+    }
+
+    /// Set how numbers are rounded. See [`RoundingMode`] for details.
+    pub fn set_rounding_mode(&mut self, rounding_mode: RoundingMode) {
+        self.rounding_mode = rounding_mode;
+        self.result_cache.clear();
+    }
+
+    /// Set how much detail is included in the error messages of
+    /// evaluations. See [`ErrorVerbosity`] for details.
+    pub fn set_error_verbosity(&mut self, error_verbosity: ErrorVerbosity) {
+        self.error_verbosity = error_verbosity;
+    }
+
+    /// Set how [`FendResult::get_combined_result`] combines the main result
+    /// with any other info. See [`NewlinePolicy`] for details.
+    pub fn set_newline_policy(&mut self, newline_policy: NewlinePolicy) {
+        self.newline_policy = newline_policy;
+        self.result_cache.clear();
+    }
+
+    /// Set whether units in results are simplified, e.g. by combining
+    /// `m * m` into `m^2`. Disabling this shows the units exactly as they
+    /// were calculated, which can be useful for debugging. This is enabled
+    /// by default.
+    pub fn set_auto_simplify(&mut self, enabled: bool) {
+        self.auto_simplify = enabled;
+        self.result_cache.clear();
+    }
+
+    fn format_error(&self, error: &FendError) -> String {
+        match (self.error_verbosity, error.position()) {
+            (ErrorVerbosity::Terse, _) => "error".to_string(),
+            (ErrorVerbosity::Verbose, Some(position)) => {
+                format!("{error} (at position {position})")
+            }
+            (ErrorVerbosity::Normal | ErrorVerbosity::Verbose, _) => error.to_string(),
+        }
+    }
+
+    /// Keep a history of up to `limit` previous inputs, which can be
+    /// retrieved with [`Context::input_history`]. This is disabled by
+    /// default (i.e. the limit is 0). When the limit is reached, the
+    /// oldest inputs are removed first.
+    pub fn set_input_history_limit(&mut self, limit: usize) {
+        self.input_history_limit = limit;
+        self.truncate_input_history();
+    }
+
+    /// Returns previously evaluated inputs, from oldest to newest. Blank
+    /// inputs are skipped, and repeating the previous input doesn't add a
+    /// new entry. Unlike `_` and `ans`, which contain the previous result,
+    /// this records inputs even if they couldn't be evaluated.
+    #[must_use]
+    pub fn input_history(&self) -> &[String] {
+        &self.input_history
+    }
+
+    /// Removes all entries from the input history
+    pub fn clear_input_history(&mut self) {
+        self.input_history.clear();
+    }
+
+    /// Clears the input history along with the previous result (`_` and
+    /// `ans`), so that `ans` is undefined until the next calculation.
+    /// Other variables and settings (including the history limit) are
+    /// kept.
+    pub fn clear_history(&mut self) {
+        self.clear_input_history();
+        self.variables.retain(|name, _| !is_builtin_variable(name));
+    }
+
+    /// Remember the results of up to `size` previous evaluations, so that
+    /// evaluating the same input again (e.g. in a frontend that evaluates
+    /// the input as it is being typed) returns the remembered result instead
+    /// of recomputing it. This is disabled by default (i.e. the size is 0).
+    /// When the cache is full, the least recently used result is removed.
+    ///
+    /// The cache is cleared whenever a setting, a variable or a custom
+    /// definition changes. Inputs that use random numbers, the current
+    /// date, exchange rates or the previous result (`ans`) are never
+    /// cached, and neither are errors. Functions registered with
+    /// [`Self::register_function`] are assumed to always return the same
+    /// result for the same argument.
+    pub fn set_result_cache_size(&mut self, size: usize) {
+        self.result_cache_size = size;
+        if self.result_cache.len() > size {
+            let excess = self.result_cache.len() - size;
+            self.result_cache.drain(..excess);
+        }
+    }
+
+    fn get_cached_result(&mut self, input: &str) -> Option<FendResult> {
+        let idx = self.result_cache.iter().position(|c| c.input == input)?;
+        let cached = self.result_cache.remove(idx);
+        if let Some(ans) = &cached.ans {
+            self.variables.insert("_".to_string(), ans.clone());
+            self.variables.insert("ans".to_string(), ans.clone());
+        }
+        let result = cached.result.clone();
+        self.result_cache.push(cached);
+        Some(result)
+    }
+
+    fn cache_result(&mut self, input: &str, result: &FendResult) {
+        if self.result_cache.len() >= self.result_cache_size {
+            let excess = self.result_cache.len() + 1 - self.result_cache_size;
+            self.result_cache.drain(..excess);
+        }
+        self.result_cache.push(CachedResult {
+            input: input.to_string(),
+            result: result.clone(),
+            ans: self.variables.get("ans").cloned(),
+        });
+    }
+
+    fn add_to_input_history(&mut self, input: &str) {
+        if self.input_history_limit == 0
+            || input.trim().is_empty()
+            || self.input_history.last().map(String::as_str) == Some(input)
+        {
+            return;
+        }
+        self.input_history.push(input.to_string());
+        self.truncate_input_history();
+    }
+
+    fn truncate_input_history(&mut self) {
+        if self.input_history.len() > self.input_history_limit {
+            let excess = self.input_history.len() - self.input_history_limit;
+            self.input_history.drain(..excess);
+        }
     }
 
     /// Set a random number generator
@@ -235,6 +1009,23 @@ impl Context {
     /// graphs in the output.
     pub fn set_output_mode_terminal(&mut self) {
         self.output_mode = OutputMode::TerminalFixedWidth;
+        self.result_cache.clear();
+    }
+
+    /// Treat unknown identifiers as symbolic variables instead of raising
+    /// an error. Each unknown identifier behaves like a unit of its own,
+    /// so e.g. `x + x` evaluates to `2 x` and `3x * x` to `3 x^2`. Adding
+    /// two different symbols (like `x + y`) is still an error.
+    pub fn set_symbolic_identifiers(&mut self, enabled: bool) {
+        self.symbolic_identifiers = enabled;
+        self.result_cache.clear();
+    }
+
+    /// Set how booleans are shown and whether they can be used as numbers.
+    /// See [`BooleanStyle`] for details.
+    pub fn set_boolean_style(&mut self, boolean_style: BooleanStyle) {
+        self.boolean_style = boolean_style;
+        self.result_cache.clear();
     }
 
     fn serialize_variables_internal(&self, write: &mut impl io::Write) -> Result<(), FendError> {
@@ -264,6 +1055,7 @@ impl Context {
         read: &mut impl io::Read,
     ) -> Result<(), FendError> {
         let len = deserialize_usize(read)?;
+        self.result_cache.clear();
         self.variables.clear();
         self.variables.reserve(len);
         for _ in 0..len {
@@ -289,6 +1081,449 @@ impl Context {
     /// Set a handler function for loading exchange rates.
     pub fn set_exchange_rate_handler_v1(&mut self, get_exchange_rate: ExchangeRateFn) {
         self.get_exchange_rate = Some(get_exchange_rate);
+        self.result_cache.clear();
+    }
+
+    /// Register a custom function that can be called from fend expressions,
+    /// e.g. `register_function("double", |x| Ok(2.0 * x))` allows `double 21`.
+    /// The function receives its argument as an `f64`, so it can only be
+    /// called with unitless real numbers, and it must return a finite value.
+    /// Its result is always treated as approximate.
+    ///
+    /// Registered functions take precedence over built-in functions, units
+    /// and constants with the same name, but variables defined in fend
+    /// (e.g. `double = 5`) still shadow them. Registering a function under
+    /// an existing name replaces it.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        function: impl Fn(f64) -> Result<f64, Box<dyn std::error::Error + Send + Sync + 'static>>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.registered_functions
+            .insert(name.to_string(), Arc::new(function));
+        self.result_cache.clear();
+    }
+
+    fn define_unit_internal(
+        &mut self,
+        singular: &str,
+        plural: &str,
+        definition: &str,
+    ) -> Result<(), FendError> {
+        let plural = if plural.is_empty() { singular } else { plural };
+        self.result_cache.clear();
+        let previous_units = self.custom_units.clone();
+        self.custom_units.retain(|(s, _, _)| s != singular);
+        self.custom_units.push((
+            singular.to_string(),
+            plural.to_string(),
+            definition.to_string(),
+        ));
+        // make sure the definition is valid (and doesn't depend on the new
+        // unit itself) before keeping it
+        if definition != "!" {
+            self.resolving_units.push(singular.to_string());
+            let result = eval::evaluate_to_value(
+                definition,
+                None,
+                Attrs::default(),
+                self,
+                &interrupt::Never::default(),
+            )
+            .and_then(value::Value::expect_num);
+            self.resolving_units.pop();
+            if let Err(e) = result {
+                self.custom_units = previous_units;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Define a custom unit, e.g. `define_unit("smoot", "smoots", "1.7018 m")`.
+    /// If `plural` is empty, the singular name is used for both, and a
+    /// definition of `!` creates a new base unit. Custom
+    /// units can be used with long prefixes (like `kilosmoot`), and take
+    /// precedence over built-in units with the same name. Redefining a
+    /// custom unit replaces the previous definition.
+    ///
+    /// # Errors
+    /// Returns an error if the definition cannot be evaluated to a number.
+    pub fn define_unit(
+        &mut self,
+        singular: &str,
+        plural: &str,
+        definition: &str,
+    ) -> Result<(), String> {
+        self.define_unit_internal(singular, plural, definition)
+            .map_err(|e| e.to_string())
+    }
+
+    fn define_prefix_internal(&mut self, name: &str, definition: &str) -> Result<(), FendError> {
+        if units::is_defined_unit_or_prefix(name, self) {
+            return Err(FendError::PrefixAlreadyDefined(name.to_string()));
+        }
+        let int = interrupt::Never::default();
+        let value = eval::evaluate_to_value(definition, None, Attrs::default(), self, &int)?
+            .expect_num()?;
+        if !value.is_unitless(&int)? {
+            return Err(FendError::ExpectedAUnitlessNumber);
+        }
+        self.result_cache.clear();
+        self.custom_prefixes
+            .push((name.to_string(), definition.to_string()));
+        Ok(())
+    }
+
+    /// Define a custom prefix, e.g. `define_prefix("bi", "2")`. Like the
+    /// long SI prefixes (e.g. `kilo`), it can be combined with any unit that
+    /// accepts them, so `3 bimeters` is `6 meters`.
+    ///
+    /// # Errors
+    /// Returns an error if the name is already used by a unit or prefix, or
+    /// if the definition does not evaluate to a unitless number.
+    pub fn define_prefix(&mut self, name: &str, definition: &str) -> Result<(), String> {
+        self.define_prefix_internal(name, definition)
+            .map_err(|e| e.to_string())
+    }
+
+    fn define_constant_internal(&mut self, name: &str, definition: &str) -> Result<(), FendError> {
+        let value = eval::evaluate_to_value(
+            definition,
+            None,
+            Attrs::default(),
+            self,
+            &interrupt::Never::default(),
+        )?
+        .expect_num()?;
+        self.result_cache.clear();
+        self.variables.remove(name);
+        self.constants
+            .insert(name.to_string(), value::Value::Num(Box::new(value)));
+        Ok(())
+    }
+
+    /// Define a named constant, e.g. `define_constant("golden", "(1 + sqrt 5) / 2")`.
+    /// Unlike variables, constants can't be reassigned in calculations
+    /// (`golden = 2` is an error), and they take precedence over variables,
+    /// units and built-in constants with the same name. The definition is
+    /// evaluated once, and approximate values are shown as such (e.g.
+    /// `golden` is `approx. 1.6180339886`). Redefining a constant replaces
+    /// the previous value.
+    ///
+    /// # Errors
+    /// Returns an error if the definition cannot be evaluated to a number.
+    pub fn define_constant(&mut self, name: &str, definition: &str) -> Result<(), String> {
+        self.define_constant_internal(name, definition)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Set a variable to the given quantity, as if it had been assigned
+    /// with `name = value`. Like other variables, it can be reassigned in
+    /// calculations, and constants with the same name take precedence.
+    pub fn set_variable(&mut self, name: &str, value: Quantity) {
+        self.result_cache.clear();
+        self.variables
+            .insert(name.to_string(), value::Value::Num(Box::new(value.0)));
+    }
+
+    /// Returns the canonical name of the given unit, resolving aliases,
+    /// alternative spellings, plurals and symbols. For example, `metre`,
+    /// `meters` and `m` all return `meter`. Returns `None` if there is no
+    /// unit with that name. Prefixed units like `km` are not resolved.
+    #[must_use]
+    pub fn canonical_unit_name(&self, name: &str) -> Option<String> {
+        units::canonical_unit_name(name, self)
+    }
+
+    /// Returns the dimensions of the given unit along with their exponents,
+    /// e.g. `{"length": 1, "mass": 1, "time": -2}` for `N`. The built-in
+    /// dimensions are `time`, `length`, `mass`, `temperature`, `current`,
+    /// `amount`, `luminous intensity`, `information` and `currency`, and
+    /// unitless values like `percent` have no dimensions at all. Custom base
+    /// units (see [`Self::define_unit`]) appear under their own name.
+    ///
+    /// # Errors
+    /// Returns [`UnitDimensionError::UnknownUnit`] if there is no unit with
+    /// that name, or [`UnitDimensionError::Other`] if its dimensions can't
+    /// be represented, e.g. for a custom unit defined as `Hz^0.5`.
+    pub fn unit_dimension(
+        &mut self,
+        name: &str,
+    ) -> Result<BTreeMap<String, i64>, UnitDimensionError> {
+        units::unit_dimension(name, self, &interrupt::Never::default())
+    }
+
+    /// Returns the names and values of all supported prefixes, including
+    /// short prefixes like `k` and custom prefixes defined with
+    /// [`Self::define_prefix`]. Prefixes are sorted by their value, from
+    /// smallest (`quecto`, 1e-30) to largest (`quecca`, 1e30).
+    ///
+    /// # Errors
+    /// Returns an error if a custom prefix can no longer be evaluated, e.g.
+    /// because it was defined in terms of a variable that has since been
+    /// reassigned to a value with a unit.
+    pub fn list_prefixes(&mut self) -> Result<Vec<(String, f64)>, String> {
+        units::list_prefixes(self, &interrupt::Never::default()).map_err(|e| e.to_string())
+    }
+
+    /// Evaluates the given string and converts the result into `T`, e.g.
+    /// `context.evaluate_as::<f64>("1/4")` is `0.25`. See [`FromValue`] for
+    /// the supported types and how results are converted. Like [`evaluate`],
+    /// this can modify the context, e.g. by assigning variables.
+    ///
+    /// # Errors
+    /// It returns an error if the given string is invalid, or if the result
+    /// cannot be converted into `T` (e.g. `1.5` or `2 m` as an `i64`).
+    pub fn evaluate_as<T: FromValue>(&mut self, input: &str) -> Result<T, String> {
+        let int = interrupt::Never::default();
+        let value = eval::evaluate_to_value(input, None, Attrs::default(), self, &int)
+            .and_then(|value| eval::simplify_result(value, Attrs::default(), self, &int))
+            .map_err(|e| e.to_string())?;
+        T::from_value(from_value::Value(value), self).map_err(|e| e.0.to_string())
+    }
+
+    /// Evaluates the given string and formats the result in binary, octal,
+    /// decimal and hexadecimal, e.g. `255` is `11111111`, `377`, `255` and
+    /// `ff`. Results that aren't integers (like `1.5`) are only formatted in
+    /// decimal. Like [`evaluate`], this can modify the context, e.g. by
+    /// assigning variables.
+    ///
+    /// # Errors
+    /// It returns an error if the given string is invalid, or if the result
+    /// isn't a number.
+    pub fn format_number_base(&mut self, input: &str) -> Result<NumberBases, String> {
+        let int = interrupt::Never::default();
+        self.format_number_base_internal(input, &int)
+            .map_err(|e| e.to_string())
+    }
+
+    fn format_number_base_internal<I: Interrupt>(
+        &mut self,
+        input: &str,
+        int: &I,
+    ) -> Result<NumberBases, FendError> {
+        let value = eval::evaluate_to_value(input, None, Attrs::default(), self, int)?;
+        let number = eval::simplify_result(value, Attrs::default(), self, int)?.expect_num()?;
+        let is_integer = number.is_integer(int)?;
+        let format = |base: u8| -> Result<String, FendError> {
+            let value = value::Value::Num(Box::new(
+                number.clone().with_base(num::Base::from_plain_base(base)?),
+            ));
+            value.format_to_plain_string(0, Attrs::default(), self, int)
+        };
+        let format_integer = |base: u8| -> Result<Option<String>, FendError> {
+            if is_integer {
+                Ok(Some(format(base)?))
+            } else {
+                Ok(None)
+            }
+        };
+        Ok(NumberBases {
+            binary: format_integer(2)?,
+            octal: format_integer(8)?,
+            decimal: format(10)?,
+            hexadecimal: format_integer(16)?,
+        })
+    }
+
+    /// Returns identifiers (variables, functions and units) starting with
+    /// the given prefix, e.g. `me` suggests `meter` and `mega`. User-defined
+    /// variables and units are listed first, followed by built-in names.
+    /// Both groups are sorted alphabetically, and duplicates are removed.
+    #[must_use]
+    pub fn autocomplete(&self, prefix: &str) -> Vec<String> {
+        let mut user_defined = self
+            .variables
+            .keys()
+            .chain(self.constants.keys())
+            .chain(self.registered_functions.keys())
+            .map(String::as_str)
+            .chain(self.custom_units.iter().map(|(s, _, _)| s.as_str()))
+            .filter(|name| name.starts_with(prefix))
+            .collect::<Vec<_>>();
+        user_defined.sort_unstable();
+        user_defined.dedup();
+        let mut builtin = ast::BUILTIN_FUNCTION_NAMES
+            .iter()
+            .copied()
+            .chain(units::builtin_unit_names())
+            .filter(|name| name.starts_with(prefix) && !user_defined.contains(name))
+            .collect::<Vec<_>>();
+        builtin.sort_unstable();
+        builtin.dedup();
+        user_defined
+            .into_iter()
+            .chain(builtin)
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Returns the names and values of all variables defined in this context,
+    /// sorted by name. Variables that contain functions are returned by
+    /// [`Context::functions`] instead. If `include_builtins` is false, the
+    /// `_` and `ans` variables (which contain the previous result) are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if a variable's value could not be formatted.
+    pub fn variables(&self, include_builtins: bool) -> Result<Vec<(String, String)>, String> {
+        let mut variables = self
+            .variables
+            .iter()
+            .filter(|(name, value)| {
+                !value.is_fn() && (include_builtins || !is_builtin_variable(name))
+            })
+            .collect::<Vec<_>>();
+        variables.sort_unstable_by_key(|(name, _)| name.as_str());
+        variables
+            .into_iter()
+            .map(|(name, value)| {
+                let mut spans = vec![];
+                value
+                    .format(
+                        0,
+                        &mut spans,
+                        Attrs::default(),
+                        self,
+                        &interrupt::Never::default(),
+                    )
+                    .map_err(|e| e.to_string())?;
+                let formatted = spans.into_iter().map(|span| span.string).collect();
+                Ok((name.clone(), formatted))
+            })
+            .collect()
+    }
+
+    /// Returns the names of all functions defined in this context, sorted
+    /// alphabetically. This includes lambdas assigned to variables and
+    /// functions added via [`Context::register_function`]. Built-in
+    /// functions like `sqrt` are only included if `include_builtins` is true.
+    #[must_use]
+    pub fn functions(&self, include_builtins: bool) -> Vec<String> {
+        let mut functions = self
+            .variables
+            .iter()
+            .filter(|(name, value)| {
+                value.is_fn() && (include_builtins || !is_builtin_variable(name))
+            })
+            .map(|(name, _)| name.as_str())
+            .chain(self.registered_functions.keys().map(String::as_str))
+            .collect::<Vec<_>>();
+        if include_builtins {
+            functions.extend(ast::BUILTIN_FUNCTION_NAMES);
+        }
+        functions.sort_unstable();
+        functions.dedup();
+        functions.into_iter().map(ToString::to_string).collect()
+    }
+
+    fn load_definition(&mut self, line: &str) -> Result<(), FendError> {
+        if let Some(unit) = line.strip_prefix("unit ") {
+            let (names, definition) = unit
+                .split_once('=')
+                .ok_or(FendError::InvalidUnitDefinition)?;
+            let mut names = names.split_whitespace();
+            let singular = names.next().ok_or(FendError::InvalidUnitDefinition)?;
+            let plural = names.next().unwrap_or("");
+            if names.next().is_some() {
+                return Err(FendError::InvalidUnitDefinition);
+            }
+            self.define_unit_internal(singular, plural, definition.trim())
+        } else {
+            eval::evaluate_for_side_effects(line, self, &interrupt::Never::default())
+        }
+    }
+
+    /// Explains how the given input is evaluated, step by step. This includes
+    /// the parsed form of the input, any operators and unit conversions that
+    /// are applied, and the final result. For example, `2 km + 300 m`
+    /// involves converting `300 m` to `0.3 km` and then adding it to `2 km`.
+    ///
+    /// This is a dry run: the context (e.g. variables and the input history)
+    /// is not modified.
+    ///
+    /// # Errors
+    /// It returns an error if the given string is invalid.
+    /// This may be due to parser or runtime errors.
+    pub fn explain(&self, input: &str) -> Result<Explanation, String> {
+        let mut context = self.clone();
+        eval::explain(input, &mut context, &interrupt::Never::default()).map_err(|e| e.to_string())
+    }
+
+    /// Parses the given input without evaluating it, and returns it in a
+    /// normalized form that shows how it was interpreted. Operators are
+    /// surrounded by spaces, and operands that aren't a single number or
+    /// identifier are wrapped in parentheses, so `1+2*3` becomes
+    /// `1 + (2 * 3)` and `-2^2` becomes `-(2 ^ 2)`.
+    ///
+    /// # Errors
+    /// Returns an error if the input cannot be parsed.
+    pub fn canonical_input(&self, input: &str) -> Result<String, String> {
+        eval::canonical_input(input, self, &interrupt::Never::default()).map_err(|e| e.to_string())
+    }
+
+    /// Loads a block of definitions, one per line. Lines of the form
+    /// `unit <singular> [<plural>] = <definition>` define custom units
+    /// (see [`Self::define_unit`]), while all other lines are evaluated
+    /// normally, so they can be used to define variables and functions
+    /// (e.g. `taxrate = 0.15` or `double = x: 2x`). Empty lines and
+    /// comments starting with `#` are skipped.
+    ///
+    /// # Errors
+    /// Stops at the first line that fails to evaluate, and returns an error
+    /// message containing its (1-based) line number.
+    pub fn load_definitions(&mut self, definitions: &str) -> Result<(), String> {
+        for (idx, line) in definitions.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.load_definition(line)
+                .map_err(|e| format!("line {}: {e}", idx + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates the given string like [`evaluate`], and returns the
+    /// outcome as a JSON object, so that tools don't need to parse the
+    /// human-readable output. For example, `2 km` returns:
+    ///
+    /// ```json
+    /// {
+    ///   "ok": true,
+    ///   "result": "2 km",
+    ///   "approximate": false,
+    ///   "components": [{"number": "2", "unit": "km"}],
+    ///   "other_info": [],
+    ///   "error": null
+    /// }
+    /// ```
+    ///
+    /// (the actual output contains no line breaks). The fields are:
+    ///
+    /// * `ok`: whether the evaluation succeeded
+    /// * `result`: the main result, or `null` if there was an error
+    /// * `approximate`: whether the result is an approximation
+    /// * `components`: the numbers in the result, each with its unit (which
+    ///   is an empty string for unitless numbers). This is empty for results
+    ///   that aren't numbers, like strings or booleans.
+    /// * `other_info`: additional notes, see [`FendResult::get_other_info`]
+    /// * `error`: the error message, or `null` if the evaluation succeeded
+    pub fn evaluate_json(&mut self, input: &str) -> String {
+        let mut out = String::new();
+        match evaluate(input, self) {
+            Ok(result) => result.write_json(&mut out),
+            Err(error) => {
+                out.push_str("{\"ok\": false, \"result\": null, \"approximate\": false, ");
+                out.push_str("\"components\": [], \"other_info\": [], \"error\": \"");
+                json::escape_string(&error, &mut out);
+                out.push_str("\"}");
+            }
+        }
+        out
     }
 }
 
@@ -304,28 +1539,98 @@ pub fn evaluate(input: &str, context: &mut Context) -> Result<FendResult, String
     evaluate_with_interrupt(input, context, &interrupt::Never::default())
 }
 
+/// This function evaluates a string using the given context, and additionally
+/// returns timing information for each evaluation phase. This is useful
+/// for finding out why a particular calculation is slow.
+///
+/// Note that this function relies on [`std::time::Instant`], which is not
+/// available on all platforms (e.g. `wasm32-unknown-unknown`).
+///
+/// # Errors
+/// The result is an error if the given string is invalid.
+/// This may be due to parser or runtime errors.
+pub fn evaluate_with_diagnostics(
+    input: &str,
+    context: &mut Context,
+) -> (Result<FendResult, String>, Diagnostics) {
+    let mut diagnostics = Diagnostics::default();
+    let result = evaluate_with_interrupt_internal(
+        input,
+        context,
+        &interrupt::Never::default(),
+        Some(&mut diagnostics),
+    )
+    .map_err(|e| context.format_error(&e));
+    (result, diagnostics)
+}
+
+// `_` and `ans` are set automatically after each calculation
+fn is_builtin_variable(name: &str) -> bool {
+    name == "_" || name == "ans"
+}
+
 fn evaluate_with_interrupt_internal(
     input: &str,
     context: &mut Context,
     int: &impl Interrupt,
-) -> Result<FendResult, String> {
+    diagnostics: Option<&mut Diagnostics>,
+) -> Result<FendResult, FendError> {
+    context.add_to_input_history(input);
     if input.is_empty() {
         // no or blank input: return no output
         return Ok(FendResult::empty());
     }
-    let (result, is_unit) = match eval::evaluate_to_spans(input, None, context, int) {
-        Ok(value) => value,
-        Err(e) => return Err(e.to_string()),
-    };
+    // timings are only meaningful if the input is actually evaluated
+    let use_cache = context.result_cache_size > 0 && diagnostics.is_none();
+    if use_cache {
+        if let Some(result) = context.get_cached_result(input) {
+            return Ok(result);
+        }
+    }
+    context.result_is_cacheable = true;
+    let (result, is_unit, is_approximate) =
+        eval::evaluate_to_spans(input, None, context, int, diagnostics)?;
+    let mut result = result;
+    let mut other_info = vec![];
+    if truncate_spans(&mut result, context.max_digits) {
+        other_info.push(format!(
+            "output truncated to {} characters",
+            context.max_digits
+        ));
+    }
     let mut plain_result = String::new();
     for s in &result {
         plain_result.push_str(&s.string);
     }
-    Ok(FendResult {
+    let result = FendResult {
         plain_result,
         span_result: result,
         is_unit,
-    })
+        is_approximate,
+        other_info,
+        newline_policy: context.newline_policy,
+    };
+    if use_cache && context.result_is_cacheable {
+        context.cache_result(input, &result);
+    }
+    Ok(result)
+}
+
+/// Shortens the given spans to at most `max_chars` characters, followed by
+/// an ellipsis. Returns whether any characters were removed.
+fn truncate_spans(spans: &mut Vec<Span>, max_chars: usize) -> bool {
+    let mut remaining = max_chars;
+    for (idx, span) in spans.iter_mut().enumerate() {
+        let Some((byte_idx, _)) = span.string.char_indices().nth(remaining) else {
+            remaining -= span.string.chars().count();
+            continue;
+        };
+        span.string.truncate(byte_idx);
+        span.string.push('\u{2026}');
+        spans.truncate(idx + 1);
+        return true;
+    }
+    false
 }
 
 /// This function evaluates a string using the given context and the provided
@@ -341,7 +1646,163 @@ pub fn evaluate_with_interrupt(
     context: &mut Context,
     int: &impl Interrupt,
 ) -> Result<FendResult, String> {
-    evaluate_with_interrupt_internal(input, context, int)
+    evaluate_with_interrupt_internal(input, context, int, None)
+        .map_err(|e| context.format_error(&e))
+}
+
+/// An error returned by [`evaluate_with_error_details`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvaluationError {
+    message: String,
+    position: Option<usize>,
+}
+
+impl EvaluationError {
+    /// The error message. This is the same message that would be returned
+    /// by [`evaluate`].
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The byte offset in the input where the problem was found, e.g. so
+    /// that frontends can underline it. This is only available for syntax
+    /// errors like `1 + )`, not for errors that occur during evaluation
+    /// like `1/0`.
+    #[must_use]
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for EvaluationError {}
+
+/// An error returned by [`Context::unit_dimension`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnitDimensionError {
+    /// There is no unit with the given name.
+    UnknownUnit,
+    /// The unit exists, but its dimensions couldn't be determined. This
+    /// contains the error message.
+    Other(String),
+}
+
+impl fmt::Display for UnitDimensionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownUnit => write!(f, "unknown unit"),
+            Self::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for UnitDimensionError {}
+
+mod from_value {
+    // wrappers around crate-private types, which can't be used directly
+    // in the public `Sealed` trait
+    pub struct Value(pub(crate) crate::value::Value);
+    pub struct Error(pub(crate) crate::FendError);
+
+    impl From<crate::FendError> for Error {
+        fn from(e: crate::FendError) -> Self {
+            Self(e)
+        }
+    }
+
+    pub trait Sealed: Sized {
+        fn from_value(value: Value, context: &crate::Context) -> Result<Self, Error>;
+    }
+}
+
+/// Types that results can be converted into using [`Context::evaluate_as`]:
+///
+/// * `f64`: numbers without a unit. Dimensionless units are converted
+///   away, so e.g. `50%` becomes `0.5`.
+/// * `i64`: exact integers without a unit. Fractions, approximate values
+///   and numbers that don't fit are errors.
+/// * `(f64, String)`: numbers along with their unit, e.g. `2.5 km` becomes
+///   `(2.5, "km")`. The unit is empty for unitless numbers.
+/// * `bool`: boolean results like `1 < 2`
+/// * `String`: any result, formatted the same way as by [`evaluate`]
+/// * [`Quantity`]: numbers along with their unit, without any loss of
+///   precision
+pub trait FromValue: from_value::Sealed {}
+
+impl from_value::Sealed for f64 {
+    fn from_value(value: from_value::Value, _context: &Context) -> Result<Self, from_value::Error> {
+        Ok(value
+            .0
+            .expect_num()?
+            .try_as_f64(&interrupt::Never::default())?)
+    }
+}
+impl FromValue for f64 {}
+
+impl from_value::Sealed for i64 {
+    fn from_value(value: from_value::Value, _context: &Context) -> Result<Self, from_value::Error> {
+        Ok(value
+            .0
+            .expect_num()?
+            .try_as_i64(&interrupt::Never::default())?)
+    }
+}
+impl FromValue for i64 {}
+
+impl from_value::Sealed for (f64, String) {
+    fn from_value(value: from_value::Value, context: &Context) -> Result<Self, from_value::Error> {
+        let int = interrupt::Never::default();
+        Ok(value.0.expect_num()?.try_as_f64_with_unit(context, &int)?)
+    }
+}
+impl FromValue for (f64, String) {}
+
+impl from_value::Sealed for bool {
+    fn from_value(value: from_value::Value, _context: &Context) -> Result<Self, from_value::Error> {
+        Ok(value.0.as_bool()?)
+    }
+}
+impl FromValue for bool {}
+
+impl from_value::Sealed for String {
+    fn from_value(value: from_value::Value, context: &Context) -> Result<Self, from_value::Error> {
+        let int = interrupt::Never::default();
+        Ok(value
+            .0
+            .format_to_plain_string(0, Attrs::default(), context, &int)?)
+    }
+}
+impl FromValue for String {}
+
+impl from_value::Sealed for Quantity {
+    fn from_value(value: from_value::Value, _context: &Context) -> Result<Self, from_value::Error> {
+        Ok(Self(value.0.expect_num()?))
+    }
+}
+impl FromValue for Quantity {}
+
+/// This function evaluates a string like [`evaluate_with_interrupt`], but
+/// returns more details on errors, such as where in the input they occurred.
+///
+/// # Errors
+/// It returns an error if the given string is invalid.
+/// This may be due to parser or runtime errors.
+pub fn evaluate_with_error_details(
+    input: &str,
+    context: &mut Context,
+    int: &impl Interrupt,
+) -> Result<FendResult, EvaluationError> {
+    evaluate_with_interrupt_internal(input, context, int, None).map_err(|e| EvaluationError {
+        message: context.format_error(&e),
+        position: e.position(),
+    })
 }
 
 /// Evaluate the given string to use as a live preview.
@@ -363,7 +1824,7 @@ pub fn evaluate_preview_with_interrupt(
     let context_clone = context.clone();
     context.random_u32 = None;
     context.get_exchange_rate = None;
-    let result = evaluate_with_interrupt_internal(input, context, int);
+    let result = evaluate_with_interrupt_internal(input, context, int, None);
     *context = context_clone;
     let result = match result {
         Ok(result) => result,
@@ -419,14 +1880,90 @@ pub fn get_completions_for_prefix(mut prefix: &str) -> (usize, Vec<Completion>)
 
 pub use inline_substitutions::substitute_inline_fend_expressions;
 
-const fn get_version_as_str() -> &'static str {
-    env!("CARGO_PKG_VERSION")
+/// A `fend-core` version number, as returned by [`version`]. Versions can
+/// be compared, e.g. to check whether a feature is available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// Pre-release identifier (e.g. `beta.1`), or an empty string for
+    /// normal releases
+    pub pre: &'static str,
+    /// The date of the release, as listed in the changelog
+    pub release_date: ReleaseDate,
+}
+
+/// The date a `fend-core` version was released
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub struct ReleaseDate {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl fmt::Display for ReleaseDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+        Ok(())
+    }
+}
+
+// Updated by `contrib/deploy.sh` together with the version number
+const RELEASE_DATE: &str = "2022-10-31";
+
+const fn parse_version_component(s: &str) -> u32 {
+    parse_digits(s.as_bytes(), 0, s.len())
+}
+
+const fn parse_digits(bytes: &[u8], start: usize, end: usize) -> u32 {
+    let mut result = 0;
+    let mut i = start;
+    while i < end {
+        result = result * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    result
+}
+
+// parses a date in the format `YYYY-MM-DD`
+const fn parse_release_date(s: &str) -> ReleaseDate {
+    let bytes = s.as_bytes();
+    ReleaseDate {
+        year: parse_digits(bytes, 0, 4),
+        month: parse_digits(bytes, 5, 7),
+        day: parse_digits(bytes, 8, 10),
+    }
 }
 
 /// Returns the current version of `fend-core`.
 #[must_use]
+pub const fn version() -> Version {
+    Version {
+        major: parse_version_component(env!("CARGO_PKG_VERSION_MAJOR")),
+        minor: parse_version_component(env!("CARGO_PKG_VERSION_MINOR")),
+        patch: parse_version_component(env!("CARGO_PKG_VERSION_PATCH")),
+        pre: env!("CARGO_PKG_VERSION_PRE"),
+        release_date: parse_release_date(RELEASE_DATE),
+    }
+}
+
+/// Returns the current version of `fend-core` as a string, e.g. `1.1.2`.
+/// See [`version`] for a structured representation.
+#[must_use]
 pub fn get_version() -> String {
-    get_version_as_str().to_string()
+    version().to_string()
 }
 
 /// Used by unit and integration tests
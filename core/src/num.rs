@@ -18,6 +18,15 @@ pub(crate) type Number = unit::Value;
 pub(crate) type Base = base::Base;
 pub(crate) type Exact<T> = exact::Exact<T>;
 
+/// How to round a number to a multiple of some increment
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Rounding {
+    Floor,
+    Ceil,
+    /// Round according to the given mode, as used by `round`
+    Round(crate::RoundingMode),
+}
+
 #[derive(Debug)]
 pub(crate) enum RangeBound<T> {
     None,
@@ -48,6 +57,13 @@ impl<T> Range<T> {
             end: RangeBound::Open(end),
         }
     }
+
+    pub(crate) fn closed(start: T, end: T) -> Self {
+        Self {
+            start: RangeBound::Closed(start),
+            end: RangeBound::Closed(end),
+        }
+    }
 }
 
 impl Range<i32> {
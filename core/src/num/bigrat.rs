@@ -1,8 +1,9 @@
 use crate::error::{FendError, Interrupt};
 use crate::format::Format;
-use crate::interrupt::test_int;
+use crate::interrupt::{test_int, test_int_with_progress};
 use crate::num::biguint::BigUint;
-use crate::num::{Base, Exact, FormattingStyle, Range, RangeBound};
+use crate::num::{Base, Exact, FormattingStyle, Range, RangeBound, Rounding};
+use std::borrow::Cow;
 use std::{cmp, fmt, hash, io, ops};
 
 mod sign {
@@ -147,9 +148,51 @@ impl BigRat {
         self.num.try_as_usize(int)
     }
 
+    pub(crate) fn is_integer<I: Interrupt>(&self, int: &I) -> Result<bool, FendError> {
+        Ok(self.clone().simplify(int)?.den == 1.into())
+    }
+
+    /// Writes the square root of this number as `a * sqrt(b)`, where `b` is
+    /// a square-free integer. Returns `None` if the number is negative, or if
+    /// it is too large to be factorised quickly.
+    pub(crate) fn sqrt_as_surd<I: Interrupt>(
+        self,
+        int: &I,
+    ) -> Result<Option<(Self, Self)>, FendError> {
+        if self.sign == Sign::Negative && self.num != 0.into() {
+            return Ok(None);
+        }
+        let x = self.simplify(int)?;
+        // sqrt(a/b) == sqrt(a*b)/b
+        let Some(mut radicand) = x.num.mul(&x.den, int)?.try_as_u64() else {
+            return Ok(None);
+        };
+        if radicand > MAX_SURD_RADICAND {
+            return Ok(None);
+        }
+        let mut factor: u64 = 1;
+        let mut i: u64 = 2;
+        while i * i <= radicand {
+            test_int(int)?;
+            if radicand % (i * i) == 0 {
+                radicand /= i * i;
+                factor *= i;
+            } else {
+                i += 1;
+            }
+        }
+        Ok(Some((
+            Self::from(factor).div(&Self::from(x.den), int)?,
+            Self::from(radicand),
+        )))
+    }
+
     pub(crate) fn into_f64<I: Interrupt>(mut self, int: &I) -> Result<f64, FendError> {
         self = self.simplify(int)?;
         let positive_result = self.num.as_f64() / self.den.as_f64();
+        if !positive_result.is_finite() {
+            return Err(FendError::NumberTooLargeToApproximate);
+        }
         if self.sign == Sign::Positive {
             Ok(positive_result)
         } else {
@@ -163,10 +206,33 @@ impl BigRat {
         clippy::cast_precision_loss
     )]
     pub(crate) fn from_f64<I: Interrupt>(mut f: f64, int: &I) -> Result<Self, FendError> {
+        if f.is_nan() {
+            return Err(FendError::UndefinedResult);
+        }
+        if f.is_infinite() {
+            return Err(FendError::NumberTooLargeToApproximate);
+        }
         let negative = f < 0.0;
         if negative {
             f = -f;
         }
+        if f >= 18_446_744_073_709_551_616.0 {
+            // floats this large are integers, so they can be converted exactly
+            // rather than being multiplied by u64::MAX (which would overflow)
+            let bits = f.to_bits();
+            let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+            let exponent = ((bits >> 52) & 0x7ff) - 1075;
+            let num = BigUint::from(mantissa).lshift_n(&BigUint::from(exponent), int)?;
+            return Ok(Self {
+                sign: if negative {
+                    Sign::Negative
+                } else {
+                    Sign::Positive
+                },
+                num,
+                den: BigUint::from(1),
+            });
+        }
         let i = (f * u64::MAX as f64) as u128;
         let part1 = i as u64;
         let part2 = (i >> 64) as u64;
@@ -195,7 +261,7 @@ impl BigRat {
     pub(crate) fn asin<I: Interrupt>(self, int: &I) -> Result<Self, FendError> {
         let one = Self::from(1);
         if self > one || self < -one {
-            return Err(out_of_range(self.fm(int)?, Range::open(-1, 1)));
+            return Err(out_of_range(self.fm(int)?, Range::closed(-1, 1)));
         }
         Self::from_f64(f64::asin(self.into_f64(int)?), int)
     }
@@ -203,7 +269,7 @@ impl BigRat {
     pub(crate) fn acos<I: Interrupt>(self, int: &I) -> Result<Self, FendError> {
         let one = Self::from(1);
         if self > one || self < -one {
-            return Err(out_of_range(self.fm(int)?, Range::open(-1, 1)));
+            return Err(out_of_range(self.fm(int)?, Range::closed(-1, 1)));
         }
         Self::from_f64(f64::acos(self.into_f64(int)?), int)
     }
@@ -391,7 +457,7 @@ impl BigRat {
         })
     }
 
-    fn simplify<I: Interrupt>(mut self, int: &I) -> Result<Self, FendError> {
+    pub(crate) fn simplify<I: Interrupt>(mut self, int: &I) -> Result<Self, FendError> {
         if self.den == 1.into() {
             return Ok(self);
         }
@@ -436,6 +502,121 @@ impl BigRat {
         })
     }
 
+    /// Decides whether `whole + remainder / den` (where `remainder` is
+    /// non-zero and less than `den`) should be rounded away from zero
+    fn rounds_away_from_zero<I: Interrupt>(
+        mode: crate::RoundingMode,
+        whole: &BigUint,
+        remainder: &BigUint,
+        den: &BigUint,
+        int: &I,
+    ) -> Result<bool, FendError> {
+        let twice_remainder = remainder.clone().mul(&2.into(), int)?;
+        Ok(match mode {
+            crate::RoundingMode::HalfUp => &twice_remainder >= den,
+            crate::RoundingMode::HalfEven => {
+                &twice_remainder > den || (&twice_remainder == den && !whole.is_even(int)?)
+            }
+            crate::RoundingMode::TowardZero => false,
+            crate::RoundingMode::AwayFromZero => true,
+        })
+    }
+
+    /// Rounds to a multiple of `increment`, e.g. 17 rounded to the nearest
+    /// multiple of 5 is 15
+    pub(crate) fn round_to_multiple<I: Interrupt>(
+        self,
+        increment: &Self,
+        rounding: Rounding,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        if increment.num == 0.into() {
+            return Err(FendError::RoundingToZeroIncrement);
+        }
+        let quotient = self.div(increment, int)?;
+        let (whole, remainder) = quotient.num.divmod(&quotient.den, int)?;
+        let round_away_from_zero = remainder != 0.into()
+            && match rounding {
+                Rounding::Floor => quotient.sign == Sign::Negative,
+                Rounding::Ceil => quotient.sign == Sign::Positive,
+                Rounding::Round(mode) => {
+                    Self::rounds_away_from_zero(mode, &whole, &remainder, &quotient.den, int)?
+                }
+            };
+        let whole = if round_away_from_zero {
+            whole.add(&1.into())
+        } else {
+            whole
+        };
+        let sign = if whole == 0.into() {
+            Sign::Positive
+        } else {
+            quotient.sign
+        };
+        Self {
+            sign,
+            num: whole,
+            den: 1.into(),
+        }
+        .mul(increment, int)
+    }
+
+    /// Finds the fraction closest to `self` with a denominator of at most
+    /// `max_den`, using the convergents and semiconvergents of its
+    /// continued fraction expansion, e.g. for pi and 1000 this is 355/113
+    pub(crate) fn best_rational_approximation<I: Interrupt>(
+        self,
+        max_den: usize,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        if max_den == 0 {
+            return Err(FendError::ZeroMaxDenominator);
+        }
+        let max_den = BigUint::from(max_den as u64);
+        let value = self.simplify(int)?;
+        if value.den <= max_den {
+            return Ok(value);
+        }
+        // p0/q0 and p1/q1 are the two most recent convergents
+        let (mut p0, mut q0) = (BigUint::from(0), BigUint::from(1));
+        let (mut p1, mut q1) = (BigUint::from(1), BigUint::from(0));
+        let (mut n, mut d) = (value.num.clone(), value.den.clone());
+        loop {
+            test_int(int)?;
+            let (a, r) = n.divmod(&d, int)?;
+            let q2 = q0.clone().add(&a.clone().mul(&q1, int)?);
+            if q2 > max_den {
+                break;
+            }
+            let p2 = p0.add(&a.mul(&p1, int)?);
+            (p0, q0) = (p1, q1);
+            (p1, q1) = (p2, q2);
+            (n, d) = (d, r);
+        }
+        // the best semiconvergent that still fits within `max_den`
+        let k = max_den.sub(&q0).divmod(&q1, int)?.0;
+        let semiconvergent = Self {
+            sign: value.sign,
+            num: p0.add(&k.clone().mul(&p1, int)?),
+            den: q0.add(&k.mul(&q1, int)?),
+        };
+        let convergent = Self {
+            sign: value.sign,
+            num: p1,
+            den: q1,
+        };
+        let distance = |x: &Self| -> Result<Self, FendError> {
+            let mut diff = x.clone().add(-value.clone(), int)?;
+            diff.sign = Sign::Positive;
+            Ok(diff)
+        };
+        if distance(&convergent)? <= distance(&semiconvergent)? {
+            convergent.simplify(int)
+        } else {
+            semiconvergent.simplify(int)
+        }
+    }
+
     // test if this fraction has a terminating representation
     // e.g. in base 10: 1/4 = 0.25, but not 1/3
     fn terminates_in_base<I: Interrupt>(&self, base: Base, int: &I) -> Result<bool, FendError> {
@@ -461,7 +642,7 @@ impl BigRat {
         num: &BigUint,
         base: Base,
         sign: Sign,
-        term: &'static str,
+        term: Cow<'static, str>,
         use_parens_if_product: bool,
         sf_limit: Option<usize>,
         int: &I,
@@ -477,25 +658,22 @@ impl BigRat {
                 },
                 int,
             )?;
+            let space = !term.is_empty() && base.base_as_u8() > 10;
+            // print surrounding parentheses if the number is imaginary
+            let use_parens = use_parens_if_product && !term.is_empty();
             (
-                FormattedBigRatType::Integer(
-                    Some(formatted_int.value),
-                    !term.is_empty() && base.base_as_u8() > 10,
-                    term,
-                    // print surrounding parentheses if the number is imaginary
-                    use_parens_if_product && !term.is_empty(),
-                ),
+                FormattedBigRatType::Integer(Some(formatted_int.value), space, term, use_parens),
                 formatted_int.exact,
             )
         };
-        Ok(Exact::new(FormattedBigRat { sign, ty }, exact))
+        Ok(Exact::new(FormattedBigRat::new(sign, ty), exact))
     }
 
     fn format_as_fraction<I: Interrupt>(
         &self,
         base: Base,
         sign: Sign,
-        term: &'static str,
+        term: Cow<'static, str>,
         mixed: bool,
         use_parens: bool,
         int: &I,
@@ -528,19 +706,18 @@ impl BigRat {
                         false,
                         term,
                         formatted_den.value,
-                        "",
+                        "".into(),
                         use_parens,
                     ),
                     true,
                 )
             } else {
                 let formatted_num = num.format(&format_options, int)?;
-                let i_suffix = term;
                 let space = !term.is_empty() && (base.base_as_u8() >= 19 || actually_mixed);
                 let (isuf1, isuf2) = if actually_mixed {
-                    ("", i_suffix)
+                    ("".into(), term)
                 } else {
-                    (i_suffix, "")
+                    (term, "".into())
                 };
                 (
                     FormattedBigRatType::Fraction(
@@ -556,7 +733,7 @@ impl BigRat {
                 )
             };
         Ok(Exact::new(
-            FormattedBigRat { sign, ty },
+            FormattedBigRat::new(sign, ty),
             formatted_den.exact && prefix_exact && num_exact,
         ))
     }
@@ -566,7 +743,7 @@ impl BigRat {
         style: FormattingStyle,
         base: Base,
         sign: Sign,
-        term: &'static str,
+        term: Cow<'static, str>,
         mut terminating: impl FnMut() -> Result<bool, FendError>,
         int: &I,
     ) -> Result<Exact<FormattedBigRat>, FendError> {
@@ -643,18 +820,152 @@ impl BigRat {
             int,
         )?;
         Ok(Exact::new(
-            FormattedBigRat {
+            FormattedBigRat::new(
                 sign,
-                ty: FormattedBigRatType::Decimal(
+                FormattedBigRatType::Decimal(
                     formatted_trailing_digits.value,
                     !term.is_empty() && base.base_as_u8() > 10,
                     term,
                 ),
-            },
+            ),
             formatted_integer_part.exact && formatted_trailing_digits.exact,
         ))
     }
 
+    // When printing a positive number with `dp` digits after the decimal
+    // point, digits are normally just truncated. If the rounding mode
+    // requires rounding up instead, this returns the rounded-up number
+    // as a multiple of the last decimal place.
+    fn round_up_to_decimal_places<I: Interrupt>(
+        &self,
+        base: Base,
+        dp: usize,
+        mode: crate::RoundingMode,
+        int: &I,
+    ) -> Result<Option<BigUint>, FendError> {
+        let base_as_u64: u64 = base.base_as_u8().into();
+        let scale = BigUint::pow(&base_as_u64.into(), &(dp as u64).into(), int)?;
+        let (whole, remainder) = self.num.clone().mul(&scale, int)?.divmod(&self.den, int)?;
+        if remainder == 0.into()
+            || !Self::rounds_away_from_zero(mode, &whole, &remainder, &self.den, int)?
+        {
+            return Ok(None);
+        }
+        Ok(Some(whole.add(&1.into())))
+    }
+
+    // Formats `units` (a multiple of the last decimal place) with up
+    // to `dp` decimal places. The result is always approximate.
+    fn format_rounded_decimal<I: Interrupt>(
+        units: &BigUint,
+        base: Base,
+        dp: usize,
+        sign: Sign,
+        term: Cow<'static, str>,
+        int: &I,
+    ) -> Result<Exact<FormattedBigRat>, FendError> {
+        let base_as_u64: u64 = base.base_as_u8().into();
+        let scale = BigUint::pow(&base_as_u64.into(), &(dp as u64).into(), int)?;
+        let (integer_part, fraction) = units.divmod(&scale, int)?;
+        let mut formatted = integer_part
+            .format(
+                &biguint::FormatOptions {
+                    base,
+                    write_base_prefix: true,
+                    sf_limit: None,
+                },
+                int,
+            )?
+            .value
+            .to_string();
+        if fraction != 0.into() {
+            let digits = fraction
+                .format(
+                    &biguint::FormatOptions {
+                        base,
+                        write_base_prefix: false,
+                        sf_limit: None,
+                    },
+                    int,
+                )?
+                .value
+                .to_string();
+            formatted.push('.');
+            for _ in digits.len()..dp {
+                formatted.push('0');
+            }
+            formatted.push_str(digits.trim_end_matches('0'));
+        }
+        Ok(Exact::new(
+            FormattedBigRat::new(
+                sign,
+                FormattedBigRatType::Decimal(
+                    formatted,
+                    !term.is_empty() && base.base_as_u8() > 10,
+                    term,
+                ),
+            ),
+            false,
+        ))
+    }
+
+    // Formats a positive number as e.g. 1.23e4, with a mantissa
+    // that's at least 1 and less than 10
+    /// The number of decimal digits in `n`
+    fn num_digits<I: Interrupt>(n: &BigUint, int: &I) -> Result<usize, FendError> {
+        Ok(n.format(&biguint::FormatOptions::default(), int)?
+            .value
+            .num_digits())
+    }
+
+    fn format_as_scientific<I: Interrupt>(
+        &self,
+        sign: Sign,
+        term: Cow<'static, str>,
+        mantissa_options: &FormatOptions,
+        int: &I,
+    ) -> Result<Exact<FormattedBigRat>, FendError> {
+        let num_digits_of_num = Self::num_digits(&self.num, int)?;
+        let num_digits_of_den = Self::num_digits(&self.den, int)?;
+        let (mut negative_exponent, mut exponent) = if num_digits_of_num >= num_digits_of_den {
+            (false, num_digits_of_num - num_digits_of_den)
+        } else {
+            (true, num_digits_of_den - num_digits_of_num)
+        };
+        let ten = Self::from(10);
+        let power_of_ten = Self::from(BigUint::pow(&10.into(), &(exponent as u64).into(), int)?);
+        let mut mantissa = if negative_exponent {
+            self.clone().mul(&power_of_ten, int)?
+        } else {
+            self.clone().div(&power_of_ten, int)?
+        };
+        // the mantissa is now greater than 0.1 and less than 10
+        if mantissa < 1.into() {
+            mantissa = mantissa.mul(&ten, int)?;
+            if negative_exponent {
+                exponent += 1;
+            } else if exponent == 0 {
+                negative_exponent = true;
+                exponent = 1;
+            } else {
+                exponent -= 1;
+            }
+        }
+        let formatted_mantissa = mantissa.format(mantissa_options, int)?;
+        let exponent_sign = if negative_exponent { "-" } else { "" };
+        Ok(Exact::new(
+            FormattedBigRat::new(
+                sign,
+                FormattedBigRatType::Decimal(
+                    format!("{}e{exponent_sign}{exponent}", formatted_mantissa.value),
+                    false,
+                    term,
+                ),
+            ),
+            formatted_mantissa.exact,
+        ))
+    }
+
     /// Prints the decimal expansion of num/den, where num < den, in the given base.
     fn format_trailing_digits<I: Interrupt>(
         base: Base,
@@ -667,9 +978,15 @@ impl BigRat {
     ) -> Result<(Sign, Exact<String>), FendError> {
         let base_as_u64: u64 = base.base_as_u8().into();
         let b: BigUint = base_as_u64.into();
+        let total_digits = match max_digits {
+            MaxDigitsToPrint::AllDigits => None,
+            MaxDigitsToPrint::DecimalPlaces(n) | MaxDigitsToPrint::DpButIgnoreLeadingZeroes(n) => {
+                Some(n as u64)
+            }
+        };
         let next_digit =
             |i: usize, num: BigUint, base: &BigUint| -> Result<(BigUint, BigUint), NextDigitErr> {
-                test_int(int)?;
+                test_int_with_progress(int, i as u64, total_digits)?;
                 if num == 0.into()
                     || max_digits == MaxDigitsToPrint::DecimalPlaces(i)
                     || max_digits == MaxDigitsToPrint::DpButIgnoreLeadingZeroes(i)
@@ -875,7 +1192,8 @@ impl BigRat {
     ) -> Result<Exact<Self>, FendError> {
         self = self.simplify(int)?;
         rhs = rhs.simplify(int)?;
-        if self.num != 0.into() && self.sign == Sign::Negative && rhs.den != 1.into() {
+        // odd roots of negative numbers are real, e.g. (-8)^(1/3) = -2
+        if self.num != 0.into() && self.sign == Sign::Negative && rhs.den.is_even(int)? {
             return Err(FendError::RootsOfNegativeNumbers);
         }
         if rhs.sign == Sign::Negative {
@@ -893,22 +1211,24 @@ impl BigRat {
             Sign::Negative
         };
         let pow_res = Self {
-            sign: result_sign,
+            sign: Sign::Positive,
             num: BigUint::pow(&self.num, &rhs.num, int)?,
             den: BigUint::pow(&self.den, &rhs.num, int)?,
         };
-        if rhs.den == 1.into() {
-            Ok(Exact::new(pow_res, true))
+        let mut res = if rhs.den == 1.into() {
+            Exact::new(pow_res, true)
         } else {
-            Ok(pow_res.root_n(
+            pow_res.root_n(
                 &Self {
                     sign: Sign::Positive,
                     num: rhs.den,
                     den: 1.into(),
                 },
                 int,
-            )?)
-        }
+            )?
+        };
+        res.value.sign = result_sign;
+        Ok(res)
     }
 
     /// n must be an integer
@@ -1063,12 +1383,43 @@ impl From<BigUint> for BigRat {
     }
 }
 
-#[derive(Default)]
+/// Square roots are only kept in their exact form (e.g. `2\u{221a}3`) if
+/// the radicand is at most this large, so that it can be factorised quickly
+const MAX_SURD_RADICAND: u64 = 1 << 32;
+
+/// Exact numbers smaller than 1e-50 are printed in scientific notation
+/// (e.g. `1e-300`) instead of with all their leading zeroes
+const SCIENTIFIC_THRESHOLD_EXPONENT: u64 = 50;
+
+/// How decimal numbers are written, e.g. `1,234.5` or `1.234,5`
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Separators {
+    pub(crate) thousands_style: crate::ThousandsStyle,
+    pub(crate) digit: char,
+    pub(crate) decimal: char,
+}
+
+impl Default for Separators {
+    fn default() -> Self {
+        Self {
+            thousands_style: crate::ThousandsStyle::None,
+            digit: ',',
+            decimal: '.',
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub(crate) struct FormatOptions {
     pub(crate) base: Base,
     pub(crate) style: FormattingStyle,
-    pub(crate) term: &'static str,
+    pub(crate) term: Cow<'static, str>,
     pub(crate) use_parens_if_fraction: bool,
+    pub(crate) rounding_mode: crate::RoundingMode,
+    /// Integers with more digits than this are printed in scientific
+    /// notation, with an approximated mantissa
+    pub(crate) integer_digit_limit: Option<usize>,
+    pub(crate) separators: Separators,
 }
 
 impl Format for BigRat {
@@ -1083,9 +1434,21 @@ impl Format for BigRat {
         params: &Self::Params,
         int: &I,
     ) -> Result<Exact<Self::Out>, FendError> {
+        let mut res = self.format_without_separators(params, int)?;
+        res.value.separators = params.separators;
+        Ok(res)
+    }
+}
+
+impl BigRat {
+    fn format_without_separators<I: Interrupt>(
+        &self,
+        params: &FormatOptions,
+        int: &I,
+    ) -> Result<Exact<FormattedBigRat>, FendError> {
         let base = params.base;
-        let style = params.style;
-        let term = params.term;
+        let mut style = params.style;
+        let term = params.term.clone();
         let use_parens_if_fraction = params.use_parens_if_fraction;
 
         let mut x = self.clone().simplify(int)?;
@@ -1096,6 +1459,47 @@ impl Format for BigRat {
         };
         x.sign = Sign::Positive;
 
+        if style == FormattingStyle::Scientific {
+            // `e` is a digit in bases above 10, so only use
+            // scientific notation for decimal numbers
+            if base != Base::default() {
+                style = FormattingStyle::Auto;
+            } else if x != 0.into() {
+                return x.format_as_scientific(sign, term, &FormatOptions::default(), int);
+            }
+        }
+
+        // very small numbers would otherwise be printed with a huge number
+        // of leading zeroes, so use scientific notation instead
+        if style == FormattingStyle::Auto
+            && base == Base::default()
+            && x != 0.into()
+            && x.num.clone().mul(
+                &BigUint::pow(&10.into(), &SCIENTIFIC_THRESHOLD_EXPONENT.into(), int)?,
+                int,
+            )? < x.den
+            && x.terminates_in_base(base, int)?
+        {
+            return x.format_as_scientific(sign, term, &FormatOptions::default(), int);
+        }
+
+        if let Some(limit) = params.integer_digit_limit {
+            if style == FormattingStyle::Auto
+                && base == Base::default()
+                && x.den == 1.into()
+                && x != 0.into()
+                && Self::num_digits(&x.num, int)? > limit
+            {
+                // approximations are truncated rather than rounded
+                let mantissa_options = FormatOptions {
+                    style: FormattingStyle::DecimalPlaces(10),
+                    rounding_mode: crate::RoundingMode::TowardZero,
+                    ..FormatOptions::default()
+                };
+                return x.format_as_scientific(sign, term, &mantissa_options, int);
+            }
+        }
+
         // try as integer if possible
         if x.den == 1.into() {
             let sf_limit = if let FormattingStyle::SignificantFigures(sf) = style {
@@ -1132,6 +1536,13 @@ impl Format for BigRat {
         }
 
         // not a fraction, will be printed as a decimal
+        if let FormattingStyle::DecimalPlaces(dp) = style {
+            if let Some(units) =
+                x.round_up_to_decimal_places(base, dp, params.rounding_mode, int)?
+            {
+                return Self::format_rounded_decimal(&units, base, dp, sign, term, int);
+            }
+        }
         x.format_as_decimal(style, base, sign, term, terminating, int)
     }
 }
@@ -1142,7 +1553,7 @@ enum FormattedBigRatType {
     // bool whether to add a space before the string
     // followed by a string (empty, "i" or "pi"),
     // followed by whether to wrap the number in parentheses
-    Integer(Option<FormattedBigUint>, bool, &'static str, bool),
+    Integer(Option<FormattedBigUint>, bool, Cow<'static, str>, bool),
     // optional int (for mixed fractions)
     // optional int (numerator)
     // space
@@ -1155,15 +1566,15 @@ enum FormattedBigRatType {
         Option<FormattedBigUint>,
         Option<FormattedBigUint>,
         bool,
-        &'static str,
+        Cow<'static, str>,
         FormattedBigUint,
-        &'static str,
+        Cow<'static, str>,
         bool,
     ),
     // string representation of decimal number (may or may not contain recurring digits)
     // space
     // string (empty, "i", "pi", etc.)
-    Decimal(String, bool, &'static str),
+    Decimal(String, bool, Cow<'static, str>),
 }
 
 #[must_use]
@@ -1172,6 +1583,51 @@ pub(crate) struct FormattedBigRat {
     // whether or not to print a minus sign
     sign: Sign,
     ty: FormattedBigRatType,
+    separators: Separators,
+}
+
+impl FormattedBigRat {
+    // separators are set by `BigRat::format`
+    fn new(sign: Sign, ty: FormattedBigRatType) -> Self {
+        Self {
+            sign,
+            ty,
+            separators: Separators::default(),
+        }
+    }
+
+    fn grouped<'a>(&self, digits: &'a str) -> Cow<'a, str> {
+        group_digits(
+            digits,
+            self.separators.thousands_style,
+            self.separators.digit,
+        )
+    }
+}
+
+/// Inserts separators between groups of digits, e.g. `1,234,567` or `12,34,567`
+fn group_digits(
+    digits: &str,
+    thousands_style: crate::ThousandsStyle,
+    separator: char,
+) -> Cow<'_, str> {
+    let group_len = match thousands_style {
+        crate::ThousandsStyle::None => return digits.into(),
+        crate::ThousandsStyle::Western => 3,
+        crate::ThousandsStyle::Indian => 2,
+    };
+    if digits.len() <= 3 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return digits.into();
+    }
+    let (mut rest, last_group) = digits.split_at(digits.len() - 3);
+    let mut groups = vec![last_group];
+    while !rest.is_empty() {
+        let (remaining, group) = rest.split_at(rest.len().saturating_sub(group_len));
+        groups.push(group);
+        rest = remaining;
+    }
+    groups.reverse();
+    groups.join(separator.encode_utf8(&mut [0; 4])).into()
 }
 
 impl fmt::Display for FormattedBigRat {
@@ -1185,7 +1641,7 @@ impl fmt::Display for FormattedBigRat {
                     write!(f, "(")?;
                 }
                 if let Some(int) = int {
-                    write!(f, "{int}")?;
+                    write!(f, "{}", self.grouped(&int.to_string()))?;
                 }
                 if *space {
                     write!(f, " ")?;
@@ -1200,15 +1656,17 @@ impl fmt::Display for FormattedBigRat {
                     write!(f, "(")?;
                 }
                 if let Some(integer) = integer {
-                    write!(f, "{integer} ")?;
+                    let integer = integer.to_string();
+                    write!(f, "{} ", self.grouped(&integer))?;
                 }
                 if let Some(num) = num {
-                    write!(f, "{num}")?;
+                    write!(f, "{}", self.grouped(&num.to_string()))?;
                 }
                 if *space && !isuf.is_empty() {
                     write!(f, " ")?;
                 }
-                write!(f, "{isuf}/{den}")?;
+                let den = den.to_string();
+                write!(f, "{isuf}/{}", self.grouped(&den))?;
                 if *space && !isuf2.is_empty() {
                     write!(f, " ")?;
                 }
@@ -1218,7 +1676,19 @@ impl fmt::Display for FormattedBigRat {
                 }
             }
             FormattedBigRatType::Decimal(s, space, term) => {
-                write!(f, "{s}")?;
+                let decimal_separator = self.separators.decimal;
+                match s.split_once('.') {
+                    // numbers in scientific notation aren't grouped
+                    Some((integer, fraction)) if s.contains('e') => {
+                        write!(f, "{integer}{decimal_separator}{fraction}")?;
+                    }
+                    _ if s.contains('e') => write!(f, "{s}")?,
+                    Some((integer, fraction)) => {
+                        let integer = self.grouped(integer);
+                        write!(f, "{integer}{decimal_separator}{fraction}")?;
+                    }
+                    None => write!(f, "{}", self.grouped(s))?,
+                }
                 if *space {
                     write!(f, " ")?;
                 }
@@ -1,6 +1,6 @@
 use crate::error::{FendError, Interrupt};
 use crate::format::Format;
-use crate::interrupt::test_int;
+use crate::interrupt::{test_int, test_int_with_progress};
 use crate::num::{out_of_range, Base, Exact, Range, RangeBound};
 use crate::serialize::{
     deserialize_u64, deserialize_u8, deserialize_usize, serialize_u64, serialize_u8,
@@ -70,6 +70,20 @@ impl BigUint {
         }
     }
 
+    /// Returns this number as a `u64`, or `None` if it is too large
+    pub(crate) fn try_as_u64(&self) -> Option<u64> {
+        match self {
+            Small(n) => Some(*n),
+            Large(v) => {
+                if v.iter().skip(1).all(|&digit| digit == 0) {
+                    Some(v.first().copied().unwrap_or(0))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
     pub(crate) fn try_as_usize<I: Interrupt>(&self, int: &I) -> Result<usize, FendError> {
         let error = || -> Result<_, FendError> {
             Ok(out_of_range(
@@ -178,9 +192,7 @@ impl BigUint {
     }
 
     pub(crate) fn pow<I: Interrupt>(a: &Self, b: &Self, int: &I) -> Result<Self, FendError> {
-        if a.is_zero() && b.is_zero() {
-            return Err(FendError::ZeroToThePowerOfZero);
-        }
+        // by convention, 0^0 = 1
         if b.is_zero() {
             return Ok(Self::from(1));
         }
@@ -360,8 +372,14 @@ impl BigUint {
     // Note: 0! = 1, 1! = 1
     pub(crate) fn factorial<I: Interrupt>(mut self, int: &I) -> Result<Self, FendError> {
         let mut res = Self::from(1);
+        let total = match self {
+            Small(n) => Some(n),
+            Large(_) => None,
+        };
+        let mut step = 0;
         while self > 1.into() {
-            test_int(int)?;
+            test_int_with_progress(int, step, total)?;
+            step += 1;
             res = res.mul(&self, int)?;
             self = self.sub(&1.into());
         }
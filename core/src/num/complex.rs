@@ -1,7 +1,8 @@
 use crate::error::{FendError, Interrupt};
+use crate::num::bigrat;
 use crate::num::real::{self, Real};
 use crate::num::Exact;
-use crate::num::{Base, FormattingStyle};
+use crate::num::{Base, FormattingStyle, Rounding};
 use std::cmp::Ordering;
 use std::ops::Neg;
 use std::{fmt, io};
@@ -29,6 +30,65 @@ pub(crate) enum UseParentheses {
     IfComplexOrFraction,
 }
 
+#[derive(Copy, Clone)]
+pub(crate) struct FormatOptions {
+    pub(crate) style: FormattingStyle,
+    pub(crate) base: Base,
+    pub(crate) use_parentheses: UseParentheses,
+    pub(crate) imaginary_unit: crate::ImaginaryUnitSymbol,
+    pub(crate) complex_output: crate::ComplexOutput,
+    pub(crate) rounding_mode: crate::RoundingMode,
+    /// Only applies to real numbers, see [`bigrat::FormatOptions`]
+    pub(crate) integer_digit_limit: Option<usize>,
+    pub(crate) separators: bigrat::Separators,
+}
+
+impl FormatOptions {
+    /// Uses the formatting settings from the context
+    pub(crate) fn new(
+        style: FormattingStyle,
+        base: Base,
+        use_parentheses: UseParentheses,
+        ctx: &crate::Context,
+    ) -> Self {
+        Self {
+            style,
+            base,
+            use_parentheses,
+            imaginary_unit: ctx.imaginary_unit,
+            complex_output: ctx.complex_output,
+            rounding_mode: ctx.rounding_mode,
+            integer_digit_limit: ctx.integer_digit_limit,
+            // only decimal numbers are grouped
+            separators: if base == Base::default() {
+                bigrat::Separators {
+                    thousands_style: ctx.thousands_style,
+                    digit: ctx.digit_separator,
+                    decimal: ctx.decimal_separator,
+                }
+            } else {
+                bigrat::Separators::default()
+            },
+        }
+    }
+
+    fn real_options(
+        &self,
+        use_parens_if_fraction: bool,
+        integer_digit_limit: Option<usize>,
+    ) -> bigrat::FormatOptions {
+        bigrat::FormatOptions {
+            base: self.base,
+            style: self.style,
+            term: "".into(),
+            use_parens_if_fraction,
+            rounding_mode: self.rounding_mode,
+            integer_digit_limit,
+            separators: self.separators,
+        }
+    }
+}
+
 impl Complex {
     pub(crate) fn serialize(&self, write: &mut impl io::Write) -> Result<(), FendError> {
         self.real.serialize(write)?;
@@ -50,6 +110,26 @@ impl Complex {
         self.real.try_as_usize(int)
     }
 
+    /// Orders two real numbers. Complex numbers don't have an ordering,
+    /// so this returns an error if either number has an imaginary part.
+    pub(crate) fn compare(&self, other: &Self) -> Result<Ordering, FendError> {
+        if self.imag != 0.into() || other.imag != 0.into() {
+            return Err(FendError::ComplexComparison);
+        }
+        Ok(self.real.cmp(&other.real))
+    }
+
+    pub(crate) fn is_real(&self) -> bool {
+        self.imag == 0.into()
+    }
+
+    pub(crate) fn into_f64<I: Interrupt>(self, int: &I) -> Result<f64, FendError> {
+        if self.imag != 0.into() {
+            return Err(FendError::ExpectedARealNumber);
+        }
+        self.real.into_f64(int)
+    }
+
     pub(crate) fn conjugate(self) -> Self {
         Self {
             real: self.real,
@@ -68,6 +148,32 @@ impl Complex {
     }
 
     pub(crate) fn pow<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Exact<Self>, FendError> {
+        if self.imag != 0.into() && rhs.imag == 0.into() {
+            // integer powers of complex numbers, e.g. i^2 = -1
+            let negative = rhs.real < 0.into();
+            let exponent = if negative {
+                -rhs.real.clone()
+            } else {
+                rhs.real.clone()
+            };
+            if let Ok(mut exponent) = exponent.try_as_usize(int) {
+                let mut result = Exact::new(Self::from(1), true);
+                let mut base = Exact::new(self, true);
+                while exponent > 0 {
+                    if exponent % 2 == 1 {
+                        result = result.mul(&base, int)?;
+                    }
+                    exponent /= 2;
+                    if exponent > 0 {
+                        base = base.clone().mul(&base, int)?;
+                    }
+                }
+                if negative {
+                    result = Exact::new(Self::from(1), true).div(result, int)?;
+                }
+                return Ok(result);
+            }
+        }
         if self.imag != 0.into() || rhs.imag != 0.into() {
             return Err(FendError::ExpComplex);
         }
@@ -142,22 +248,29 @@ impl Complex {
     pub(crate) fn format<I: Interrupt>(
         &self,
         exact: bool,
-        style: FormattingStyle,
-        base: Base,
-        use_parentheses: UseParentheses,
+        options: &FormatOptions,
         int: &I,
     ) -> Result<Exact<Formatted>, FendError> {
-        let style = if !exact && style == FormattingStyle::Auto {
-            FormattingStyle::DecimalPlaces(10)
-        } else if self.imag != 0.into() && style == FormattingStyle::Auto {
-            FormattingStyle::Exact
-        } else {
-            style
-        };
+        if options.complex_output == crate::ComplexOutput::Polar && !self.imag.is_zero() {
+            return self.format_polar(exact, options, int);
+        }
+        let mut options = *options;
+        if !exact && options.style == FormattingStyle::Auto {
+            // approximations are truncated rather than rounded
+            options.style = FormattingStyle::DecimalPlaces(10);
+            options.rounding_mode = crate::RoundingMode::TowardZero;
+        } else if self.imag != 0.into() && options.style == FormattingStyle::Auto {
+            options.style = FormattingStyle::Exact;
+        }
+        let use_parens = options.use_parentheses == UseParentheses::IfComplexOrFraction;
+        let imaginary_unit = Some(options.imaginary_unit);
 
         if self.imag.is_zero() {
-            let use_parens = use_parentheses == UseParentheses::IfComplexOrFraction;
-            let x = self.real.format(base, style, false, use_parens, int)?;
+            let x = self.real.format(
+                None,
+                options.real_options(use_parens, options.integer_digit_limit),
+                int,
+            )?;
             return Ok(Exact::new(
                 Formatted {
                     first_component: x.value,
@@ -170,8 +283,9 @@ impl Complex {
         }
 
         Ok(if self.real.is_zero() {
-            let use_parens = use_parentheses == UseParentheses::IfComplexOrFraction;
-            let x = self.imag.format(base, style, true, use_parens, int)?;
+            let x =
+                self.imag
+                    .format(imaginary_unit, options.real_options(use_parens, None), int)?;
             Exact::new(
                 Formatted {
                     first_component: x.value,
@@ -182,32 +296,91 @@ impl Complex {
                 exact && x.exact,
             )
         } else {
-            let mut exact = exact;
-            let real_part = self.real.format(base, style, false, false, int)?;
-            exact = exact && real_part.exact;
+            let real_part = self
+                .real
+                .format(None, options.real_options(false, None), int)?;
             let (positive, imag_part) = if self.imag > 0.into() {
-                (true, self.imag.format(base, style, true, false, int)?)
+                (true, self.imag.clone())
             } else {
-                (
-                    false,
-                    (-self.imag.clone()).format(base, style, true, false, int)?,
-                )
+                (false, -self.imag.clone())
             };
-            exact = exact && imag_part.exact;
+            let imag_part =
+                imag_part.format(imaginary_unit, options.real_options(false, None), int)?;
             let separator = if positive { " + " } else { " - " };
             Exact::new(
                 Formatted {
                     first_component: real_part.value,
                     separator,
                     second_component: Some(imag_part.value),
-                    use_parentheses: use_parentheses == UseParentheses::IfComplex
-                        || use_parentheses == UseParentheses::IfComplexOrFraction,
+                    use_parentheses: options.use_parentheses == UseParentheses::IfComplex
+                        || options.use_parentheses == UseParentheses::IfComplexOrFraction,
                 },
-                exact,
+                exact && real_part.exact && imag_part.exact,
             )
         })
     }
 
+    /// Formats this number as `r∠θ`, with the angle in radians
+    fn format_polar<I: Interrupt>(
+        &self,
+        exact: bool,
+        options: &FormatOptions,
+        int: &I,
+    ) -> Result<Exact<Formatted>, FendError> {
+        let magnitude = self.clone().abs(int)?;
+        let argument = self.clone().arg(int)?;
+        let exact = exact && magnitude.exact && argument.exact;
+        let mut options = *options;
+        if !exact && options.style == FormattingStyle::Auto {
+            // approximations are truncated rather than rounded
+            options.style = FormattingStyle::DecimalPlaces(10);
+            options.rounding_mode = crate::RoundingMode::TowardZero;
+        } else if options.style == FormattingStyle::Auto {
+            options.style = FormattingStyle::Exact;
+        }
+        let r = magnitude
+            .value
+            .real
+            .format(None, options.real_options(false, None), int)?;
+        let theta = argument
+            .value
+            .format(None, options.real_options(false, None), int)?;
+        Ok(Exact::new(
+            Formatted {
+                first_component: r.value,
+                separator: "\u{2220}",
+                second_component: Some(theta.value),
+                use_parentheses: options.use_parentheses == UseParentheses::IfComplex
+                    || options.use_parentheses == UseParentheses::IfComplexOrFraction,
+            },
+            exact && r.exact && theta.exact,
+        ))
+    }
+
+    /// The angle between the positive real axis and this number, in
+    /// radians between -π and π
+    fn arg<I: Interrupt>(self, int: &I) -> Result<Exact<Real>, FendError> {
+        let pi = Exact::new(Real::pi(), true);
+        if self.real.is_zero() {
+            let half_pi = pi.div(&Exact::new(2.into(), true), int)?;
+            return Ok(if self.imag < 0.into() {
+                half_pi.apply(Neg::neg)
+            } else {
+                half_pi
+            });
+        }
+        let ratio =
+            Exact::new(self.imag.clone(), true).div(&Exact::new(self.real.clone(), true), int)?;
+        let atan = Exact::new(ratio.value.atan(int)?, false);
+        Ok(if self.real > 0.into() {
+            atan
+        } else if self.imag < 0.into() {
+            atan.add(pi.apply(Neg::neg), int)?
+        } else {
+            atan.add(pi, int)?
+        })
+    }
+
     pub(crate) fn root_n<I: Interrupt>(self, n: &Self, int: &I) -> Result<Exact<Self>, FendError> {
         if self.imag != 0.into() || n.imag != 0.into() {
             return Err(FendError::RootsComplex);
@@ -305,12 +478,40 @@ impl Complex {
         self.real.is_definitely_one() && self.imag.is_definitely_zero()
     }
 
+    pub(crate) fn is_rational(&self) -> bool {
+        self.real.is_rational() && self.imag.is_rational()
+    }
+
     pub(crate) fn modulo<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Self, FendError> {
         Ok(Self::from(
             self.expect_real()?.modulo(rhs.expect_real()?, int)?,
         ))
     }
 
+    pub(crate) fn round_to_multiple<I: Interrupt>(
+        self,
+        increment: Self,
+        rounding: Rounding,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        Ok(Self::from(self.expect_real()?.round_to_multiple(
+            increment.expect_real()?,
+            rounding,
+            int,
+        )?))
+    }
+
+    pub(crate) fn best_rational_approximation<I: Interrupt>(
+        self,
+        max_den: usize,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        Ok(Self::from(
+            self.expect_real()?
+                .best_rational_approximation(max_den, int)?,
+        ))
+    }
+
     pub(crate) fn bitwise<I: Interrupt>(
         self,
         rhs: Self,
@@ -467,6 +668,8 @@ pub(crate) struct Formatted {
     use_parentheses: bool,
 }
 
+impl Formatted {}
+
 impl fmt::Display for Formatted {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.use_parentheses {
@@ -9,7 +9,7 @@ use std::fmt::Write;
 use std::ops::Neg;
 use std::{fmt, io};
 
-use super::{Base, Exact, FormattingStyle};
+use super::Exact;
 
 #[derive(Clone)]
 pub(crate) struct Dist {
@@ -105,29 +105,23 @@ impl Dist {
         Ok(res.expect("there must be at least one part in a dist"))
     }
 
-    #[allow(
-        clippy::cast_possible_truncation,
-        clippy::cast_sign_loss,
-        clippy::too_many_arguments
-    )]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     pub(crate) fn format<I: Interrupt>(
         &self,
         exact: bool,
-        style: FormattingStyle,
-        base: Base,
-        use_parentheses: complex::UseParentheses,
+        options: &complex::FormatOptions,
         out: &mut String,
         ctx: &crate::Context,
         int: &I,
     ) -> Result<Exact<()>, FendError> {
         if self.parts.len() == 1 {
-            let res = self.parts.iter().next().unwrap().0.format(
-                exact,
-                style,
-                base,
-                use_parentheses,
-                int,
-            )?;
+            let res = self
+                .parts
+                .iter()
+                .next()
+                .unwrap()
+                .0
+                .format(exact, options, int)?;
             write!(out, "{}", res.value)?;
             Ok(Exact::new((), res.exact))
         } else {
@@ -148,10 +142,7 @@ impl Dist {
             }
             let mut first = true;
             for (num, _prob, prob_f64) in ordered_kvs {
-                let num = num
-                    .format(exact, style, base, use_parentheses, int)?
-                    .value
-                    .to_string();
+                let num = num.format(exact, options, int)?.value.to_string();
                 let prob_percentage = prob_f64 * 100.0;
                 if ctx.output_mode == crate::OutputMode::TerminalFixedWidth {
                     if !first {
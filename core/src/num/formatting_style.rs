@@ -26,6 +26,8 @@ pub(crate) enum FormattingStyle {
     /// If not exact: DecimalPlaces(10). If no recurring digits: ExactFloat.
     /// Other numbers: MixedFraction, albeit possibly including fractions of pi
     Exact,
+    /// Print in scientific notation, e.g. 1.23e4
+    Scientific,
 }
 
 impl fmt::Display for FormattingStyle {
@@ -38,6 +40,7 @@ impl fmt::Display for FormattingStyle {
             Self::DecimalPlaces(d) => write!(f, "{d} dp"),
             Self::SignificantFigures(s) => write!(f, "{s} sf"),
             Self::Auto => write!(f, "auto"),
+            Self::Scientific => write!(f, "scientific"),
         }
     }
 }
@@ -52,6 +55,7 @@ impl fmt::Debug for FormattingStyle {
             Self::DecimalPlaces(d) => write!(f, "{d} dp"),
             Self::SignificantFigures(s) => write!(f, "{s} sf"),
             Self::Auto => write!(f, "auto"),
+            Self::Scientific => write!(f, "scientific"),
         }
     }
 }
@@ -72,6 +76,7 @@ impl FormattingStyle {
                 serialize_usize(*s, write)?;
             }
             Self::Auto => serialize_u8(7, write)?,
+            Self::Scientific => serialize_u8(8, write)?,
         }
         Ok(())
     }
@@ -85,6 +90,7 @@ impl FormattingStyle {
             5 => Self::DecimalPlaces(deserialize_usize(read)?),
             6 => Self::SignificantFigures(deserialize_usize(read)?),
             7 => Self::Auto,
+            8 => Self::Scientific,
             _ => return Err(FendError::DeserializationError),
         })
     }
@@ -2,8 +2,9 @@ use crate::error::{FendError, Interrupt};
 use crate::format::Format;
 use crate::num::bigrat::{BigRat, FormattedBigRat};
 use crate::num::Exact;
-use crate::num::{Base, FormattingStyle};
+use crate::num::{Base, FormattingStyle, Rounding};
 use crate::serialize::{deserialize_u8, serialize_u8};
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::ops::Neg;
 use std::{fmt, hash, io};
@@ -26,6 +27,7 @@ impl fmt::Debug for Real {
                     write!(f, "{:?} * pi", x)
                 }
             }
+            Pattern::Sqrt(x, r) => write!(f, "{x:?} * sqrt({r:?})"),
         }
     }
 }
@@ -36,12 +38,15 @@ pub(crate) enum Pattern {
     Simple(BigRat),
     // n * pi
     Pi(BigRat),
+    // n * sqrt(r), where r is a square-free integer greater than 1
+    Sqrt(BigRat, BigRat),
 }
 
 impl Ord for Real {
     fn cmp(&self, other: &Self) -> Ordering {
         match (&self.pattern, &other.pattern) {
             (Pattern::Simple(a), Pattern::Simple(b)) | (Pattern::Pi(a), Pattern::Pi(b)) => a.cmp(b),
+            (Pattern::Sqrt(a, r), Pattern::Sqrt(b, s)) if r == s => a.cmp(b),
             _ => {
                 let int = &crate::interrupt::Never::default();
                 let a = self.clone().approximate(int).unwrap();
@@ -69,7 +74,7 @@ impl Eq for Real {}
 impl hash::Hash for Real {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         match &self.pattern {
-            Pattern::Simple(r) | Pattern::Pi(r) => r.hash(state),
+            Pattern::Simple(r) | Pattern::Pi(r) | Pattern::Sqrt(r, _) => r.hash(state),
         }
     }
 }
@@ -85,6 +90,11 @@ impl Real {
                 serialize_u8(2, write)?;
                 n.serialize(write)?;
             }
+            Pattern::Sqrt(n, r) => {
+                serialize_u8(3, write)?;
+                n.serialize(write)?;
+                r.serialize(write)?;
+            }
         }
         Ok(())
     }
@@ -94,6 +104,7 @@ impl Real {
             pattern: match deserialize_u8(read)? {
                 1 => Pattern::Simple(BigRat::deserialize(read)?),
                 2 => Pattern::Pi(BigRat::deserialize(read)?),
+                3 => Pattern::Sqrt(BigRat::deserialize(read)?, BigRat::deserialize(read)?),
                 _ => return Err(FendError::DeserializationError),
             },
         })
@@ -108,13 +119,51 @@ impl Real {
                 let pi = num.div(&den, int)?;
                 Ok(n.mul(&pi, int)?)
             }
+            Pattern::Sqrt(n, r) => {
+                // n * sqrt(r) == sqrt(n^2 * r), with the sign of n
+                let negative = n < 0.into();
+                let squared = n.clone().mul(&n, int)?.mul(&r, int)?.simplify(int)?;
+                let root = squared.root_n(&2.into(), int)?.value;
+                Ok(if negative { -root } else { root })
+            }
         }
     }
 
+    /// Returns `coefficient * sqrt(radicand)`, simplified so that the radicand
+    /// is a square-free integer (e.g. `sqrt(8)` becomes `2 sqrt(2)`). Large
+    /// radicands are approximated instead.
+    fn surd<I: Interrupt>(
+        coefficient: BigRat,
+        radicand: BigRat,
+        int: &I,
+    ) -> Result<Exact<Self>, FendError> {
+        if coefficient == 0.into() || radicand == 0.into() {
+            return Ok(Exact::new(Self::from(0), true));
+        }
+        let Some((factor, radicand)) = radicand.clone().sqrt_as_surd(int)? else {
+            let root = radicand.root_n(&2.into(), int)?;
+            return Ok(Exact::new(
+                Self::from(coefficient.mul(&root.value, int)?),
+                root.exact,
+            ));
+        };
+        let coefficient = coefficient.mul(&factor, int)?;
+        Ok(Exact::new(
+            if radicand == 1.into() {
+                Self::from(coefficient)
+            } else {
+                Self {
+                    pattern: Pattern::Sqrt(coefficient, radicand),
+                }
+            },
+            true,
+        ))
+    }
+
     pub(crate) fn try_as_usize<I: Interrupt>(self, int: &I) -> Result<usize, FendError> {
         match self.pattern {
             Pattern::Simple(s) => s.try_as_usize(int),
-            Pattern::Pi(n) => {
+            Pattern::Pi(n) | Pattern::Sqrt(n, _) => {
                 if n == 0.into() {
                     Ok(0)
                 } else {
@@ -124,6 +173,10 @@ impl Real {
         }
     }
 
+    pub(crate) fn into_f64<I: Interrupt>(self, int: &I) -> Result<f64, FendError> {
+        self.approximate(int)?.into_f64(int)
+    }
+
     // sin works for all real numbers
     pub(crate) fn sin<I: Interrupt>(self, int: &I) -> Result<Exact<Self>, FendError> {
         Ok(match self.pattern {
@@ -157,6 +210,12 @@ impl Real {
                 };
                 s.approximate(int)?.sin(int)?.apply(Self::from)
             }
+            Pattern::Sqrt(n, r) => {
+                let s = Self {
+                    pattern: Pattern::Sqrt(n, r),
+                };
+                s.approximate(int)?.sin(int)?.apply(Self::from)
+            }
         })
     }
 
@@ -213,54 +272,72 @@ impl Real {
         Ok(Self::from(self.approximate(int)?.factorial(int)?))
     }
 
+    /// The term in `options` is replaced by the imaginary unit (if any) and
+    /// symbolic constants like `π`
     pub(crate) fn format<I: Interrupt>(
         &self,
-        base: Base,
-        mut style: FormattingStyle,
-        imag: bool,
-        use_parens_if_fraction: bool,
+        imag: Option<crate::ImaginaryUnitSymbol>,
+        options: bigrat::FormatOptions,
         int: &I,
     ) -> Result<Exact<Formatted>, FendError> {
-        let mut pi = false;
-        if style == FormattingStyle::Exact && !self.is_zero() {
-            if let Pattern::Pi(_) = self.pattern {
-                pi = true;
+        // surds are also shown exactly by default (e.g. `2√2`), as long as
+        // the coefficient doesn't need to be approximated
+        if options.style == FormattingStyle::Auto {
+            if let Pattern::Sqrt(..) = &self.pattern {
+                let formatted = self.format_with_style(imag, options.clone(), true, int)?;
+                if formatted.exact {
+                    return Ok(formatted);
+                }
             }
         }
+        self.format_with_style(imag, options, false, int)
+    }
 
-        let term = match (imag, pi) {
-            (false, false) => "",
-            (false, true) => "\u{3c0}", // pi symbol
-            (true, false) => "i",
-            (true, true) => "\u{3c0}i",
+    fn format_with_style<I: Interrupt>(
+        &self,
+        imag: Option<crate::ImaginaryUnitSymbol>,
+        mut options: bigrat::FormatOptions,
+        surd: bool,
+        int: &I,
+    ) -> Result<Exact<Formatted>, FendError> {
+        let symbolic = options.style == FormattingStyle::Exact && !self.is_zero();
+        let constant: Cow<'static, str> = match &self.pattern {
+            Pattern::Pi(_) if symbolic => "\u{3c0}".into(), // pi symbol
+            // radicands are always written in decimal
+            Pattern::Sqrt(_, r) if (symbolic || surd) && options.base == Base::default() => {
+                let r = r.format(&bigrat::FormatOptions::default(), int)?.value;
+                format!("\u{221a}{r}").into()
+            }
+            _ => "".into(),
+        };
+        let symbolic = !constant.is_empty();
+
+        let term = match imag {
+            None => constant,
+            Some(crate::ImaginaryUnitSymbol::I) => format!("{constant}i").into(),
+            Some(crate::ImaginaryUnitSymbol::J) => format!("{constant}j").into(),
         };
 
         let mut override_exact = true;
 
         let rat = match &self.pattern {
             Pattern::Simple(f) => f.clone(),
-            Pattern::Pi(f) => {
-                if pi {
+            Pattern::Pi(f) | Pattern::Sqrt(f, _) => {
+                if symbolic {
                     f.clone()
                 } else {
                     override_exact = false;
-                    if style == FormattingStyle::Auto {
-                        style = FormattingStyle::DecimalPlaces(10);
+                    if options.style == FormattingStyle::Auto {
+                        // approximations are truncated rather than rounded
+                        options.style = FormattingStyle::DecimalPlaces(10);
+                        options.rounding_mode = crate::RoundingMode::TowardZero;
                     }
                     self.clone().approximate(int)?
                 }
             }
         };
 
-        let formatted = rat.format(
-            &bigrat::FormatOptions {
-                base,
-                style,
-                term,
-                use_parens_if_fraction,
-            },
-            int,
-        )?;
+        let formatted = rat.format(&bigrat::FormatOptions { term, ..options }, int)?;
         let exact = formatted.exact && override_exact;
         Ok(Exact::new(
             Formatted {
@@ -285,9 +362,24 @@ impl Real {
             }
         }
 
+        // (a * sqrt(r))^n == a^n * sqrt(r^n) for integers n
+        if let (Pattern::Sqrt(a, r), Pattern::Simple(n)) = (&self.pattern, &rhs.pattern) {
+            if n.is_integer(int)? {
+                let a = a.clone().pow(n.clone(), int)?;
+                let r = r.clone().pow(n.clone(), int)?;
+                return Ok(Self::surd(a.value, r.value, int)?.combine(a.exact && r.exact));
+            }
+        }
+
         if let (Pattern::Simple(a), Pattern::Simple(b)) =
             (self.clone().pattern, rhs.clone().pattern)
         {
+            // square roots are kept exact, e.g. 8^(1/2) == 2 sqrt(2)
+            let double = b.clone().mul(&2.into(), int)?;
+            if a >= 0.into() && !b.is_integer(int)? && double.is_integer(int)? {
+                let radicand = a.pow(double, int)?;
+                return Ok(Self::surd(1.into(), radicand.value, int)?.combine(radicand.exact));
+            }
             Ok(a.pow(b, int)?.apply(Self::from))
         } else {
             Ok(self
@@ -304,13 +396,17 @@ impl Real {
         // https://github.com/rust-lang/rust/pull/76119
         Ok(match self.pattern {
             Pattern::Simple(a) => match &n.pattern {
+                // square roots are kept exact, e.g. sqrt(8) == 2 sqrt(2)
+                Pattern::Simple(b) if b == &2.into() && a >= 0.into() => {
+                    Self::surd(1.into(), a, int)?
+                }
                 Pattern::Simple(b) => a.root_n(b, int)?.apply(Self::from),
-                Pattern::Pi(_) => {
+                Pattern::Pi(_) | Pattern::Sqrt(..) => {
                     let b = n.clone().approximate(int)?;
                     a.root_n(&b, int)?.apply(Self::from).combine(false)
                 }
             },
-            Pattern::Pi(_) => {
+            Pattern::Pi(_) | Pattern::Sqrt(..) => {
                 let a = self.clone().approximate(int)?;
                 let b = n.clone().approximate(int)?;
                 a.root_n(&b, int)?.apply(Self::from).combine(false)
@@ -326,27 +422,33 @@ impl Real {
 
     pub(crate) fn is_zero(&self) -> bool {
         match &self.pattern {
-            Pattern::Simple(a) | Pattern::Pi(a) => a.is_definitely_zero() || a == &0.into(),
+            Pattern::Simple(a) | Pattern::Pi(a) | Pattern::Sqrt(a, _) => {
+                a.is_definitely_zero() || a == &0.into()
+            }
         }
     }
 
     pub(crate) fn is_definitely_zero(&self) -> bool {
         match &self.pattern {
-            Pattern::Simple(a) | Pattern::Pi(a) => a.is_definitely_zero(),
+            Pattern::Simple(a) | Pattern::Pi(a) | Pattern::Sqrt(a, _) => a.is_definitely_zero(),
         }
     }
 
     pub(crate) fn is_definitely_one(&self) -> bool {
         match &self.pattern {
             Pattern::Simple(a) => a.is_definitely_one(),
-            Pattern::Pi(_) => false,
+            Pattern::Pi(_) | Pattern::Sqrt(..) => false,
         }
     }
 
+    pub(crate) fn is_rational(&self) -> bool {
+        matches!(self.pattern, Pattern::Simple(_))
+    }
+
     pub(crate) fn expect_rational(self) -> Result<BigRat, FendError> {
         match self.pattern {
             Pattern::Simple(a) => Ok(a),
-            Pattern::Pi(_) => Err(FendError::ExpectedARationalNumber),
+            Pattern::Pi(_) | Pattern::Sqrt(..) => Err(FendError::ExpectedARationalNumber),
         }
     }
 
@@ -357,6 +459,30 @@ impl Real {
         ))
     }
 
+    pub(crate) fn round_to_multiple<I: Interrupt>(
+        self,
+        increment: Self,
+        rounding: Rounding,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        Ok(Self::from(self.approximate(int)?.round_to_multiple(
+            &increment.approximate(int)?,
+            rounding,
+            int,
+        )?))
+    }
+
+    pub(crate) fn best_rational_approximation<I: Interrupt>(
+        self,
+        max_den: usize,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        Ok(Self::from(
+            self.approximate(int)?
+                .best_rational_approximation(max_den, int)?,
+        ))
+    }
+
     pub(crate) fn bitwise<I: Interrupt>(
         self,
         rhs: Self,
@@ -397,6 +523,9 @@ impl Exact<Real> {
                     },
                     args_exact,
                 ),
+                (Pattern::Sqrt(a, r), Pattern::Sqrt(b, s)) if r == s => {
+                    Real::surd(a.add(b, int)?, r, int)?.combine(args_exact)
+                }
                 _ => {
                     let a = self.value.approximate(int)?;
                     let b = rhs.value.approximate(int)?;
@@ -422,6 +551,9 @@ impl Exact<Real> {
                     },
                     args_exact,
                 ),
+                Pattern::Sqrt(b, r) => {
+                    Real::surd(a.mul(b, int)?, r.clone(), int)?.combine(args_exact)
+                }
             },
             Pattern::Pi(a) => match &rhs.value.pattern {
                 Pattern::Simple(b) => Self::new(
@@ -430,13 +562,31 @@ impl Exact<Real> {
                     },
                     args_exact,
                 ),
-                Pattern::Pi(_) => Self::new(
+                Pattern::Pi(_) | Pattern::Sqrt(..) => Self::new(
                     Real {
                         pattern: Pattern::Pi(a.mul(&rhs.value.clone().approximate(int)?, int)?),
                     },
                     false,
                 ),
             },
+            Pattern::Sqrt(a, r) => match &rhs.value.pattern {
+                Pattern::Simple(b) => Real::surd(a.mul(b, int)?, r, int)?.combine(args_exact),
+                Pattern::Sqrt(b, s) => {
+                    Real::surd(a.mul(b, int)?, r.mul(s, int)?, int)?.combine(args_exact)
+                }
+                Pattern::Pi(b) => {
+                    let a = Real {
+                        pattern: Pattern::Sqrt(a, r),
+                    }
+                    .approximate(int)?;
+                    Self::new(
+                        Real {
+                            pattern: Pattern::Pi(a.mul(b, int)?),
+                        },
+                        false,
+                    )
+                }
+            },
         })
     }
 
@@ -447,22 +597,50 @@ impl Exact<Real> {
         if self.exact && self.value.is_zero() {
             return Ok(self);
         }
+        let args_exact = self.exact && rhs.exact;
         Ok(match self.value.pattern {
             Pattern::Simple(a) => match &rhs.value.pattern {
-                Pattern::Simple(b) => Self::new(a.div(b, int)?.into(), self.exact && rhs.exact),
+                Pattern::Simple(b) => Self::new(a.div(b, int)?.into(), args_exact),
                 Pattern::Pi(_) => Self::new(
                     a.div(&rhs.value.clone().approximate(int)?, int)?.into(),
                     false,
                 ),
+                // a / (b * sqrt(s)) == (a / b) * sqrt(1 / s)
+                Pattern::Sqrt(b, s) => {
+                    Real::surd(a.div(b, int)?, BigRat::from(1).div(s, int)?, int)?
+                        .combine(args_exact)
+                }
             },
             Pattern::Pi(a) => match &rhs.value.pattern {
                 Pattern::Simple(b) => Self::new(
                     Real {
                         pattern: Pattern::Pi(a.div(b, int)?),
                     },
-                    self.exact && rhs.exact,
+                    args_exact,
+                ),
+                Pattern::Pi(b) => Self::new(a.div(b, int)?.into(), args_exact),
+                Pattern::Sqrt(..) => Self::new(
+                    Real {
+                        pattern: Pattern::Pi(a.div(&rhs.value.clone().approximate(int)?, int)?),
+                    },
+                    false,
                 ),
-                Pattern::Pi(b) => Self::new(a.div(b, int)?.into(), self.exact && rhs.exact),
+            },
+            Pattern::Sqrt(a, r) => match &rhs.value.pattern {
+                Pattern::Simple(b) => Real::surd(a.div(b, int)?, r, int)?.combine(args_exact),
+                Pattern::Sqrt(b, s) => {
+                    Real::surd(a.div(b, int)?, r.div(s, int)?, int)?.combine(args_exact)
+                }
+                Pattern::Pi(_) => {
+                    let a = Real {
+                        pattern: Pattern::Sqrt(a, r),
+                    }
+                    .approximate(int)?;
+                    Self::new(
+                        a.div(&rhs.value.clone().approximate(int)?, int)?.into(),
+                        false,
+                    )
+                }
             },
         })
     }
@@ -477,6 +655,9 @@ impl Neg for Real {
             Pattern::Pi(n) => Self {
                 pattern: Pattern::Pi(-n),
             },
+            Pattern::Sqrt(n, r) => Self {
+                pattern: Pattern::Sqrt(-n, r),
+            },
         }
     }
 }
@@ -502,6 +683,8 @@ pub(crate) struct Formatted {
     num: FormattedBigRat,
 }
 
+impl Formatted {}
+
 impl fmt::Display for Formatted {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.num)
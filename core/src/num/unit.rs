@@ -1,8 +1,10 @@
 use crate::ast::{BitwiseBop, Bop};
 use crate::error::{FendError, Interrupt};
-use crate::num::complex::{Complex, UseParentheses};
+use crate::num::bigrat::BigRat;
+use crate::num::complex::{self, Complex, UseParentheses};
 use crate::num::dist::Dist;
-use crate::num::{Base, FormattingStyle};
+use crate::num::real::Real;
+use crate::num::{Base, FormattingStyle, Rounding};
 use crate::scope::Scope;
 use crate::serialize::{deserialize_bool, deserialize_usize, serialize_bool, serialize_usize};
 use crate::{ast, ident::Ident};
@@ -69,6 +71,73 @@ impl Value {
         self.value.one_point()?.try_as_usize(int)
     }
 
+    /// Whether this is an exact whole number in its own unit, e.g. `3 m`
+    /// but not `1.5` or `approx. 3`
+    pub(crate) fn is_integer<I: Interrupt>(&self, int: &I) -> Result<bool, FendError> {
+        if !self.exact || !self.value.one_point_ref()?.is_real() {
+            return Ok(false);
+        }
+        let rounded = self.clone().round(Rounding::Floor, int)?;
+        self.equals(&rounded, int)
+    }
+
+    pub(crate) fn try_as_i64<I: Interrupt>(self, int: &I) -> Result<i64, FendError> {
+        if !self.is_unitless(int)? {
+            return Err(FendError::NumberWithUnitToInt);
+        }
+        let negative = self.compare(&Self::from(0), int)? == std::cmp::Ordering::Less;
+        let magnitude = i128::try_from(self.abs(int)?.try_as_usize_unit(int)?)
+            .map_err(|_| FendError::NumberTooLargeForInteger)?;
+        i64::try_from(if negative { -magnitude } else { magnitude })
+            .map_err(|_| FendError::NumberTooLargeForInteger)
+    }
+
+    /// Returns the numerical part of this value along with its unit,
+    /// e.g. `(2.5, "km")` for `2.5 km`
+    pub(crate) fn try_as_f64_with_unit<I: Interrupt>(
+        self,
+        ctx: &crate::Context,
+        int: &I,
+    ) -> Result<(f64, String), FendError> {
+        let unit = self.format(ctx, int)?.unit_str.trim().to_string();
+        let value = self.value.one_point()?.into_f64(int)?;
+        Ok((value, unit))
+    }
+
+    /// Returns true if both values have the same dimensions, i.e. if
+    /// one can be converted into the other
+    pub(crate) fn is_compatible_with<I: Interrupt>(
+        &self,
+        other: &Self,
+        int: &I,
+    ) -> Result<bool, FendError> {
+        self.unit.is_compatible_with(&other.unit, int)
+    }
+
+    pub(crate) fn try_as_f64<I: Interrupt>(self, int: &I) -> Result<f64, FendError> {
+        if !self.is_unitless(int)? {
+            return Err(FendError::ExpectedAUnitlessNumber);
+        }
+        self.convert_to(Self::unitless(), int)?
+            .value
+            .one_point()?
+            .into_f64(int)
+    }
+
+    /// Converts an `f64` (e.g. the result of a registered function) into an
+    /// approximate unitless number
+    pub(crate) fn from_f64<I: Interrupt>(f: f64, int: &I) -> Result<Self, FendError> {
+        let value = Real::from(BigRat::from_f64(f, int)?);
+        Ok(Self {
+            value: Complex::from(value).into(),
+            unit: Unit::unitless(),
+            exact: false,
+            base: Base::default(),
+            format: FormattingStyle::default(),
+            simplifiable: true,
+        })
+    }
+
     pub(crate) fn create_unit_value_from_value<I: Interrupt>(
         value: &Self,
         prefix: Cow<'static, str>,
@@ -85,6 +154,17 @@ impl Value {
         Ok(result)
     }
 
+    /// Marks a unit like `Δ°C` as a temperature difference
+    pub(crate) fn into_temperature_difference(mut self) -> Self {
+        self.unit.components = self
+            .unit
+            .components
+            .into_iter()
+            .map(|c| UnitExponent::new(c.unit.into_temperature_difference(), c.exponent))
+            .collect();
+        self
+    }
+
     pub(crate) fn new_base_unit(
         singular_name: Cow<'static, str>,
         plural_name: Cow<'static, str>,
@@ -107,6 +187,17 @@ impl Value {
         }
     }
 
+    /// Used for `to decimal`, which shows fractions as decimals again,
+    /// e.g. for `0.75 to fraction to decimal`
+    pub(crate) fn with_decimal_format(self) -> Self {
+        match self.format {
+            FormattingStyle::ImproperFraction | FormattingStyle::MixedFraction => {
+                self.with_format(FormattingStyle::Auto)
+            }
+            _ => self,
+        }
+    }
+
     pub(crate) fn with_base(self, base: Base) -> Self {
         Self {
             value: self.value,
@@ -146,6 +237,11 @@ impl Value {
     }
 
     pub(crate) fn add<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Self, FendError> {
+        if self.unit.is_temperature_difference() && rhs.unit.is_absolute_temperature() {
+            // adding a temperature difference to an absolute temperature
+            // results in an absolute temperature, regardless of the order
+            return rhs.add(self, int);
+        }
         let scale_factor = Unit::compute_scale_factor(&rhs.unit, &self.unit, int)?;
         let scaled = Exact::new(rhs.value, rhs.exact)
             .mul(&scale_factor.scale_1.apply(Dist::from), int)?
@@ -162,6 +258,28 @@ impl Value {
         })
     }
 
+    /// Converts this value to the unit of `other`, like when adding the two
+    /// values. Unlike [`Self::convert_to`], offsets (e.g. between
+    /// temperature scales) are ignored.
+    pub(crate) fn convert_to_unit_of<I: Interrupt>(
+        self,
+        other: &Self,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let scale_factor = Unit::compute_scale_factor(&self.unit, &other.unit, int)?;
+        let scaled = Exact::new(self.value, self.exact)
+            .mul(&scale_factor.scale_1.apply(Dist::from), int)?
+            .div(&scale_factor.scale_2.apply(Dist::from), int)?;
+        Ok(Self {
+            value: scaled.value,
+            unit: other.unit.clone(),
+            exact: scaled.exact,
+            base: self.base,
+            format: self.format,
+            simplifiable: self.simplifiable,
+        })
+    }
+
     /// Called for implicit addition to modify the second operand.
     /// For example, when evaluating `5'0`, this function can change the second
     /// operand's unit from `unitless` to `"`.
@@ -187,6 +305,11 @@ impl Value {
         if rhs.value.one_point()? != 1.into() {
             return Err(FendError::ConversionRhsNumerical);
         }
+        // differences don't have an offset, so they can only be converted
+        // to other differences (like `Δ°F`) or to kelvin
+        if self.unit.is_temperature_difference() && rhs.unit.is_absolute_temperature() {
+            return Err(FendError::ConvertTemperatureDifferenceToAbsolute);
+        }
         let scale_factor = Unit::compute_scale_factor(&self.unit, &rhs.unit, int)?;
         let new_value = Exact::new(self.value, self.exact)
             .mul(&scale_factor.scale_1.apply(Dist::from), int)?
@@ -202,7 +325,101 @@ impl Value {
         })
     }
 
+    /// Shows compound units as an equivalent named unit, e.g. `10 N m`
+    /// becomes `10 J`. This only applies if the named unit has exactly the
+    /// same scale, so e.g. `kW h` is left unchanged.
+    pub(crate) fn use_preferred_unit<I: Interrupt>(
+        self,
+        attrs: Attrs,
+        context: &mut crate::Context,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        if !self.simplifiable || self.unit.components.len() < 2 {
+            return Ok(self);
+        }
+        for &name in crate::units::PREFERRED_UNITS {
+            let preferred = crate::units::query_unit_static(name, attrs, context, int)?
+                .expect_num()?
+                .unit;
+            let scale_factor = match Unit::compute_scale_factor(&self.unit, &preferred, int) {
+                Ok(scale_factor) => scale_factor,
+                Err(FendError::Interrupted) => return Err(FendError::Interrupted),
+                Err(_) => continue,
+            };
+            if scale_factor.offset.value == 0.into()
+                && scale_factor.scale_1.value == scale_factor.scale_2.value
+            {
+                return Ok(Self {
+                    unit: preferred,
+                    ..self
+                });
+            }
+        }
+        Ok(self)
+    }
+
+    /// Used for explicit conversions with `to`. In addition to normal unit
+    /// conversions, this supports rates with a numerical value on the
+    /// right-hand side (e.g. `to L per 100 km`), as well as converting
+    /// between reciprocal units (e.g. from `mpg` to `L per 100 km`, or from
+    /// `mph` to `seconds per mile`).
+    pub(crate) fn convert_to_explicit<I: Interrupt>(
+        self,
+        rhs: Self,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let rhs = rhs.fold_numerical_value_into_rate(int)?;
+        if !self.unit.is_compatible_with(&rhs.unit, int)?
+            && self.unit.reciprocal().is_compatible_with(&rhs.unit, int)?
+        {
+            let (base, format) = (self.base, self.format);
+            return Self::from(1)
+                .div(self, int)?
+                .with_base(base)
+                .with_format(format)
+                .convert_to(rhs, int);
+        }
+        self.convert_to(rhs, int)
+    }
+
+    /// Turns e.g. `L / (100 km)`, which has a numerical value of `1/100`,
+    /// into `1 L / 100 km` by merging the number into the last unit with
+    /// an exponent of -1
+    fn fold_numerical_value_into_rate<I: Interrupt>(self, int: &I) -> Result<Self, FendError> {
+        let value = self.value.clone().one_point()?;
+        if value == 1.into() {
+            return Ok(self);
+        }
+        let factor = Exact::new(Complex::from(1), true)
+            .div(Exact::new(value, true), int)?
+            .value
+            .try_as_usize(int)
+            .map_err(|_| FendError::ConversionRhsNumerical)?;
+        let mut components = self.unit.components;
+        let Some(component) = components
+            .iter_mut()
+            .rev()
+            .find(|c| c.exponent == -Complex::from(1))
+        else {
+            return Err(FendError::ConversionRhsNumerical);
+        };
+        component.unit = component.unit.with_factor(factor as u64, int)?;
+        Ok(Self {
+            value: 1.into(),
+            unit: Unit { components },
+            ..self
+        })
+    }
+
     pub(crate) fn sub<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Self, FendError> {
+        if rhs.unit.is_absolute_temperature() {
+            if self.unit.is_absolute_temperature() {
+                return self.sub_absolute_temperatures(rhs, int);
+            }
+            if self.unit.is_temperature_difference() {
+                return Err(FendError::SubtractAbsoluteTemperatureFromDifference);
+            }
+        }
         let scale_factor = Unit::compute_scale_factor(&rhs.unit, &self.unit, int)?;
         let scaled = Exact::new(rhs.value, rhs.exact)
             .mul(&scale_factor.scale_1.apply(Dist::from), int)?
@@ -218,6 +435,62 @@ impl Value {
         })
     }
 
+    /// Returns `1` in the same unit as `self`, as used by [`Self::convert_to`]
+    pub(crate) fn unit_of(&self) -> Self {
+        Self {
+            value: 1.into(),
+            unit: self.unit.clone(),
+            exact: true,
+            base: self.base,
+            format: self.format,
+            simplifiable: self.simplifiable,
+        }
+    }
+
+    /// Orders two values, after converting `other` into the unit of `self`
+    pub(crate) fn compare<I: Interrupt>(
+        &self,
+        other: &Self,
+        int: &I,
+    ) -> Result<std::cmp::Ordering, FendError> {
+        let other = other.clone().convert_to(self.unit_of(), int)?;
+        self.value
+            .one_point_ref()?
+            .compare(&other.value.one_point()?)
+    }
+
+    /// Checks whether two values are equal, after converting `other` into
+    /// the unit of `self`
+    pub(crate) fn equals<I: Interrupt>(&self, other: &Self, int: &I) -> Result<bool, FendError> {
+        let other = other.clone().convert_to(self.unit_of(), int)?;
+        Ok(self.value.one_point_ref()? == &other.value.one_point()?)
+    }
+
+    /// Subtracting two absolute temperatures (e.g. `10 °C - 5 °C`) gives
+    /// a temperature difference like `5 Δ°C`, which can then be converted
+    /// without applying an offset.
+    fn sub_absolute_temperatures<I: Interrupt>(
+        self,
+        rhs: Self,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let (delta_unit, scale) = self.unit.temperature_difference_unit(int)?;
+        let rhs = rhs.convert_to(self.unit_of(), int)?;
+        let value = Exact::new(self.value, self.exact)
+            .add(&-Exact::new(rhs.value, rhs.exact), int)?
+            .mul(&scale.apply(Dist::from), int)?;
+        Ok(Self {
+            value: value.value,
+            unit: Unit {
+                components: vec![UnitExponent::new(delta_unit, 1)],
+            },
+            exact: self.exact && rhs.exact && value.exact,
+            base: self.base,
+            format: self.format,
+            simplifiable: self.simplifiable,
+        })
+    }
+
     pub(crate) fn div<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Self, FendError> {
         let mut components = self.unit.components.clone();
         for rhs_component in rhs.unit.components {
@@ -238,7 +511,15 @@ impl Value {
         })
     }
 
-    fn modulo<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Self, FendError> {
+    /// Floor division, i.e. the quotient rounded towards negative infinity,
+    /// so `-7 // 2` is `-4`
+    fn int_div<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Self, FendError> {
+        self.div(rhs, int)?
+            .simplify(int)?
+            .round(Rounding::Floor, int)
+    }
+
+    pub(crate) fn modulo<I: Interrupt>(self, rhs: Self, int: &I) -> Result<Self, FendError> {
         if !self.is_unitless(int)? || !rhs.is_unitless(int)? {
             return Err(FendError::ModuloUnitless);
         }
@@ -309,14 +590,17 @@ impl Value {
             Bop::Minus => self.sub(rhs, int),
             Bop::Mul => self.mul(rhs, int),
             Bop::Div => self.div(rhs, int),
+            Bop::IntDiv => self.int_div(rhs, int),
             Bop::Mod => self.modulo(rhs, int),
             Bop::Pow => self.pow(rhs, int),
             Bop::Bitwise(bitwise_bop) => self.bitwise(rhs, bitwise_bop, int),
             Bop::Combination => self.combination(rhs, int),
+            // comparisons result in booleans, so they're handled separately
+            Bop::Comparison(_) => Err(FendError::InvalidOperandsForComparison),
         }
     }
 
-    fn is_unitless<I: Interrupt>(&self, int: &I) -> Result<bool, FendError> {
+    pub(crate) fn is_unitless<I: Interrupt>(&self, int: &I) -> Result<bool, FendError> {
         // todo this is broken for unitless components
         if self.unit.components.is_empty() {
             return Ok(true);
@@ -328,6 +612,72 @@ impl Value {
         Ok(false)
     }
 
+    /// Returns the names of the base units that make up this number's unit,
+    /// along with their (non-zero) exponents. Fails if any exponent is
+    /// not an integer.
+    pub(crate) fn base_unit_exponents<I: Interrupt>(
+        &self,
+        int: &I,
+    ) -> Result<Vec<(String, i64)>, FendError> {
+        let (hashmap, _scale) = self.unit.to_hashmap_and_scale(int)?;
+        let mut result = vec![];
+        for (base_unit, exponent) in hashmap {
+            let negative = exponent.compare(&0.into())? == std::cmp::Ordering::Less;
+            let magnitude = if negative { -exponent } else { exponent };
+            let magnitude = magnitude.try_as_usize(int).map_err(|e| match e {
+                FendError::FractionToInteger => FendError::NonIntegerDimension,
+                e => e,
+            })?;
+            let magnitude = i64::try_from(magnitude).map_err(|_| FendError::ExponentTooLarge)?;
+            let exponent = if negative { -magnitude } else { magnitude };
+            result.push((base_unit.name().to_string(), exponent));
+        }
+        Ok(result)
+    }
+
+    /// Returns `1 / self`, e.g. `0.25 m^-1` for `4 m`. If the result only
+    /// has a single base unit, like the reciprocal of `Hz`, it is shown in
+    /// that unit, so `2 Hz` becomes `0.5 s` rather than `0.5 Hz^-1`. Units
+    /// with a dimensionless part (like `rad/s`, or `rpm` which includes a
+    /// factor of 2π) are kept, since converting them would lose that part.
+    pub(crate) fn reciprocal<I: Interrupt>(
+        self,
+        attrs: Attrs,
+        context: &mut crate::Context,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let collapsible = self.unit.components.iter().all(|unit_exp| {
+            !unit_exp.unit.base_units.is_empty() && unit_exp.unit.scale.is_rational()
+        });
+        let result = Self::from(1).div(self, int)?;
+        if !collapsible {
+            return Ok(result);
+        }
+        // keep simple units like `km` (e.g. from `km^-1`) as they are
+        if let [unit_exp] = result.unit.components.as_slice() {
+            if unit_exp.exponent == 1.into() {
+                return Ok(result);
+            }
+        }
+        let (hashmap, _scale) = result.unit.to_hashmap_and_scale(int)?;
+        let mut base_units = hashmap.into_iter();
+        let (Some((base_unit, exponent)), None) = (base_units.next(), base_units.next()) else {
+            return Ok(result);
+        };
+        if exponent != 1.into() {
+            return Ok(result);
+        }
+        let name = crate::units::base_unit_symbol(base_unit.name()).unwrap_or(base_unit.name());
+        let Ok(unit) = crate::units::query_unit_static(name, attrs, context, int) else {
+            return Ok(result);
+        };
+        result.convert_to(unit.expect_num()?, int)
+    }
+
+    pub(crate) fn is_exact(&self) -> bool {
+        self.exact
+    }
+
     pub(crate) fn is_unitless_one<I: Interrupt>(&self, int: &I) -> Result<bool, FendError> {
         Ok(self.exact && self.value.equals_int(1) && self.is_unitless(int)?)
     }
@@ -336,11 +686,31 @@ impl Value {
         if !rhs.is_unitless(int)? {
             return Err(FendError::ExpUnitless);
         }
+        // quantities can only be raised to powers that result in whole
+        // exponents, e.g. `(4 m^2)^0.5` is `2 m`, but `(5 m)^0.5` is an error.
+        // Bare units can still have fractional exponents (like `V / Hz^0.5`).
+        let check_exponents = !self.value.equals_int(1) && !self.is_unitless(int)?;
+        // combine identical units first, so that e.g. `(2 m * 8 m)^0.5` works
+        let lhs = if check_exponents {
+            self.simplify(int)?
+        } else {
+            self
+        };
         let mut new_components = vec![];
         let mut exact_res = true;
-        for unit_exp in self.unit.components {
-            let exponent = Exact::new(unit_exp.exponent, self.exact)
+        for unit_exp in lhs.unit.components {
+            let exponent = Exact::new(unit_exp.exponent, lhs.exact)
                 .mul(&Exact::new(rhs.value.clone().one_point()?, rhs.exact), int)?;
+            if check_exponents {
+                let rounded =
+                    exponent
+                        .value
+                        .clone()
+                        .round_to_multiple(1.into(), Rounding::Floor, int)?;
+                if rounded != exponent.value {
+                    return Err(FendError::FractionalUnitExponent);
+                }
+            }
             exact_res = exact_res && exponent.exact;
             new_components.push(UnitExponent {
                 unit: unit_exp.unit,
@@ -350,14 +720,14 @@ impl Value {
         let new_unit = Unit {
             components: new_components,
         };
-        let value = self.value.one_point()?.pow(rhs.value.one_point()?, int)?;
+        let value = lhs.value.one_point()?.pow(rhs.value.one_point()?, int)?;
         Ok(Self {
             value: value.value.into(),
             unit: new_unit,
-            exact: self.exact && rhs.exact && exact_res && value.exact,
-            base: self.base,
-            format: self.format,
-            simplifiable: self.simplifiable,
+            exact: lhs.exact && rhs.exact && exact_res && value.exact,
+            base: lhs.base,
+            format: lhs.format,
+            simplifiable: lhs.simplifiable,
         })
     }
 
@@ -395,6 +765,131 @@ impl Value {
         })
     }
 
+    /// Rounds to a whole number in the value's own unit
+    pub(crate) fn round<I: Interrupt>(
+        self,
+        rounding: Rounding,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let increment = self.unit_of();
+        self.round_to_increment(increment, rounding, int)
+    }
+
+    /// Rounds to a multiple of `increment`, which is first converted into
+    /// the unit of `self`, e.g. `17 minutes` rounded up to a multiple of
+    /// `15 minutes` is `30 minutes`
+    pub(crate) fn round_to_increment<I: Interrupt>(
+        self,
+        increment: Self,
+        rounding: Rounding,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        if increment.is_zero() {
+            return Err(FendError::RoundingToZeroIncrement);
+        }
+        let increment = increment.convert_to(self.unit_of(), int)?;
+        let value = self.value.one_point()?.round_to_multiple(
+            increment.value.one_point()?,
+            rounding,
+            int,
+        )?;
+        Ok(Self {
+            value: Dist::from(value),
+            unit: self.unit,
+            exact: self.exact && increment.exact,
+            base: self.base,
+            format: self.format,
+            simplifiable: self.simplifiable,
+        })
+    }
+
+    /// Rounds this number to the given number of significant figures, keeping
+    /// its unit, e.g. `12345 m` becomes `12000 m`. Zero, and numbers that
+    /// already have fewer significant figures, are left unchanged.
+    pub(crate) fn round_to_sig_figs<I: Interrupt>(
+        self,
+        sig_figs: usize,
+        rounding: Rounding,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let value = self.value.clone().one_point()?;
+        if value.compare(&0.into())? == std::cmp::Ordering::Equal {
+            return Ok(self);
+        }
+        let magnitude = value.clone().abs(int)?;
+        let ten = Exact::new(Complex::from(10), true);
+        // the value divided by the increment needs to have `sig_figs` digits
+        // before the decimal point, i.e. lie in [10^(sig_figs - 1), 10^sig_figs)
+        let mut lower = Exact::new(Complex::from(1), true);
+        for _ in 1..sig_figs {
+            crate::interrupt::test_int(int)?;
+            lower = lower.mul(&ten, int)?;
+        }
+        let upper = lower.clone().mul(&ten, int)?;
+        let mut increment = Exact::new(Complex::from(1), true);
+        let scaled = |increment: &Exact<Complex>| magnitude.clone().div(increment.clone(), int);
+        while scaled(&increment)?.value.compare(&upper.value)? != std::cmp::Ordering::Less {
+            crate::interrupt::test_int(int)?;
+            increment = increment.mul(&ten, int)?;
+        }
+        while scaled(&increment)?.value.compare(&lower.value)? == std::cmp::Ordering::Less {
+            crate::interrupt::test_int(int)?;
+            increment = increment.div(ten.clone(), int)?;
+        }
+        let value = value.round_to_multiple(increment.value, rounding, int)?;
+        Ok(Self {
+            value: Dist::from(value),
+            unit: self.unit,
+            exact: self.exact,
+            base: self.base,
+            format: self.format,
+            simplifiable: self.simplifiable,
+        })
+    }
+
+    /// Replaces the value with the closest fraction whose denominator is
+    /// at most `max_den`, keeping the unit. The result is exact, and shown
+    /// as a fraction.
+    pub(crate) fn best_rational_approximation<I: Interrupt>(
+        self,
+        max_den: Self,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let max_den = max_den.try_as_usize(int)?;
+        let value = self
+            .value
+            .one_point()?
+            .best_rational_approximation(max_den, int)?;
+        Ok(Self {
+            value: Dist::from(value),
+            unit: self.unit,
+            exact: true,
+            base: self.base,
+            format: FormattingStyle::ImproperFraction,
+            simplifiable: self.simplifiable,
+        })
+    }
+
+    /// Shows the value exactly (e.g. `1/3` instead of `0.3333333333`). This
+    /// fails for values that are only known approximately, like `sin 1`.
+    pub(crate) fn make_exact(self) -> Result<Self, FendError> {
+        if !self.exact {
+            return Err(FendError::CannotMakeExact);
+        }
+        Ok(self.with_format(FormattingStyle::Exact))
+    }
+
+    /// Marks the value as approximate, and shows fractions as decimals
+    pub(crate) fn make_approximate_decimal(self) -> Self {
+        let format = match self.format {
+            FormattingStyle::ImproperFraction
+            | FormattingStyle::MixedFraction
+            | FormattingStyle::Exact => FormattingStyle::Auto,
+            format => format,
+        };
+        self.make_approximate().with_format(format)
+    }
+
     pub(crate) fn make_approximate(self) -> Self {
         Self {
             value: self.value,
@@ -624,19 +1119,27 @@ impl Value {
             UseParentheses::IfComplex
         };
         let mut formatted_value = String::new();
+        let options = complex::FormatOptions::new(self.format, self.base, use_parentheses, ctx);
         let mut exact = self
             .value
-            .format(
-                self.exact,
-                self.format,
-                self.base,
-                use_parentheses,
-                &mut formatted_value,
-                ctx,
-                int,
-            )?
+            .format(self.exact, &options, &mut formatted_value, ctx, int)?
             .exact;
-        let unit_string = self.unit.format(
+        let unit = if ctx.zero_display == crate::ZeroDisplay::DropUnit && self.value.equals_int(0) {
+            Unit::unitless()
+        } else {
+            Unit {
+                components: self
+                    .unit
+                    .components
+                    .iter()
+                    .map(|c| UnitExponent {
+                        unit: c.unit.clone().with_spelling(ctx.unit_spelling),
+                        exponent: c.exponent.clone(),
+                    })
+                    .collect(),
+            }
+        };
+        let unit_string = unit.format(
             "",
             self.value.equals_int(1),
             self.base,
@@ -854,6 +1357,10 @@ pub(crate) struct FormattedValue {
 }
 
 impl FormattedValue {
+    pub(crate) fn is_exact(&self) -> bool {
+        self.exact
+    }
+
     pub(crate) fn spans(self, spans: &mut Vec<Span>, attrs: Attrs) {
         if !self.exact && attrs.show_approx && !attrs.plain_number {
             spans.push(Span {
@@ -939,6 +1446,75 @@ impl Unit {
         prefix.is_empty() && name == rhs
     }
 
+    fn reciprocal(&self) -> Self {
+        Self {
+            components: self
+                .components
+                .iter()
+                .map(|c| UnitExponent::new(c.unit.clone(), -c.exponent.clone()))
+                .collect(),
+        }
+    }
+
+    fn single_component(&self) -> Option<&NamedUnit> {
+        match self.components.as_slice() {
+            [c] if c.exponent == 1.into() => Some(&c.unit),
+            _ => None,
+        }
+    }
+
+    /// Returns true for temperature scales with an offset, like `°C` or `°F`
+    fn is_absolute_temperature(&self) -> bool {
+        self.single_component().is_some_and(|unit| {
+            unit.base_units.len() == 1
+                && ["celsius", "fahrenheit"]
+                    .into_iter()
+                    .any(|name| unit.base_units.get(&BaseUnit::new_static(name)) == Some(&1.into()))
+        })
+    }
+
+    /// Returns true for units like `Δ°C` or `Δ°F`
+    fn is_temperature_difference(&self) -> bool {
+        self.single_component()
+            .is_some_and(NamedUnit::is_temperature_difference)
+    }
+
+    /// For an absolute temperature unit, returns the matching temperature
+    /// difference unit and the factor needed to convert into it
+    /// (e.g. `1/1000` for millicelsius)
+    fn temperature_difference_unit<I: Interrupt>(
+        &self,
+        int: &I,
+    ) -> Result<(NamedUnit, Exact<Complex>), FendError> {
+        let unit = self
+            .single_component()
+            .expect("unit should be an absolute temperature");
+        let (name, kelvin_scale) = if unit
+            .base_units
+            .contains_key(&BaseUnit::new_static("fahrenheit"))
+        {
+            (
+                "\u{394}\u{b0}F",
+                Exact::new(Complex::from(5), true).div(Exact::new(Complex::from(9), true), int)?,
+            )
+        } else {
+            ("\u{394}\u{b0}C", Exact::new(Complex::from(1), true))
+        };
+        let mut base_units = HashMap::new();
+        base_units.insert(BaseUnit::new_static("kelvin"), 1.into());
+        Ok((
+            NamedUnit::new(
+                Cow::Borrowed(""),
+                Cow::Borrowed(name),
+                Cow::Borrowed(name),
+                base_units,
+                kelvin_scale.value,
+            )
+            .into_temperature_difference(),
+            Exact::new(unit.scale.clone(), true),
+        ))
+    }
+
     /// guarantees that base units with an cancelled exponents do not appear in the hashmap
     fn to_hashmap_and_scale<I: Interrupt>(&self, int: &I) -> Result<HashmapScale, FendError> {
         let mut hashmap = HashMap::<BaseUnit, Complex>::new();
@@ -1023,6 +1599,14 @@ impl Unit {
     }
 
     /// Returns the combined scale factor if successful
+    fn is_compatible_with<I: Interrupt>(&self, other: &Self, int: &I) -> Result<bool, FendError> {
+        let (hash_a, _) = self.to_hashmap_and_scale(int)?;
+        let (hash_b, _) = other.to_hashmap_and_scale(int)?;
+        let (hash_a, _, _) = Self::reduce_hashmap(hash_a, int)?;
+        let (hash_b, _, _) = Self::reduce_hashmap(hash_b, int)?;
+        Ok(hash_a == hash_b)
+    }
+
     fn compute_scale_factor<I: Interrupt>(
         from: &Self,
         into: &Self,
@@ -3,8 +3,12 @@ use std::{borrow::Cow, collections::HashMap, fmt, io};
 use super::base_unit::BaseUnit;
 use crate::{
     error::FendError,
-    num::complex::Complex,
-    serialize::{deserialize_string, deserialize_usize, serialize_string, serialize_usize},
+    num::{complex::Complex, Exact},
+    serialize::{
+        deserialize_bool, deserialize_string, deserialize_u64, deserialize_usize, serialize_bool,
+        serialize_string, serialize_u64, serialize_usize,
+    },
+    Interrupt,
 };
 
 /// A named unit, like kilogram, megabyte or percent.
@@ -15,6 +19,12 @@ pub(crate) struct NamedUnit {
     plural_name: Cow<'static, str>,
     pub(super) base_units: HashMap<BaseUnit, Complex>,
     pub(super) scale: Complex,
+    /// Numerical factor shown before the unit, e.g. 100 for the `100 km`
+    /// in `L / 100 km`
+    factor: u64,
+    /// Set for temperature differences like `Δ°C`, which are added to
+    /// absolute temperatures without an offset
+    temperature_difference: bool,
 }
 
 impl NamedUnit {
@@ -31,6 +41,8 @@ impl NamedUnit {
             plural_name,
             base_units,
             scale: scale.into(),
+            factor: 1,
+            temperature_difference: false,
         }
     }
 
@@ -46,6 +58,8 @@ impl NamedUnit {
         }
 
         self.scale.serialize(write)?;
+        serialize_u64(self.factor, write)?;
+        serialize_bool(self.temperature_difference, write)?;
         Ok(())
     }
 
@@ -67,6 +81,8 @@ impl NamedUnit {
             plural_name: Cow::Owned(plural_name),
             base_units: hashmap,
             scale: Complex::deserialize(read)?,
+            factor: deserialize_u64(read)?,
+            temperature_difference: deserialize_bool(read)?,
         })
     }
 
@@ -81,9 +97,44 @@ impl NamedUnit {
                 base_units
             },
             scale: 1.into(),
+            factor: 1,
+            temperature_difference: false,
         }
     }
 
+    /// Returns a unit like `100 km`, as used for rates like `L per 100 km`
+    pub(crate) fn with_factor<I: Interrupt>(
+        &self,
+        factor: u64,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        Ok(Self {
+            scale: Exact::new(self.scale.clone(), true)
+                .mul(&Exact::new(factor.into(), true), int)?
+                .value,
+            factor: self
+                .factor
+                .checked_mul(factor)
+                .ok_or(FendError::NumberTooLargeForInteger)?,
+            ..self.clone()
+        })
+    }
+
+    pub(crate) fn factor(&self) -> u64 {
+        self.factor
+    }
+
+    pub(crate) fn into_temperature_difference(self) -> Self {
+        Self {
+            temperature_difference: true,
+            ..self
+        }
+    }
+
+    pub(crate) fn is_temperature_difference(&self) -> bool {
+        self.temperature_difference
+    }
+
     pub(crate) fn prefix_and_name(&self, plural: bool) -> (&str, &str) {
         (
             self.prefix.as_ref(),
@@ -95,6 +146,22 @@ impl NamedUnit {
         )
     }
 
+    pub(crate) fn with_spelling(mut self, spelling: crate::UnitSpelling) -> Self {
+        if spelling == crate::UnitSpelling::British {
+            for name in [&mut self.singular_name, &mut self.plural_name] {
+                let british = match name.as_ref() {
+                    "meter" => "metre",
+                    "meters" => "metres",
+                    "liter" => "litre",
+                    "liters" => "litres",
+                    _ => continue,
+                };
+                *name = Cow::Borrowed(british);
+            }
+        }
+        self
+    }
+
     pub(crate) fn has_no_base_units(&self) -> bool {
         self.base_units.is_empty()
     }
@@ -107,6 +174,10 @@ impl NamedUnit {
         // while non-alphabetic names like % or ' shouldn't.
         // Empty names shouldn't really exist, but they might as well have a space.
 
+        if self.factor != 1 {
+            return true;
+        }
+
         // degree symbol
         if self.singular_name == "\u{b0}" {
             return false;
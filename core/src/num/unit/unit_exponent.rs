@@ -4,6 +4,7 @@ use crate::{
     error::FendError,
     interrupt::test_int,
     num::{
+        bigrat,
         complex::{self, Complex, UseParentheses},
         Base, Exact, FormattingStyle,
     },
@@ -101,12 +102,23 @@ impl UnitExponent {
         let (exact, exponent) = if exp == 1.into() {
             (true, None)
         } else {
-            let formatted =
-                exp.format(true, format, base, UseParentheses::IfComplexOrFraction, int)?;
+            // complex exponents are rare enough that they always use `i`
+            let options = complex::FormatOptions {
+                style: format,
+                base,
+                use_parentheses: UseParentheses::IfComplexOrFraction,
+                imaginary_unit: crate::ImaginaryUnitSymbol::I,
+                complex_output: crate::ComplexOutput::Rectangular,
+                rounding_mode: crate::RoundingMode::default(),
+                integer_digit_limit: None,
+                separators: bigrat::Separators::default(),
+            };
+            let formatted = exp.format(true, &options, int)?;
             (formatted.exact, Some(formatted.value))
         };
         Ok(Exact::new(
             FormattedExponent {
+                factor: self.unit.factor(),
                 prefix,
                 name,
                 number: exponent,
@@ -128,6 +140,7 @@ impl fmt::Debug for UnitExponent {
 
 #[derive(Debug)]
 pub(crate) struct FormattedExponent<'a> {
+    factor: u64,
     prefix: &'a str,
     name: &'a str,
     number: Option<complex::Formatted>,
@@ -135,6 +148,9 @@ pub(crate) struct FormattedExponent<'a> {
 
 impl<'a> fmt::Display for FormattedExponent<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.factor != 1 {
+            write!(f, "{} ", self.factor)?;
+        }
         write!(f, "{}{}", self.prefix, self.name.replace('_', " "))?;
         if let Some(number) = &self.number {
             write!(f, "^{number}")?;
@@ -1,6 +1,9 @@
-use crate::ast::{Bop, Expr};
+use crate::ast::{Bop, ComparisonOp, Expr};
+use crate::ident::Ident;
+use crate::interrupt::test_int;
 use crate::lexer::{Symbol, Token};
 use crate::value::Value;
+use crate::Interrupt;
 use std::fmt;
 
 #[derive(Debug)]
@@ -18,6 +21,38 @@ pub(crate) enum ParseError {
     ExpectedIdentifierInAssignment,
     ExpectedDotInLambda(Box<ParseError>),
     InvalidMixedFraction,
+    ExpectedAPercentage,
+    TooDeeplyNested,
+    Interrupted,
+    // an error along with the number of tokens that were left to parse,
+    // which is used to find its position in the input
+    At(Box<Self>, usize),
+}
+
+impl ParseError {
+    fn at(self, remaining: &[Token]) -> Self {
+        Self::At(Box::new(self), remaining.len())
+    }
+
+    // for errors found while parsing only a prefix of the input, this adds
+    // the number of tokens after the prefix
+    fn offset(self, tokens: usize) -> Self {
+        match self {
+            Self::At(e, remaining) => Self::At(e, remaining + tokens),
+            Self::ExpectedDotInLambda(e) => Self::ExpectedDotInLambda(Box::new(e.offset(tokens))),
+            e => e,
+        }
+    }
+
+    /// Returns how many tokens were left to parse when the error occurred,
+    /// if known
+    pub(crate) fn remaining_tokens(&self) -> Option<usize> {
+        match self {
+            Self::At(_, remaining) => Some(*remaining),
+            Self::ExpectedDotInLambda(e) => e.remaining_tokens(),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for ParseError {
@@ -44,21 +79,81 @@ impl fmt::Display for ParseError {
                 write!(f, "missing '.' in lambda (expected e.g. \\x.x)")
             }
             Self::InvalidMixedFraction => write!(f, "invalid mixed fraction"),
+            Self::ExpectedAPercentage => {
+                write!(
+                    f,
+                    "expected a percentage like '10%', instead found a plain number"
+                )
+            }
+            Self::TooDeeplyNested => write!(f, "expression is too deeply nested"),
+            Self::Interrupted => write!(f, "interrupted"),
+            Self::At(e, _) => write!(f, "{e}"),
         }
     }
 }
 
 type ParseResult<'a, T = Expr> = Result<(T, &'a [Token]), ParseError>;
 
+// The result of parsing an infix operator and its right-hand operand: `None`
+// if the operator isn't there, but once it has been found a missing or
+// invalid operand is an error (e.g. in `1 + )`)
+type OperatorResult<'a> = Result<Option<(Expr, &'a [Token])>, ParseError>;
+
+// Both parsing and evaluation are recursive, so very deeply nested input
+// (e.g. thousands of opening parentheses) would otherwise overflow the stack.
+// This limit is chosen so that optimised builds stay well within a 1 MiB
+// stack (e.g. on WebAssembly); unoptimised builds need a larger stack.
+const MAX_DEPTH: usize = 128;
+
+// Chains of left-associative operators like `1 + 2 + 3` are parsed and
+// evaluated iteratively, so they don't count towards `MAX_DEPTH`. Other
+// operations on expressions (e.g. formatting or cloning a lambda) are still
+// recursive though, so the overall height of the tree is limited separately.
+const MAX_HEIGHT: usize = 1024;
+
+// Backtracking can make parsing take exponential time for some nested
+// inputs (e.g. `(1 (1 (1 ...)))`), so this is also where we check for
+// interrupts
+fn nested<I: Interrupt>(depth: usize, int: &I) -> Result<usize, ParseError> {
+    if depth >= MAX_DEPTH {
+        return Err(ParseError::TooDeeplyNested);
+    }
+    test_int(int).map_err(|_| ParseError::Interrupted)?;
+    Ok(depth + 1)
+}
+
+// Chains of left-associative operators don't nest, but their length still
+// counts towards the height of the tree
+fn chained<I: Interrupt>(height: usize, int: &I) -> Result<usize, ParseError> {
+    if height >= MAX_HEIGHT {
+        return Err(ParseError::TooDeeplyNested);
+    }
+    test_int(int).map_err(|_| ParseError::Interrupted)?;
+    Ok(height + 1)
+}
+
+// Like `Result::ok`, except that `TooDeeplyNested` and `Interrupted` errors
+// are still propagated instead of falling back to a different way of parsing
+fn try_parse<T>(result: Result<T, ParseError>) -> Result<Option<T>, ParseError> {
+    match result {
+        Ok(res) => Ok(Some(res)),
+        Err(e @ (ParseError::TooDeeplyNested | ParseError::Interrupted)) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
 impl From<ParseError> for crate::error::FendError {
     fn from(e: ParseError) -> Self {
-        Self::ParseError(e)
+        match e {
+            ParseError::Interrupted => Self::Interrupted,
+            e => Self::ParseError(e),
+        }
     }
 }
 
 fn parse_token(input: &[Token]) -> ParseResult<'_, Token> {
     if input.is_empty() {
-        return Err(ParseError::ExpectedAToken);
+        return Err(ParseError::ExpectedAToken.at(input));
     }
     Ok((input[0].clone(), &input[1..]))
 }
@@ -69,111 +164,196 @@ fn parse_fixed_symbol(input: &[Token], symbol: Symbol) -> ParseResult<'_, ()> {
         if sym == symbol {
             Ok(((), remaining))
         } else {
-            Err(ParseError::ExpectedToken(sym, symbol))
+            Err(ParseError::ExpectedToken(sym, symbol).at(input))
         }
     } else {
-        Err(ParseError::FoundInvalidTokenWhileExpecting(symbol))
+        Err(ParseError::FoundInvalidTokenWhileExpecting(symbol).at(input))
     }
 }
 
 fn parse_number(input: &[Token]) -> ParseResult<'_> {
     match parse_token(input)? {
         (Token::Num(num), remaining) => Ok((Expr::Literal(Value::Num(Box::new(num))), remaining)),
-        _ => Err(ParseError::ExpectedANumber),
+        _ => Err(ParseError::ExpectedANumber.at(input)),
     }
 }
 
-fn parse_ident(input: &[Token]) -> ParseResult<'_> {
+fn parse_ident<'a, I: Interrupt>(input: &'a [Token], depth: usize, int: &I) -> ParseResult<'a> {
     match parse_token(input)? {
         (Token::Ident(ident), remaining) => {
+            // boolean literals can't be redefined, unlike other identifiers
+            match ident.as_str() {
+                "true" => return Ok((Expr::Literal(Value::Bool(true)), remaining)),
+                "false" => return Ok((Expr::Literal(Value::Bool(false)), remaining)),
+                _ => (),
+            }
             if let Ok(((), remaining2)) = parse_fixed_symbol(remaining, Symbol::Of) {
-                let (inner, remaining3) = parse_parens_or_literal(remaining2)?;
+                let (inner, remaining3) =
+                    parse_parens_or_literal(remaining2, nested(depth, int)?, int)?;
                 Ok((Expr::Of(ident, Box::new(inner)), remaining3))
             } else {
                 Ok((Expr::Ident(ident), remaining))
             }
         }
-        _ => Err(ParseError::ExpectedIdentifier),
+        _ => Err(ParseError::ExpectedIdentifier.at(input)),
     }
 }
 
-fn parse_parens(input: &[Token]) -> ParseResult<'_> {
+fn parse_parens<'a, I: Interrupt>(input: &'a [Token], depth: usize, int: &I) -> ParseResult<'a> {
     let (_, input) = parse_fixed_symbol(input, Symbol::OpenParens)?;
     if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::CloseParens) {
         return Ok((Expr::Literal(Value::Unit), remaining));
     }
-    let (inner, mut input) = parse_expression(input)?;
+    let depth = nested(depth, int)?;
+    let (inner, mut input) = parse_expression(input, depth, int)?;
+    let mut items = vec![inner];
+    // comma-separated lists, e.g. for functions with multiple arguments
+    while let Ok(((), remaining)) = parse_fixed_symbol(input, Symbol::Comma) {
+        let (item, remaining) = parse_expression(remaining, depth, int)?;
+        items.push(item);
+        input = remaining;
+    }
     // allow omitting closing parentheses at end of input
     if !input.is_empty() {
         let (_, remaining) = parse_fixed_symbol(input, Symbol::CloseParens)?;
         input = remaining;
     }
-    Ok((Expr::Parens(Box::new(inner)), input))
+    if items.len() == 1 {
+        Ok((Expr::Parens(Box::new(items.remove(0))), input))
+    } else {
+        Ok((Expr::List(items), input))
+    }
 }
 
-fn parse_backslash_lambda(input: &[Token]) -> ParseResult<'_> {
+fn parse_backslash_lambda<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
     let (_, input) = parse_fixed_symbol(input, Symbol::Backslash)?;
-    let (ident, input) = if let (Expr::Ident(ident), input) = parse_ident(input)? {
+    let (ident, input) = if let (Expr::Ident(ident), input) = parse_ident(input, depth, int)? {
         (ident, input)
     } else {
         return Err(ParseError::ExpectedIdentifier);
     };
     let (_, input) = parse_fixed_symbol(input, Symbol::Dot)
         .map_err(|e| ParseError::ExpectedDotInLambda(Box::new(e)))?;
-    let (rhs, input) = parse_function(input)?;
+    let (rhs, input) = parse_function(input, nested(depth, int)?, int)?;
     Ok((Expr::Fn(ident, Box::new(rhs)), input))
 }
 
-fn parse_parens_or_literal(input: &[Token]) -> ParseResult<'_> {
+fn parse_parens_or_literal<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
     let (token, remaining) = parse_token(input)?;
 
     match token {
         Token::Num(_) => parse_number(input),
-        Token::Ident(_) => parse_ident(input),
+        Token::Ident(_) => parse_ident(input, depth, int),
         Token::StringLiteral(s) => Ok((Expr::Literal(Value::String(s)), remaining)),
-        Token::Symbol(Symbol::OpenParens) => parse_parens(input),
-        Token::Symbol(Symbol::Backslash) => parse_backslash_lambda(input),
-        Token::Symbol(s) => Err(ParseError::UnexpectedSymbol(s)),
+        Token::Symbol(Symbol::OpenParens) => parse_parens(input, depth, int),
+        Token::Symbol(Symbol::Backslash) => parse_backslash_lambda(input, depth, int),
+        // `mod` is an operator, but can also be called as a function,
+        // e.g. `mod(7, 3)`
+        Token::Symbol(Symbol::Mod) => Ok((Expr::Ident(Ident::new_str("mod")), remaining)),
+        Token::Symbol(s) => Err(ParseError::UnexpectedSymbol(s).at(input)),
     }
 }
 
-fn parse_factorial(input: &[Token]) -> ParseResult<'_> {
-    let (mut res, mut input) = parse_parens_or_literal(input)?;
+fn parse_factorial<'a, I: Interrupt>(
+    input: &'a [Token],
+    mut depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (mut res, mut input) = parse_parens_or_literal(input, depth, int)?;
     while let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Factorial) {
         res = Expr::Factorial(Box::new(res));
         input = remaining;
+        depth = nested(depth, int)?;
     }
     Ok((res, input))
 }
 
-fn parse_power(input: &[Token], allow_unary: bool) -> ParseResult<'_> {
+fn parse_power<'a, I: Interrupt>(
+    input: &'a [Token],
+    allow_unary: bool,
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
     if allow_unary {
         if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Sub) {
-            let (result, remaining) = parse_power(remaining, true)?;
+            let (result, remaining) = parse_power(remaining, true, nested(depth, int)?, int)?;
             return Ok((Expr::UnaryMinus(Box::new(result)), remaining));
         }
         if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Add) {
-            let (result, remaining) = parse_power(remaining, true)?;
+            let (result, remaining) = parse_power(remaining, true, nested(depth, int)?, int)?;
             return Ok((Expr::UnaryPlus(Box::new(result)), remaining));
         }
         // The precedence of unary division relative to exponentiation
         // is not important because /a^b -> (1/a)^b == 1/(a^b)
-        if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Div) {
-            let (result, remaining) = parse_power(remaining, true)?;
+        if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Div)
+            .or_else(|_| parse_fixed_symbol(input, Symbol::Per))
+        {
+            let (result, remaining) = parse_power(remaining, true, nested(depth, int)?, int)?;
             return Ok((Expr::UnaryDiv(Box::new(result)), remaining));
         }
+        // `//a` in prefix position is still two unary divisions
+        if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::IntDiv) {
+            let (result, remaining) = parse_power(remaining, true, nested(depth, int)?, int)?;
+            return Ok((
+                Expr::UnaryDiv(Box::new(Expr::UnaryDiv(Box::new(result)))),
+                remaining,
+            ));
+        }
     }
-    let (mut result, mut input) = parse_factorial(input)?;
+    // `√` binds like unary minus, so `√4^2` is `√(4^2)` and `√25%` is
+    // `(√25)%`. It can also follow a number, e.g. `2√4`. The same applies
+    // to `∛` and `∜`.
+    for (symbol, n) in [
+        (Symbol::Sqrt, 2),
+        (Symbol::CubeRoot, 3),
+        (Symbol::FourthRoot, 4),
+    ] {
+        if let Ok((_, remaining)) = parse_fixed_symbol(input, symbol) {
+            let (result, remaining) = parse_power(remaining, true, nested(depth, int)?, int)?;
+            return Ok((root_expr(result, n), remaining));
+        }
+    }
+    let (mut result, mut input) = parse_factorial(input, depth, int)?;
     if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Pow) {
-        let (rhs, remaining) = parse_power(remaining, true)?;
+        let (rhs, remaining) = parse_power(remaining, true, nested(depth, int)?, int)?;
         result = Expr::Bop(Bop::Pow, Box::new(result), Box::new(rhs));
         input = remaining;
     }
     Ok((result, input))
 }
 
-fn parse_apply_cont<'a>(input: &'a [Token], lhs: &Expr) -> ParseResult<'a> {
-    let (rhs, input) = parse_power(input, false)?;
+fn root_expr(x: Expr, n: u64) -> Expr {
+    match n {
+        2 => Expr::ApplyFunctionCall(Box::new(Expr::Ident(Ident::new_str("sqrt"))), Box::new(x)),
+        3 => Expr::ApplyFunctionCall(Box::new(Expr::Ident(Ident::new_str("cbrt"))), Box::new(x)),
+        // `x^(1/n)`
+        n => Expr::Bop(
+            Bop::Pow,
+            Box::new(x),
+            Box::new(Expr::Parens(Box::new(Expr::Bop(
+                Bop::Div,
+                Box::new(Expr::Literal(Value::Num(Box::new(1.into())))),
+                Box::new(Expr::Literal(Value::Num(Box::new(n.into())))),
+            )))),
+        ),
+    }
+}
+
+fn parse_apply_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    lhs: &Expr,
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (rhs, input) = parse_power(input, false, depth, int)?;
     Ok((
         match (lhs, &rhs) {
             (
@@ -209,7 +389,12 @@ fn parse_apply_cont<'a>(input: &'a [Token], lhs: &Expr) -> ParseResult<'a> {
     ))
 }
 
-fn parse_mixed_fraction<'a>(input: &'a [Token], lhs: &Expr) -> ParseResult<'a> {
+fn parse_mixed_fraction<'a, I: Interrupt>(
+    input: &'a [Token],
+    lhs: &Expr,
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
     let (positive, lhs, other_factor) = match lhs {
         Expr::Literal(Value::Num(_)) => (true, lhs, None),
         Expr::UnaryMinus(x) => {
@@ -232,13 +417,13 @@ fn parse_mixed_fraction<'a>(input: &'a [Token], lhs: &Expr) -> ParseResult<'a> {
         },
         _ => return Err(ParseError::InvalidMixedFraction),
     };
-    let (rhs_top, input) = parse_power(input, false)?;
+    let (rhs_top, input) = parse_power(input, false, depth, int)?;
     if let Expr::Literal(Value::Num(_)) = rhs_top {
     } else {
         return Err(ParseError::InvalidMixedFraction);
     }
     let (_, input) = parse_fixed_symbol(input, Symbol::Div)?;
-    let (rhs_bottom, input) = parse_power(input, false)?;
+    let (rhs_bottom, input) = parse_power(input, false, depth, int)?;
     if let Expr::Literal(Value::Num(_)) = rhs_bottom {
     } else {
         return Err(ParseError::InvalidMixedFraction);
@@ -259,58 +444,131 @@ fn parse_mixed_fraction<'a>(input: &'a [Token], lhs: &Expr) -> ParseResult<'a> {
     Ok((mixed_fraction, input))
 }
 
-fn parse_multiplication_cont(input: &[Token]) -> ParseResult<'_> {
-    let (_, input) = parse_fixed_symbol(input, Symbol::Mul)?;
-    let (b, input) = parse_power(input, true)?;
-    Ok((b, input))
+fn parse_multiplication_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::Mul) else {
+        return Ok(None);
+    };
+    parse_power(input, true, depth, int).map(Some)
 }
 
-fn parse_division_cont(input: &[Token]) -> ParseResult<'_> {
-    let (_, input) = parse_fixed_symbol(input, Symbol::Div)?;
-    let (b, input) = parse_power(input, true)?;
-    Ok((b, input))
+fn parse_division_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::Div) else {
+        return Ok(None);
+    };
+    parse_power(input, true, depth, int).map(Some)
 }
 
-fn parse_modulo_cont(input: &[Token]) -> ParseResult<'_> {
-    let (_, input) = parse_fixed_symbol(input, Symbol::Mod)?;
-    let (b, input) = parse_power(input, true)?;
-    Ok((b, input))
+fn parse_int_division_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::IntDiv) else {
+        return Ok(None);
+    };
+    parse_power(input, true, depth, int).map(Some)
+}
+
+// `per` has the same precedence as `/`, but a number on its right-hand side
+// includes the unit that follows it, so `L per 100 km` is parsed as
+// `L / (100 km)` rather than `L / 100 * km`
+fn parse_per_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    mut depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::Per) else {
+        return Ok(None);
+    };
+    let (mut b, mut input) = parse_power(input, true, depth, int)?;
+    if let Expr::Literal(Value::Num(_)) = b {
+        while let Some((new_b, remaining)) = try_parse(parse_apply_cont(input, &b, depth, int))? {
+            b = new_b;
+            input = remaining;
+            depth = nested(depth, int)?;
+        }
+    }
+    Ok(Some((b, input)))
 }
 
-fn parse_multiplicative(input: &[Token]) -> ParseResult<'_> {
-    let (mut res, mut input) = parse_power(input, true)?;
+fn parse_modulo_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::Mod) else {
+        return Ok(None);
+    };
+    parse_power(input, true, depth, int).map(Some)
+}
+
+fn parse_multiplicative<'a, I: Interrupt>(
+    input: &'a [Token],
+    mut depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (mut res, mut input) = parse_power(input, true, depth, int)?;
+    let mut height = depth;
     loop {
-        if let Ok((term, remaining)) = parse_multiplication_cont(input) {
-            res = Expr::Bop(Bop::Mul, Box::new(res.clone()), Box::new(term));
+        if let Some((term, remaining)) = parse_multiplication_cont(input, depth, int)? {
+            res = Expr::Bop(Bop::Mul, Box::new(res), Box::new(term));
+            input = remaining;
+        } else if let Some((term, remaining)) = parse_division_cont(input, depth, int)? {
+            res = Expr::Bop(Bop::Div, Box::new(res), Box::new(term));
             input = remaining;
-        } else if let Ok((term, remaining)) = parse_division_cont(input) {
-            res = Expr::Bop(Bop::Div, Box::new(res.clone()), Box::new(term));
+        } else if let Some((term, remaining)) = parse_int_division_cont(input, depth, int)? {
+            res = Expr::Bop(Bop::IntDiv, Box::new(res), Box::new(term));
             input = remaining;
-        } else if let Ok((term, remaining)) = parse_modulo_cont(input) {
-            res = Expr::Bop(Bop::Mod, Box::new(res.clone()), Box::new(term));
+        } else if let Some((term, remaining)) = parse_per_cont(input, depth, int)? {
+            res = Expr::Bop(Bop::Div, Box::new(res), Box::new(term));
             input = remaining;
-        } else if let Ok((new_res, remaining)) = parse_mixed_fraction(input, &res) {
+        } else if let Some((term, remaining)) = parse_modulo_cont(input, depth, int)? {
+            res = Expr::Bop(Bop::Mod, Box::new(res), Box::new(term));
+            input = remaining;
+        } else if let Some((new_res, remaining)) =
+            try_parse(parse_mixed_fraction(input, &res, depth, int))?
+        {
             res = new_res;
             input = remaining;
-        } else if let Ok((new_res, remaining)) = parse_apply_cont(input, &res) {
+        } else if let Some((new_res, remaining)) =
+            try_parse(parse_apply_cont(input, &res, depth, int))?
+        {
+            // unlike the operators above, applications are evaluated
+            // recursively, so each one nests `res` one level deeper
             res = new_res;
             input = remaining;
+            depth = nested(depth, int)?;
         } else {
             break;
         }
+        height = chained(height, int)?;
     }
     Ok((res, input))
 }
 
-fn parse_implicit_addition(input: &[Token]) -> ParseResult<'_> {
-    let (res, input) = parse_multiplicative(input)?;
-    if let Ok((rhs, remaining)) = parse_implicit_addition(input) {
+fn parse_implicit_addition<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (res, input) = parse_multiplicative(input, depth, int)?;
+    // only quantities like `5 feet` can be followed by an implicit addition
+    if !matches!(res, Expr::ApplyMul(_, _)) {
+        return Ok((res, input));
+    }
+    if let Some((rhs, remaining)) =
+        try_parse(parse_implicit_addition(input, nested(depth, int)?, int))?
+    {
         // n i n i, n i i n i i, etc. (n: number literal, i: identifier)
-        if let (
-            Expr::ApplyMul(_, _),
-            Expr::ApplyMul(_, _) | Expr::Bop(Bop::ImplicitPlus, _, _) | Expr::Literal(_),
-        ) = (&res, &rhs)
-        {
+        if let Expr::ApplyMul(_, _) | Expr::Bop(Bop::ImplicitPlus, _, _) | Expr::Literal(_) = rhs {
             return Ok((
                 Expr::Bop(Bop::ImplicitPlus, Box::new(res), Box::new(rhs)),
                 remaining,
@@ -320,48 +578,68 @@ fn parse_implicit_addition(input: &[Token]) -> ParseResult<'_> {
     Ok((res, input))
 }
 
-fn parse_addition_cont(input: &[Token]) -> ParseResult<'_> {
-    let (_, input) = parse_fixed_symbol(input, Symbol::Add)?;
-    let (b, input) = parse_implicit_addition(input)?;
-    Ok((b, input))
+fn parse_addition_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::Add) else {
+        return Ok(None);
+    };
+    parse_implicit_addition(input, depth, int).map(Some)
 }
 
-fn parse_subtraction_cont(input: &[Token]) -> ParseResult<'_> {
-    let (_, input) = parse_fixed_symbol(input, Symbol::Sub)?;
-    let (b, input) = parse_implicit_addition(input)?;
-    Ok((b, input))
+fn parse_subtraction_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::Sub) else {
+        return Ok(None);
+    };
+    parse_implicit_addition(input, depth, int).map(Some)
 }
 
-fn parse_to_cont(input: &[Token]) -> ParseResult<'_> {
-    let (_, input) = parse_fixed_symbol(input, Symbol::UnitConversion)?;
-    let (b, input) = parse_implicit_addition(input)?;
-    Ok((b, input))
+fn parse_to_cont<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> OperatorResult<'a> {
+    let Ok((_, input)) = parse_fixed_symbol(input, Symbol::UnitConversion) else {
+        return Ok(None);
+    };
+    parse_implicit_addition(input, depth, int).map(Some)
 }
 
-fn parse_additive(input: &[Token]) -> ParseResult<'_> {
-    let (mut res, mut input) = parse_implicit_addition(input)?;
+fn parse_additive<'a, I: Interrupt>(input: &'a [Token], depth: usize, int: &I) -> ParseResult<'a> {
+    let (mut res, mut input) = parse_implicit_addition(input, depth, int)?;
+    let mut height = depth;
     loop {
-        if let Ok((term, remaining)) = parse_addition_cont(input) {
+        if let Some((term, remaining)) = parse_addition_cont(input, depth, int)? {
             res = Expr::Bop(Bop::Plus, Box::new(res), Box::new(term));
             input = remaining;
-        } else if let Ok((term, remaining)) = parse_subtraction_cont(input) {
+        } else if let Some((term, remaining)) = parse_subtraction_cont(input, depth, int)? {
             res = Expr::Bop(Bop::Minus, Box::new(res), Box::new(term));
             input = remaining;
-        } else if let Ok((term, remaining)) = parse_to_cont(input) {
+        } else if let Some((term, remaining)) = parse_to_cont(input, depth, int)? {
+            // conversions are left-associative, so `1 km to m to cm`
+            // is `(1 km to m) to cm`
             res = Expr::As(Box::new(res), Box::new(term));
             input = remaining;
         } else {
             break;
         }
+        height = chained(height, int)?;
     }
     Ok((res, input))
 }
 
-fn parse_bitshifts(input: &[Token]) -> ParseResult<'_> {
-    let (mut result, mut input) = parse_additive(input)?;
+fn parse_bitshifts<'a, I: Interrupt>(input: &'a [Token], depth: usize, int: &I) -> ParseResult<'a> {
+    let (mut result, mut input) = parse_additive(input, depth, int)?;
+    let mut height = depth;
     loop {
         if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::ShiftLeft) {
-            let (rhs, remaining) = parse_additive(remaining)?;
+            let (rhs, remaining) = parse_additive(remaining, depth, int)?;
             result = Expr::Bop(
                 Bop::Bitwise(crate::ast::BitwiseBop::LeftShift),
                 Box::new(result),
@@ -369,7 +647,7 @@ fn parse_bitshifts(input: &[Token]) -> ParseResult<'_> {
             );
             input = remaining;
         } else if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::ShiftRight) {
-            let (rhs, remaining) = parse_additive(remaining)?;
+            let (rhs, remaining) = parse_additive(remaining, depth, int)?;
             result = Expr::Bop(
                 Bop::Bitwise(crate::ast::BitwiseBop::RightShift),
                 Box::new(result),
@@ -379,67 +657,250 @@ fn parse_bitshifts(input: &[Token]) -> ParseResult<'_> {
         } else {
             break;
         }
+        height = chained(height, int)?;
     }
     Ok((result, input))
 }
 
-fn parse_bitwise_and(input: &[Token]) -> ParseResult<'_> {
-    let (mut result, mut input) = parse_bitshifts(input)?;
+fn parse_comparison_op(input: &[Token]) -> Option<(ComparisonOp, &[Token])> {
+    let op = match input.first()? {
+        Token::Symbol(Symbol::DoubleEquals) => ComparisonOp::Equal,
+        Token::Symbol(Symbol::NotEquals) => ComparisonOp::NotEqual,
+        Token::Symbol(Symbol::LessThan) => ComparisonOp::Less,
+        Token::Symbol(Symbol::LessThanOrEqual) => ComparisonOp::LessOrEqual,
+        Token::Symbol(Symbol::GreaterThan) => ComparisonOp::Greater,
+        Token::Symbol(Symbol::GreaterThanOrEqual) => ComparisonOp::GreaterOrEqual,
+        _ => return None,
+    };
+    Some((op, &input[1..]))
+}
+
+fn parse_comparison<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (mut result, mut input) = parse_bitshifts(input, depth, int)?;
+    let mut height = depth;
+    while let Some((op, remaining)) = parse_comparison_op(input) {
+        let (rhs, remaining) = parse_bitshifts(remaining, depth, int)?;
+        result = Expr::Bop(Bop::Comparison(op), Box::new(result), Box::new(rhs));
+        input = remaining;
+        height = chained(height, int)?;
+    }
+    Ok((result, input))
+}
+
+fn parse_bitwise_and<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (mut result, mut input) = parse_comparison(input, depth, int)?;
+    let mut height = depth;
     while let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::BitwiseAnd) {
-        let (rhs, remaining) = parse_bitshifts(remaining)?;
+        let (rhs, remaining) = parse_comparison(remaining, depth, int)?;
         result = Expr::Bop(
             Bop::Bitwise(crate::ast::BitwiseBop::And),
             Box::new(result),
             Box::new(rhs),
         );
         input = remaining;
+        height = chained(height, int)?;
     }
     Ok((result, input))
 }
 
-fn parse_bitwise_xor(input: &[Token]) -> ParseResult<'_> {
-    let (mut result, mut input) = parse_bitwise_and(input)?;
+fn parse_bitwise_xor<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (mut result, mut input) = parse_bitwise_and(input, depth, int)?;
+    let mut height = depth;
     while let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::BitwiseXor) {
-        let (rhs, remaining) = parse_bitwise_and(remaining)?;
+        let (rhs, remaining) = parse_bitwise_and(remaining, depth, int)?;
         result = Expr::Bop(
             Bop::Bitwise(crate::ast::BitwiseBop::Xor),
             Box::new(result),
             Box::new(rhs),
         );
         input = remaining;
+        height = chained(height, int)?;
     }
     Ok((result, input))
 }
 
-fn parse_bitwise_or(input: &[Token]) -> ParseResult<'_> {
-    let (mut result, mut input) = parse_bitwise_xor(input)?;
+fn parse_bitwise_or<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (mut result, mut input) = parse_bitwise_xor(input, depth, int)?;
+    let mut height = depth;
     while let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::BitwiseOr) {
-        let (rhs, remaining) = parse_bitwise_xor(remaining)?;
+        let (rhs, remaining) = parse_bitwise_xor(remaining, depth, int)?;
         result = Expr::Bop(
             Bop::Bitwise(crate::ast::BitwiseBop::Or),
             Box::new(result),
             Box::new(rhs),
         );
         input = remaining;
+        height = chained(height, int)?;
     }
     Ok((result, input))
 }
 
-fn parse_combination(input: &[Token]) -> ParseResult<'_> {
-    let (mut result, mut input) = parse_bitwise_or(input)?;
+fn parse_combination<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (mut result, mut input) = parse_bitwise_or(input, depth, int)?;
+    let mut height = depth;
     while let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Combination) {
-        let (rhs, remaining) = parse_bitwise_or(remaining)?;
+        let (rhs, remaining) = parse_bitwise_or(remaining, depth, int)?;
         result = Expr::Bop(Bop::Combination, Box::new(result), Box::new(rhs));
         input = remaining;
+        height = chained(height, int)?;
     }
     Ok((result, input))
 }
 
-fn parse_function(input: &[Token]) -> ParseResult<'_> {
-    let (lhs, input) = parse_combination(input)?;
+fn parse_conditional<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let ((), input) = parse_fixed_symbol(input, Symbol::If)?;
+    let depth = nested(depth, int)?;
+    let (condition, input) = parse_function(input, depth, int)?;
+    let ((), input) = parse_fixed_symbol(input, Symbol::Then)?;
+    let (then_branch, input) = parse_function(input, depth, int)?;
+    let ((), input) = parse_fixed_symbol(input, Symbol::Else)?;
+    let (else_branch, input) = parse_function(input, depth, int)?;
+    Ok((
+        Expr::If(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+        ),
+        input,
+    ))
+}
+
+// Finds the first token matching the given predicate that isn't inside
+// parentheses, without looking past the end of the current statement
+fn find_top_level(input: &[Token], predicate: impl Fn(&Token) -> bool) -> Option<usize> {
+    let mut parens = 0_usize;
+    for (idx, token) in input.iter().enumerate() {
+        match token {
+            Token::Symbol(Symbol::OpenParens) => parens += 1,
+            Token::Symbol(Symbol::CloseParens) => parens = parens.checked_sub(1)?,
+            Token::Symbol(Symbol::Semicolon) if parens == 0 => return None,
+            token if parens == 0 && predicate(token) => return Some(idx),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn is_ident(token: &Token, name: &str) -> bool {
+    matches!(token, Token::Ident(ident) if ident.as_str() == name)
+}
+
+// Parses the fixed phrasings `what % is <a> of <b>` (or `what percent is
+// <a> of <b>`), `increase <a> by <b>` and `decrease <a> by <b>`. Returns
+// `None` if the input doesn't use one of these phrasings. Like `if`, these
+// are only recognised at the start of an expression, so they need to be
+// wrapped in parentheses when used as an operand (e.g. `2 * (increase 100
+// by 10%)`).
+fn parse_percentage_query<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> Result<Option<(Expr, &'a [Token])>, ParseError> {
+    let (query, input) = match input {
+        [what, Token::Ident(percent), is, rest @ ..]
+            if is_ident(what, "what")
+                && matches!(percent.as_str(), "%" | "percent")
+                && is_ident(is, "is") =>
+        {
+            ("what", rest)
+        }
+        [verb @ Token::Ident(ident), rest @ ..]
+            if is_ident(verb, "increase") || is_ident(verb, "decrease") =>
+        {
+            (ident.as_str(), rest)
+        }
+        _ => return Ok(None),
+    };
+    let separator = find_top_level(input, |token| {
+        if query == "what" {
+            matches!(token, Token::Symbol(Symbol::Of))
+        } else {
+            is_ident(token, "by")
+        }
+    });
+    let Some(separator) = separator else {
+        return Ok(None);
+    };
+    let depth = nested(depth, int)?;
+    let after_separator = input.len() - separator;
+    let (a, remaining) =
+        parse_function(&input[..separator], depth, int).map_err(|e| e.offset(after_separator))?;
+    if !remaining.is_empty() {
+        return Err(ParseError::UnexpectedInput
+            .at(remaining)
+            .offset(after_separator));
+    }
+    let (b, remaining) = parse_function(&input[separator + 1..], depth, int)?;
+    // `increase 200 by 10` would otherwise be 2200
+    if query != "what" && is_plain_number(&b) {
+        return Err(ParseError::ExpectedAPercentage.at(&input[separator + 1..]));
+    }
+    let (a, b) = (Box::new(a), Box::new(b));
+    let one = || Box::new(Expr::Literal(Value::Num(Box::new(1.into()))));
+    let result = match query {
+        "what" => Expr::As(
+            Box::new(Expr::Bop(Bop::Div, a, b)),
+            Box::new(Expr::Ident(Ident::new_str("%"))),
+        ),
+        "increase" => Expr::Bop(
+            Bop::Mul,
+            a,
+            Box::new(Expr::Parens(Box::new(Expr::Bop(Bop::Plus, one(), b)))),
+        ),
+        _ => Expr::Bop(
+            Bop::Mul,
+            a,
+            Box::new(Expr::Parens(Box::new(Expr::Bop(Bop::Minus, one(), b)))),
+        ),
+    };
+    Ok(Some((result, remaining)))
+}
+
+fn is_plain_number(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(Value::Num(_)) => true,
+        Expr::UnaryMinus(inner) | Expr::UnaryPlus(inner) | Expr::Parens(inner) => {
+            is_plain_number(inner)
+        }
+        _ => false,
+    }
+}
+
+fn parse_function<'a, I: Interrupt>(input: &'a [Token], depth: usize, int: &I) -> ParseResult<'a> {
+    if let Some(Token::Symbol(Symbol::If)) = input.first() {
+        return parse_conditional(input, depth, int);
+    }
+    if let Some(result) = parse_percentage_query(input, depth, int)? {
+        return Ok(result);
+    }
+    let (lhs, input) = parse_combination(input, depth, int)?;
     if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Fn) {
         if let Expr::Ident(s) = lhs {
-            let (rhs, remaining) = parse_function(remaining)?;
+            let (rhs, remaining) = parse_function(remaining, nested(depth, int)?, int)?;
             return Ok((Expr::Fn(s, Box::new(rhs)), remaining));
         }
         return Err(ParseError::ExpectedIdentifierAsArgument);
@@ -447,11 +908,15 @@ fn parse_function(input: &[Token]) -> ParseResult<'_> {
     Ok((lhs, input))
 }
 
-fn parse_assignment(input: &[Token]) -> ParseResult<'_> {
-    let (lhs, input) = parse_function(input)?;
+fn parse_assignment<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    let (lhs, input) = parse_function(input, depth, int)?;
     if let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Equals) {
         if let Expr::Ident(s) = lhs {
-            let (rhs, remaining) = parse_assignment(remaining)?;
+            let (rhs, remaining) = parse_assignment(remaining, nested(depth, int)?, int)?;
             return Ok((Expr::Assign(s, Box::new(rhs)), remaining));
         }
         return Err(ParseError::ExpectedIdentifierInAssignment);
@@ -459,34 +924,93 @@ fn parse_assignment(input: &[Token]) -> ParseResult<'_> {
     Ok((lhs, input))
 }
 
-fn parse_statements(mut input: &[Token]) -> ParseResult<'_> {
+fn parse_statements<'a, I: Interrupt>(
+    mut input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
     while let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Semicolon) {
         input = remaining;
     }
     if input.is_empty() {
         return Ok((Expr::Literal(Value::Unit), &[]));
     }
-    let (mut result, mut input) = parse_assignment(input)?;
+    let (mut result, mut input) = parse_assignment(input, depth, int)?;
+    let mut height = depth;
     while let Ok((_, remaining)) = parse_fixed_symbol(input, Symbol::Semicolon) {
         if remaining.is_empty() || matches!(remaining[0], Token::Symbol(Symbol::Semicolon)) {
             input = remaining;
             continue;
         }
-        let (rhs, remaining) = parse_assignment(remaining)?;
+        let (rhs, remaining) = parse_assignment(remaining, depth, int)?;
         result = Expr::Statements(Box::new(result), Box::new(rhs));
         input = remaining;
+        height = chained(height, int)?;
     }
     Ok((result, input))
 }
 
-pub(crate) fn parse_expression(input: &[Token]) -> ParseResult<'_> {
-    parse_statements(input)
+pub(crate) fn parse_expression<'a, I: Interrupt>(
+    input: &'a [Token],
+    depth: usize,
+    int: &I,
+) -> ParseResult<'a> {
+    parse_statements(input, depth, int)
+}
+
+// The depth tracked while parsing is only an approximation (e.g. the first
+// operand in `(1 + 2) + 3` ends up nested more deeply than it was parsed),
+// so we also check the final expression. This check avoids recursion so
+// that it can't overflow the stack itself.
+fn exceeds_max_depth(expr: &Expr) -> bool {
+    let mut stack = vec![(expr, 1, 1)];
+    while let Some((expr, depth, height)) = stack.pop() {
+        if depth > MAX_DEPTH || height > MAX_HEIGHT {
+            return true;
+        }
+        match expr {
+            Expr::Literal(_) | Expr::Ident(_) => (),
+            Expr::Parens(x)
+            | Expr::UnaryMinus(x)
+            | Expr::UnaryPlus(x)
+            | Expr::UnaryDiv(x)
+            | Expr::Factorial(x)
+            | Expr::Fn(_, x)
+            | Expr::Of(_, x)
+            | Expr::Assign(_, x) => stack.push((x, depth + 1, height + 1)),
+            // the left operand of a left-associative chain isn't nested
+            Expr::Bop(Bop::Pow, a, b)
+            | Expr::Apply(a, b)
+            | Expr::ApplyFunctionCall(a, b)
+            | Expr::ApplyMul(a, b)
+            | Expr::As(a, b) => {
+                stack.push((a, depth + 1, height + 1));
+                stack.push((b, depth + 1, height + 1));
+            }
+            Expr::Bop(_, a, b) | Expr::Statements(a, b) => {
+                stack.push((a, depth, height + 1));
+                stack.push((b, depth + 1, height + 1));
+            }
+            Expr::If(a, b, c) => {
+                stack.push((a, depth + 1, height + 1));
+                stack.push((b, depth + 1, height + 1));
+                stack.push((c, depth + 1, height + 1));
+            }
+            Expr::List(items) => {
+                stack.extend(items.iter().map(|item| (item, depth + 1, height + 1)));
+            }
+        }
+    }
+    false
 }
 
-pub(crate) fn parse_tokens(input: &[Token]) -> Result<Expr, ParseError> {
-    let (res, remaining) = parse_expression(input)?;
+pub(crate) fn parse_tokens<I: Interrupt>(input: &[Token], int: &I) -> Result<Expr, ParseError> {
+    let (res, remaining) = parse_expression(input, 0, int)?;
     if !remaining.is_empty() {
-        return Err(ParseError::UnexpectedInput);
+        return Err(ParseError::UnexpectedInput.at(remaining));
+    }
+    if exceeds_max_depth(&res) {
+        return Err(ParseError::TooDeeplyNested);
     }
     Ok(res)
 }
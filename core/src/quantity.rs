@@ -0,0 +1,124 @@
+use crate::error::FendError;
+use crate::num::Number;
+use crate::{eval, interrupt, value, Attrs, Context};
+
+/// A number with an optional unit, like `5`, `2.5 km` or `9.81 m / s^2`.
+///
+/// Quantities can be passed to fend with [`Context::set_variable`], and
+/// results can be converted into quantities with [`Context::evaluate_as`],
+/// so numbers don't need to be formatted and parsed again.
+///
+/// ```
+/// let mut context = fend_core::Context::new();
+/// let distance = fend_core::Quantity::from(5)
+///     .with_unit("km", &context)
+///     .unwrap();
+/// context.set_variable("distance", distance);
+/// let miles = context
+///     .evaluate_as::<fend_core::Quantity>("distance to miles")
+///     .unwrap();
+/// assert_eq!(miles.format(&context).unwrap(), "approx. 3.1068559611 miles");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Quantity(pub(crate) Number);
+
+impl Quantity {
+    /// Multiplies this quantity by the given unit, e.g. `km` or `m/s^2`.
+    /// Units are looked up in the given context, so custom units can be
+    /// used too.
+    ///
+    /// # Errors
+    /// Returns an error if the unit is unknown or isn't a number.
+    pub fn with_unit(self, unit: &str, context: &Context) -> Result<Self, String> {
+        self.with_unit_internal(unit, context)
+            .map_err(|e| e.to_string())
+    }
+
+    fn with_unit_internal(self, unit: &str, context: &Context) -> Result<Self, FendError> {
+        let int = interrupt::Never::default();
+        let mut context = context.clone();
+        let unit = eval::evaluate_to_value(unit, None, Attrs::default(), &mut context, &int)?
+            .expect_num()?;
+        Ok(Self(self.0.mul(unit, &int)?))
+    }
+
+    /// Returns false if this quantity is only an approximation, e.g. for
+    /// the result of `sin 1`. Values like `pi` or `sqrt(2)` are represented
+    /// exactly, even though they can only be shown approximately.
+    #[must_use]
+    pub fn is_exact(&self) -> bool {
+        self.0.is_exact()
+    }
+
+    /// Converts this quantity into an `f64`. Dimensionless units are
+    /// converted away, so e.g. `50%` becomes `0.5`.
+    ///
+    /// # Errors
+    /// Returns an error if the quantity has a unit.
+    pub fn to_f64(&self) -> Result<f64, String> {
+        self.0
+            .clone()
+            .try_as_f64(&interrupt::Never::default())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Converts this quantity into an `f64` along with its unit, e.g.
+    /// `(2.5, "km")`. The unit is empty for unitless numbers.
+    ///
+    /// # Errors
+    /// Returns an error if the value can't be represented as an `f64`.
+    pub fn to_f64_with_unit(&self, context: &Context) -> Result<(f64, String), String> {
+        self.0
+            .clone()
+            .try_as_f64_with_unit(context, &interrupt::Never::default())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Formats this quantity the same way as [`crate::evaluate`] would,
+    /// using the settings of the given context
+    ///
+    /// # Errors
+    /// Returns an error if the quantity can't be formatted, e.g. because it
+    /// has too many digits.
+    pub fn format(&self, context: &Context) -> Result<String, String> {
+        value::Value::Num(Box::new(self.0.clone()))
+            .format_to_plain_string(0, Attrs::default(), context, &interrupt::Never::default())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl From<i64> for Quantity {
+    fn from(i: i64) -> Self {
+        let magnitude = Number::from(i.unsigned_abs());
+        Self(if i < 0 { -magnitude } else { magnitude })
+    }
+}
+
+impl TryFrom<f64> for Quantity {
+    type Error = String;
+
+    /// Converts an `f64` into an exact quantity, e.g. `2.5` becomes `2.5`
+    /// rather than an approximation with a large denominator
+    fn try_from(f: f64) -> Result<Self, String> {
+        if f.is_nan() {
+            return Err(FendError::UndefinedResult.to_string());
+        }
+        if f.is_infinite() {
+            return Err(FendError::InfinityNotSupported.to_string());
+        }
+        // formatting an `f64` gives the shortest decimal that round-trips,
+        // which is evaluated with a default context so that the locale
+        // doesn't matter
+        let int = interrupt::Never::default();
+        eval::evaluate_to_value(
+            &f.to_string(),
+            None,
+            Attrs::default(),
+            &mut Context::new(),
+            &int,
+        )
+        .and_then(value::Value::expect_num)
+        .map(Self)
+        .map_err(|e| e.to_string())
+    }
+}
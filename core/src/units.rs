@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
 use crate::error::{FendError, Interrupt};
 use crate::eval::evaluate_to_value;
@@ -8,7 +9,7 @@ use crate::Attrs;
 
 mod builtin;
 
-pub(crate) use builtin::IMPLICIT_UNIT_MAP;
+pub(crate) use builtin::{base_unit_symbol, IMPLICIT_UNIT_MAP, PREFERRED_UNITS};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub(crate) enum PrefixRule {
@@ -21,21 +22,26 @@ pub(crate) enum PrefixRule {
 
 #[derive(Debug)]
 pub(crate) struct UnitDef {
-    singular: &'static str,
-    plural: &'static str,
+    singular: Cow<'static, str>,
+    plural: Cow<'static, str>,
     prefix_rule: PrefixRule,
     value: Value,
 }
 
 fn expr_unit<I: Interrupt>(
-    singular: &'static str,
-    plural: &'static str,
-    definition: &'static str,
+    singular: Cow<'static, str>,
+    plural: Cow<'static, str>,
+    definition: &str,
     attrs: Attrs,
     context: &mut crate::Context,
     int: &I,
 ) -> Result<UnitDef, FendError> {
     let mut definition = definition.trim();
+    let mut temperature_difference = false;
+    if let Some(remaining) = definition.strip_prefix("d@") {
+        definition = remaining;
+        temperature_difference = true;
+    }
     let mut rule = PrefixRule::NoPrefixesAllowed;
     if let Some(remaining) = definition.strip_prefix("l@") {
         definition = remaining;
@@ -56,8 +62,8 @@ fn expr_unit<I: Interrupt>(
     if definition == "!" {
         return Ok(UnitDef {
             value: Value::Num(Box::new(Number::new_base_unit(
-                Cow::Borrowed(singular),
-                Cow::Borrowed(plural),
+                singular.clone(),
+                plural.clone(),
             ))),
             prefix_rule: rule,
             singular,
@@ -72,11 +78,14 @@ fn expr_unit<I: Interrupt>(
         num = Number::create_unit_value_from_value(
             &num,
             Cow::Borrowed(""),
-            Cow::Borrowed(singular),
-            Cow::Borrowed(plural),
+            singular.clone(),
+            plural.clone(),
             int,
         )?;
     }
+    if temperature_difference {
+        num = num.into_temperature_difference();
+    }
     Ok(UnitDef {
         value: Value::Num(Box::new(num)),
         prefix_rule: rule,
@@ -92,13 +101,8 @@ fn construct_prefixed_unit<I: Interrupt>(
 ) -> Result<Value, FendError> {
     let product = a.value.expect_num()?.mul(b.value.expect_num()?, int)?;
     assert_eq!(a.singular, a.plural);
-    let unit = Number::create_unit_value_from_value(
-        &product,
-        Cow::Borrowed(a.singular),
-        Cow::Borrowed(b.singular),
-        Cow::Borrowed(b.plural),
-        int,
-    )?;
+    let unit =
+        Number::create_unit_value_from_value(&product, a.singular, b.singular, b.plural, int)?;
     Ok(Value::Num(Box::new(unit)))
 }
 
@@ -183,6 +187,22 @@ fn query_unit_case_sensitive<I: Interrupt>(
                     // now construct a new unit!
                     return construct_prefixed_unit(a, b, int);
                 }
+                // the first definition with this name might be a plain number
+                // that doesn't accept prefixes (e.g. `T` for trillion), so look
+                // for a unit that does (e.g. `T` for tesla)
+                let short = a.prefix_rule == PrefixRule::ShortPrefix;
+                let is_number = match &b.value {
+                    Value::Num(n) => n.is_unitless(int)?,
+                    _ => false,
+                };
+                if is_number && (a.prefix_rule == PrefixRule::LongPrefix || short) {
+                    if let Some((s, p, expr)) =
+                        builtin::query_prefixable_unit(remaining_ident, short, case_sensitive)
+                    {
+                        let b = expr_unit(s.into(), p.into(), expr, attrs, context, int)?;
+                        return construct_prefixed_unit(a, b, int);
+                    }
+                }
                 return Err(FendError::IdentifierNotFound(ident.to_string().into()));
             }
         };
@@ -201,13 +221,53 @@ fn query_unit_internal<'a, I: Interrupt>(
 ) -> Result<UnitDef, FendError> {
     if whole_unit && context.fc_mode == crate::FCMode::CelsiusFahrenheit {
         if ident == "C" {
-            return expr_unit("C", "C", "=\u{b0}C", attrs, context, int);
+            return expr_unit("C".into(), "C".into(), "=\u{b0}C", attrs, context, int);
         } else if ident == "F" {
-            return expr_unit("F", "F", "=\u{b0}F", attrs, context, int);
+            return expr_unit("F".into(), "F".into(), "=\u{b0}F", attrs, context, int);
+        }
+    }
+    if let Some((name, definition)) = query_custom_prefix(ident, case_sensitive, context) {
+        let (name, definition) = (name.clone(), format!("lp@{definition}"));
+        return expr_unit(
+            name.clone().into(),
+            name.into(),
+            &definition,
+            attrs,
+            context,
+            int,
+        );
+    }
+    if !short_prefixes {
+        if let Some(unit) = query_custom_unit(ident, case_sensitive, context) {
+            let (singular, plural, definition) = unit.clone();
+            if context.resolving_units.contains(&singular) {
+                return Err(FendError::RecursiveUnitDefinition(singular));
+            }
+            context.resolving_units.push(singular.clone());
+            let unit_def = expr_unit(
+                singular.into(),
+                plural.into(),
+                definition.as_str(),
+                attrs,
+                context,
+                int,
+            );
+            context.resolving_units.pop();
+            let mut unit_def = unit_def?;
+            unit_def.prefix_rule = PrefixRule::LongPrefixAllowed;
+            return Ok(unit_def);
         }
     }
-    if let Some((s, p, expr)) = builtin::query_unit(ident, short_prefixes, case_sensitive) {
+    let imperial_override = if context.unit_system == crate::UnitSystem::Imperial {
+        builtin::query_imperial_override(ident, case_sensitive)
+    } else {
+        None
+    };
+    if let Some((s, p, expr)) =
+        imperial_override.or_else(|| builtin::query_unit(ident, short_prefixes, case_sensitive))
+    {
         if expr == "$CURRENCY" {
+            context.result_is_cacheable = false;
             let exchange_rate_fn = match context.get_exchange_rate {
                 Some(f) => f,
                 None => return Err(FendError::NoExchangeRatesAvailable),
@@ -229,19 +289,142 @@ fn query_unit_internal<'a, I: Interrupt>(
                 int,
             )?;
             Ok(UnitDef {
-                singular: s,
-                plural: p,
+                singular: s.into(),
+                plural: p.into(),
                 prefix_rule: PrefixRule::LongPrefixAllowed,
                 value: Value::Num(Box::new(value)),
             })
         } else {
-            expr_unit(s, p, expr, attrs, context, int)
+            expr_unit(s.into(), p.into(), expr, attrs, context, int)
         }
     } else {
         Err(FendError::IdentifierNotFound(ident.to_string().into()))
     }
 }
 
+fn query_custom_unit<'a>(
+    ident: &str,
+    case_sensitive: bool,
+    context: &'a crate::Context,
+) -> Option<&'a (String, String, String)> {
+    let matches = |name: &str| {
+        if case_sensitive {
+            name == ident
+        } else {
+            name.eq_ignore_ascii_case(ident)
+        }
+    };
+    context
+        .custom_units
+        .iter()
+        .find(|(singular, plural, _)| matches(singular) || matches(plural))
+}
+
+fn query_custom_prefix<'a>(
+    ident: &str,
+    case_sensitive: bool,
+    context: &'a crate::Context,
+) -> Option<&'a (String, String)> {
+    context.custom_prefixes.iter().find(|(name, _)| {
+        if case_sensitive {
+            name == ident
+        } else {
+            name.eq_ignore_ascii_case(ident)
+        }
+    })
+}
+
+/// Checks whether the given name is already used by a custom or built-in
+/// unit or prefix
+pub(crate) fn is_defined_unit_or_prefix(ident: &str, context: &crate::Context) -> bool {
+    query_custom_prefix(ident, true, context).is_some()
+        || query_custom_unit(ident, true, context).is_some()
+        || builtin::query_unit(ident, true, true).is_some()
+}
+
+/// Returns the canonical name of a custom or built-in unit, e.g. `meter`
+/// for `metre`, `meters` or `m`
+pub(crate) fn canonical_unit_name(ident: &str, context: &crate::Context) -> Option<String> {
+    if let Some((singular, _, _)) = query_custom_unit(ident, true, context) {
+        return Some(singular.clone());
+    }
+    let ident = match ident {
+        "C" if context.fc_mode == crate::FCMode::CelsiusFahrenheit => "\u{b0}C",
+        "F" if context.fc_mode == crate::FCMode::CelsiusFahrenheit => "\u{b0}F",
+        ident => ident,
+    };
+    builtin::canonical_name(ident).map(ToString::to_string)
+}
+
+/// Returns the dimensions of the given unit along with their exponents, e.g.
+/// `{length: 1, mass: 1, time: -2}` for `N`
+pub(crate) fn unit_dimension<I: Interrupt>(
+    ident: &str,
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<BTreeMap<String, i64>, crate::UnitDimensionError> {
+    let n = match query_unit_static(ident, Attrs::default(), context, int) {
+        Ok(Value::Num(n)) => n,
+        Ok(_) | Err(FendError::IdentifierNotFound(_)) => {
+            return Err(crate::UnitDimensionError::UnknownUnit)
+        }
+        Err(e) => return Err(crate::UnitDimensionError::Other(e.to_string())),
+    };
+    let base_unit_exponents = n
+        .base_unit_exponents(int)
+        .map_err(|e| crate::UnitDimensionError::Other(e.to_string()))?;
+    let mut dimensions = BTreeMap::<String, i64>::new();
+    for (base_unit, exponent) in base_unit_exponents {
+        let dimension = match base_unit.as_str() {
+            "second" => "time",
+            "meter" => "length",
+            "kilogram" => "mass",
+            "kelvin" | "celsius" | "fahrenheit" => "temperature",
+            "ampere" => "current",
+            "mole" => "amount",
+            "candela" => "luminous intensity",
+            "bit" => "information",
+            "BASE_CURRENCY" => "currency",
+            name => name,
+        };
+        *dimensions.entry(dimension.to_string()).or_default() += exponent;
+    }
+    dimensions.retain(|_, exponent| *exponent != 0);
+    Ok(dimensions)
+}
+
+/// Returns all built-in and custom prefixes along with their values, sorted
+/// from smallest to largest
+pub(crate) fn list_prefixes<I: Interrupt>(
+    context: &mut crate::Context,
+    int: &I,
+) -> Result<Vec<(String, f64)>, FendError> {
+    let custom_prefixes = context.custom_prefixes.clone();
+    let definitions = builtin::prefix_definitions()
+        .map(|(name, def)| (name.to_string(), def.to_string()))
+        .chain(custom_prefixes);
+    let mut prefixes = vec![];
+    for (name, definition) in definitions {
+        let value = evaluate_to_value(&definition, None, Attrs::default(), context, int)
+            .and_then(Value::expect_num)
+            .and_then(|n| n.try_as_f64(int))
+            .map_err(|error| FendError::InvalidPrefix {
+                name: name.clone(),
+                error: Box::new(error),
+            })?;
+        prefixes.push((name, value));
+    }
+    prefixes.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    Ok(prefixes)
+}
+
+/// Returns the singular names of all built-in units
+pub(crate) fn builtin_unit_names() -> impl Iterator<Item = &'static str> {
+    builtin::ALL_UNIT_DEFS
+        .iter()
+        .flat_map(|group| group.iter().map(|(s, _, _, _)| *s))
+}
+
 pub(crate) fn get_completions_for_prefix(prefix: &str) -> Vec<crate::Completion> {
     use crate::Completion;
 
@@ -256,12 +439,10 @@ pub(crate) fn get_completions_for_prefix(prefix: &str) -> Vec<crate::Completion>
         }
     };
 
-    for group in builtin::ALL_UNIT_DEFS {
-        for (s, _, _, _) in *group {
-            // only add singular name, since plurals
-            // unnecessarily clutter autocompletions
-            add(s);
-        }
+    // only add singular names, since plurals
+    // unnecessarily clutter autocompletions
+    for s in builtin_unit_names() {
+        add(s);
     }
 
     result.sort_by(|a, b| a.display().cmp(b.display()));
@@ -40,11 +40,19 @@ const TEMPERATURE_SCALES: &[UnitTuple] = &[
     ("celsius", "", "l@!", ""),
     ("\u{b0}C", "", "celsius", ""), // degree symbol
     ("oC", "", "=\u{b0}C", ""),
+    ("degC", "", "=\u{b0}C", ""),
     ("rankine", "", "l@5/9 K", ""),
     ("\u{b0}R", "", "rankine", ""),
     ("fahrenheit", "", "l@!", ""),
     ("\u{b0}F", "", "fahrenheit", ""),
     ("oF", "", "=\u{b0}F", ""),
+    ("degF", "", "=\u{b0}F", ""),
+    // temperature differences (marked with `d@`) convert without an offset
+    ("\u{394}\u{b0}C", "", "d@K", ""),
+    ("\u{394}C", "", "=\u{394}\u{b0}C", ""),
+    ("\u{394}\u{b0}F", "", "d@\u{b0}R", ""),
+    ("\u{394}F", "", "=\u{394}\u{b0}F", ""),
+    ("\u{394}K", "", "=K", ""),
 ];
 
 const BITS_AND_BYTES: &[UnitTuple] = &[
@@ -236,6 +244,7 @@ const CONSTANTS: &[UnitTuple] = &[
         "gravitational constant",
     ),
     ("gravity", "", "=9.80665 m/s^2", ""),
+    ("standard_gravity", "", "=gravity", ""),
     ("force", "", "gravity", ""), // used to convert some units
 ];
 
@@ -470,6 +479,7 @@ const COMMON_PHYSICAL_UNITS: &[UnitTuple] = &[
     ("cc", "", "cm^3", ""),
     ("are", "ares", "l@100 meter^2", ""),
     ("liter", "liters", "l@1000 cc", ""),
+    ("litre", "litres", "l@liter", ""),
     ("l", "", "s@liter", ""),
     ("L", "", "s@liter", ""),
     ("micron", "microns", "l@micrometer", ""),
@@ -494,7 +504,11 @@ const COMMON_PHYSICAL_UNITS: &[UnitTuple] = &[
     ("mmHg", "", "l@1/760 atm", "millimeter of mercury"),
     ("inHg", "", "l@25.4 mmHg", "inch of mercury"),
     ("bar", "", "l@1e5 Pa", "about 1 atmosphere"),
+    ("mbar", "", "millibar", ""),
+    ("torr", "", "l@1/760 atm", ""),
     ("diopter", "", "l@/m", "reciprocal of focal length"),
+    ("molar", "", "l@mol/L", "concentration in moles per liter"),
+    ("molal", "", "l@mol/kg", "moles per kilogram of solvent"),
     ("sqm", "", "=m^2", ""),
     ("sqmm", "", "=mm^2", ""),
     // TODO remove these compatibility units
@@ -596,6 +610,8 @@ const IMPERIAL_ABBREVIATIONS: &[UnitTuple] = &[
     ("plf", "", "lb / foot", "pounds per linear foot"),
     ("lbf", "", "lb force", ""),
     ("psi", "", "pound force / inch^2", ""),
+    ("ksi", "", "1000 psi", "kilopound per square inch"),
+    ("kgf", "", "kg force", "kilogram-force"),
 ];
 
 const NAUTICAL_UNITS: &[UnitTuple] = &[
@@ -700,6 +716,19 @@ const SHORT_PREFIXES: &[(&str, &str)] = &[
     ("y", "sp@yocto"),
 ];
 
+/// Returns the names of all long and short prefixes, along with
+/// expressions for their values (e.g. `("kilo", "1e3")` or `("k", "kilo")`)
+pub(crate) fn prefix_definitions() -> impl Iterator<Item = (&'static str, &'static str)> {
+    let long_prefixes = [STANDARD_PREFIXES, NON_STANDARD_PREFIXES, BINARY_PREFIXES]
+        .into_iter()
+        .flat_map(|group| group.iter())
+        .filter_map(|(name, _, def, _)| Some((*name, def.strip_prefix("lp@")?)));
+    let short_prefixes = SHORT_PREFIXES
+        .iter()
+        .filter_map(|(name, def)| Some((*name, def.strip_prefix("sp@")?)));
+    long_prefixes.chain(short_prefixes)
+}
+
 #[allow(clippy::too_many_lines)]
 pub(crate) fn query_unit<'a>(
     ident: &'a str,
@@ -724,8 +753,84 @@ pub(crate) fn query_unit<'a>(
         let name = CURRENCY_IDENTIFIERS[idx];
         return Some((name, name, "$CURRENCY"));
     }
-    let mut candidates = vec![];
+    find_unit(ALL_UNIT_DEFS, ident, case_sensitive)
+}
+
+/// Finds the symbol of a built-in base unit, e.g. `s` for `second`
+pub(crate) fn base_unit_symbol(base_unit: &str) -> Option<&'static str> {
+    ALL_UNIT_DEFS
+        .iter()
+        .flat_map(|group| group.iter())
+        .find(|(_, _, definition, _)| definition.strip_prefix("s@") == Some(base_unit))
+        .map(|(symbol, ..)| *symbol)
+}
+
+/// Follows aliases (i.e. definitions that only consist of another unit name,
+/// like `metre` or `ft`) to find the canonical name of a built-in unit
+pub(crate) fn canonical_name(ident: &str) -> Option<&'static str> {
+    let (mut name, _, mut definition) = find_unit(ALL_UNIT_DEFS, ident, true)?;
+    // the depth is limited in case of cycles
+    for _ in 0..10 {
+        let target = definition.trim();
+        let target = target
+            .strip_prefix("l@")
+            .or_else(|| target.strip_prefix("s@"))
+            .unwrap_or(target);
+        let target = target.strip_prefix('=').unwrap_or(target);
+        if target.is_empty() || !target.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            break;
+        }
+        match find_unit(ALL_UNIT_DEFS, target, true) {
+            Some((s, _, d)) => (name, definition) = (s, d),
+            None => break,
+        }
+    }
+    Some(name)
+}
+
+/// Finds a unit that accepts short (or long) prefixes, skipping any
+/// earlier definitions with the same name that don't
+pub(crate) fn query_prefixable_unit(
+    ident: &str,
+    short_prefixes: bool,
+    case_sensitive: bool,
+) -> Option<(&'static str, &'static str, &'static str)> {
+    let rule = if short_prefixes { "s@" } else { "l@" };
     for group in ALL_UNIT_DEFS {
+        let group = group
+            .iter()
+            .filter(|def| def.2.trim().starts_with(rule))
+            .copied()
+            .collect::<Vec<_>>();
+        if let Some(unit) = find_unit(&[&group], ident, case_sensitive) {
+            return Some(unit);
+        }
+    }
+    None
+}
+
+/// Units whose British imperial definitions differ from the US
+/// customary ones used by default
+const IMPERIAL_OVERRIDES: &[UnitTuple] = &[
+    ("gallon", "gallons", "4.54609 L", ""),
+    ("fluid_ounce", "", "1/20 pint", ""),
+    ("hundredweight", "hundredweights", "112 pounds", ""),
+];
+
+pub(crate) fn query_imperial_override(
+    ident: &str,
+    case_sensitive: bool,
+) -> Option<(&'static str, &'static str, &'static str)> {
+    find_unit(&[IMPERIAL_OVERRIDES], ident, case_sensitive)
+}
+
+fn find_unit(
+    groups: &[&[UnitTuple]],
+    ident: &str,
+    case_sensitive: bool,
+) -> Option<(&'static str, &'static str, &'static str)> {
+    let mut candidates = vec![];
+    for group in groups {
         for def in *group {
             let def = UnitDef {
                 singular: def.0,
@@ -752,6 +857,14 @@ pub(crate) fn query_unit<'a>(
 /// used for implicit unit addition, e.g. 5'5 -> 5'5"
 pub(crate) const IMPLICIT_UNIT_MAP: &[(&str, &str)] = &[("'", "\""), ("foot", "inches")];
 
+/// Named units that compound units (like `N m`) are shown as, if they have
+/// the same base units and scale. If several units match, the first one
+/// in this list is used. `C` and `F` can refer to temperatures, so
+/// coulombs and farads use their long names instead.
+pub(crate) const PREFERRED_UNITS: &[&str] = &[
+    "N", "J", "W", "Pa", "V", "ohm", "S", "coulomb", "farad", "Wb", "T", "H",
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,7 +1,8 @@
 use crate::ast::Bop;
 use crate::date::{Date, DayOfWeek, Month};
 use crate::error::{FendError, Interrupt};
-use crate::num::{Base, FormattingStyle, Number};
+use crate::interrupt::test_int;
+use crate::num::{Base, FormattingStyle, Number, Rounding};
 use crate::scope::Scope;
 use crate::serialize::{
     deserialize_bool, deserialize_string, deserialize_u8, deserialize_usize, serialize_bool,
@@ -10,6 +11,7 @@ use crate::serialize::{
 use crate::{ast::Expr, ident::Ident};
 use crate::{date, Attrs, Span, SpanKind};
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::io;
 use std::{
     fmt::{self, Write},
@@ -20,10 +22,15 @@ pub(crate) mod built_in_function;
 
 use built_in_function::BuiltInFunction;
 
+/// The maximum number of items in a list created by e.g. `range`
+const MAX_LIST_LENGTH: usize = 10_000;
+
 #[derive(Clone)]
 pub(crate) enum Value {
     Num(Box<Number>),
     BuiltInFunction(BuiltInFunction),
+    // function registered via `Context::register_function`
+    RegisteredFunction(String),
     Format(FormattingStyle),
     Dp,
     Sf,
@@ -37,6 +44,7 @@ pub(crate) enum Value {
     Month(date::Month),
     DayOfWeek(date::DayOfWeek),
     Date(date::Date),
+    List(Vec<Self>),
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -107,6 +115,17 @@ impl Value {
                 serialize_u8(13, write)?;
                 d.serialize(write)?;
             }
+            Self::RegisteredFunction(name) => {
+                serialize_u8(14, write)?;
+                serialize_string(name, write)?;
+            }
+            Self::List(items) => {
+                serialize_u8(15, write)?;
+                serialize_usize(items.len(), write)?;
+                for item in items {
+                    item.serialize(write)?;
+                }
+            }
         }
         Ok(())
     }
@@ -145,6 +164,15 @@ impl Value {
             11 => Self::Month(Month::deserialize(read)?),
             12 => Self::DayOfWeek(DayOfWeek::deserialize(read)?),
             13 => Self::Date(Date::deserialize(read)?),
+            14 => Self::RegisteredFunction(deserialize_string(read)?),
+            15 => {
+                let len = deserialize_usize(read)?;
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(Self::deserialize(read)?);
+                }
+                Self::List(items)
+            }
             _ => return Err(FendError::DeserializationError),
         })
     }
@@ -152,7 +180,9 @@ impl Value {
     pub(crate) fn type_name(&self) -> &'static str {
         match self {
             Self::Num(_) => "number",
-            Self::BuiltInFunction(_) | Self::Fn(_, _, _) => "function",
+            Self::BuiltInFunction(_) | Self::RegisteredFunction(_) | Self::Fn(_, _, _) => {
+                "function"
+            }
             Self::Format(_) => "formatting style",
             Self::Dp => "decimal places",
             Self::Sf => "significant figures",
@@ -164,10 +194,11 @@ impl Value {
             Self::Month(_) => "month",
             Self::DayOfWeek(_) => "day of week",
             Self::Date(_) => "date",
+            Self::List(_) => "list",
         }
     }
 
-    fn as_bool(&self) -> Result<bool, FendError> {
+    pub(crate) fn as_bool(&self) -> Result<bool, FendError> {
         if let Self::Bool(b) = self {
             Ok(*b)
         } else {
@@ -175,17 +206,53 @@ impl Value {
         }
     }
 
+    /// With `BooleanStyle::Numbers`, `true` and `false` can be used as 1
+    /// and 0 in arithmetic, so they're converted before being used as
+    /// numbers
+    pub(crate) fn bool_as_num(self, context: &crate::Context) -> Self {
+        match self {
+            Self::Bool(b) if context.boolean_style == crate::BooleanStyle::Numbers => {
+                Self::Num(Box::new(Number::from(u64::from(b))))
+            }
+            Self::List(items) if context.boolean_style == crate::BooleanStyle::Numbers => {
+                Self::List(items.into_iter().map(|v| v.bool_as_num(context)).collect())
+            }
+            value => value,
+        }
+    }
+
     pub(crate) fn expect_num(self) -> Result<Number, FendError> {
         match self {
             Self::Num(bigrat) => Ok(*bigrat),
+            Self::Bool(_) => Err(FendError::BoolUsedAsNumber),
             _ => Err(FendError::ExpectedANumber),
         }
     }
 
+    /// Splits a list of function arguments, e.g. `(1, 2)`
+    fn expect_args<const N: usize>(self, function: &'static str) -> Result<[Self; N], FendError> {
+        let args = match self {
+            Self::List(items) => items,
+            value => vec![value],
+        };
+        args.try_into()
+            .map_err(|_| FendError::WrongNumberOfArguments {
+                function,
+                expected: N,
+            })
+    }
+
     pub(crate) fn is_unit(&self) -> bool {
         matches!(self, Self::Unit)
     }
 
+    pub(crate) fn is_fn(&self) -> bool {
+        matches!(
+            self,
+            Self::BuiltInFunction(_) | Self::RegisteredFunction(_) | Self::Fn(_, _, _)
+        )
+    }
+
     pub(crate) fn handle_num(
         self,
         eval_fn: impl FnOnce(Number) -> Result<Number, FendError>,
@@ -196,6 +263,8 @@ impl Value {
             Self::Num(n) => Self::Num(Box::new(eval_fn(*n)?)),
             Self::Fn(param, expr, scope) => Self::Fn(param, Box::new(lazy_fn(expr)), scope),
             Self::BuiltInFunction(f) => f.wrap_with_expr(lazy_fn, scope),
+            Self::RegisteredFunction(name) => wrap_function_call(&name, lazy_fn, scope),
+            Self::Bool(_) => return Err(FendError::BoolUsedAsNumber),
             _ => return Err(FendError::ExpectedANumber),
         })
     }
@@ -212,12 +281,19 @@ impl Value {
             (Self::Num(a), Self::Num(b)) => Self::Num(Box::new(eval_fn(*a, *b)?)),
             (Self::BuiltInFunction(f), Self::Num(a)) => f.wrap_with_expr(lazy_fn_lhs(*a), scope),
             (Self::Num(a), Self::BuiltInFunction(f)) => f.wrap_with_expr(lazy_fn_rhs(*a), scope),
+            (Self::RegisteredFunction(name), Self::Num(a)) => {
+                wrap_function_call(&name, lazy_fn_lhs(*a), scope)
+            }
+            (Self::Num(a), Self::RegisteredFunction(name)) => {
+                wrap_function_call(&name, lazy_fn_rhs(*a), scope)
+            }
             (Self::Fn(param, expr, scope), Self::Num(a)) => {
                 Self::Fn(param, Box::new(lazy_fn_lhs(*a)(expr)), scope)
             }
             (Self::Num(a), Self::Fn(param, expr, scope)) => {
                 Self::Fn(param, Box::new(lazy_fn_rhs(*a)(expr)), scope)
             }
+            (Self::Bool(_), _) | (_, Self::Bool(_)) => return Err(FendError::BoolUsedAsNumber),
             _ => return Err(FendError::ExpectedANumber),
         })
     }
@@ -231,10 +307,10 @@ impl Value {
         context: &mut crate::Context,
         int: &I,
     ) -> Result<Self, FendError> {
-        let stringified_self = self.format_to_plain_string(0, attrs, context, int)?;
         Ok(match self {
             Self::Num(n) => {
-                let other = crate::ast::evaluate(other, scope.clone(), attrs, context, int)?;
+                let other = crate::ast::evaluate(other, scope.clone(), attrs, context, int)?
+                    .bool_as_num(context);
                 if let Self::Dp = other {
                     let num = Self::Num(n).expect_num()?.try_as_usize(int)?;
                     return Ok(Self::Format(FormattingStyle::DecimalPlaces(num)));
@@ -262,11 +338,25 @@ impl Value {
             Self::BuiltInFunction(func) => {
                 Self::apply_built_in_function(func, other, scope, attrs, context, int)?
             }
+            Self::RegisteredFunction(name) => {
+                Self::apply_registered_function(name, other, scope, attrs, context, int)?
+            }
+            // `exact(1/3)` is an alternative to `1/3 to exact`
+            Self::Format(FormattingStyle::Exact) => {
+                let arg = crate::ast::evaluate(other, scope, attrs, context, int)?;
+                Self::Num(Box::new(
+                    arg.bool_as_num(context).expect_num()?.make_exact()?,
+                ))
+            }
             Self::Fn(param, expr, custom_scope) => {
                 let new_scope = Scope::with_variable(param, other, scope, custom_scope);
                 return crate::ast::evaluate(*expr, Some(Arc::new(new_scope)), attrs, context, int);
             }
-            _ => return Err(FendError::IsNotAFunctionOrNumber(stringified_self)),
+            _ => {
+                return Err(FendError::IsNotAFunctionOrNumber(
+                    self.format_to_plain_string(0, attrs, context, int)?,
+                ))
+            }
         })
     }
 
@@ -279,9 +369,28 @@ impl Value {
         int: &I,
     ) -> Result<Self, FendError> {
         let arg = crate::ast::evaluate(arg, scope.clone(), attrs, context, int)?;
+        // `not` is the only built-in function that takes a bool
+        let arg = if func == BuiltInFunction::Not {
+            arg
+        } else {
+            arg.bool_as_num(context)
+        };
         Ok(Self::Num(Box::new(match func {
+            BuiltInFunction::Range => {
+                let [start, end] = arg.expect_args(func.as_str())?;
+                return Self::range(start.expect_num()?, end.expect_num()?, int);
+            }
+            BuiltInFunction::Map | BuiltInFunction::Filter => {
+                return Self::map_or_filter(arg, func, scope.as_ref(), attrs, context, int);
+            }
+            BuiltInFunction::Mean => return Self::mean(arg, int),
+            BuiltInFunction::GeometricMean | BuiltInFunction::HarmonicMean => {
+                return Self::positive_mean(arg, func, int);
+            }
+            BuiltInFunction::Sort => return Self::sort(arg, int),
             BuiltInFunction::Approximately => arg.expect_num()?.make_approximate(),
             BuiltInFunction::Abs => arg.expect_num()?.abs(int)?,
+            BuiltInFunction::Reciprocal => arg.expect_num()?.reciprocal(attrs, context, int)?,
             BuiltInFunction::Sin => arg.expect_num()?.sin(scope, attrs, context, int)?,
             BuiltInFunction::Cos => arg.expect_num()?.cos(scope, attrs, context, int)?,
             BuiltInFunction::Tan => arg.expect_num()?.tan(scope, attrs, context, int)?,
@@ -305,12 +414,229 @@ impl Value {
                     .map_err(|_| FendError::UnableToConvertToBase)?;
                 return Ok(Self::Base(Base::from_plain_base(n)?));
             }
-            BuiltInFunction::Sample => arg.expect_num()?.sample(context, int)?,
+            BuiltInFunction::Sample => {
+                context.result_is_cacheable = false;
+                arg.expect_num()?.sample(context, int)?
+            }
             BuiltInFunction::Not => return Ok(Self::Bool(!arg.as_bool()?)),
             BuiltInFunction::Conjugate => arg.expect_num()?.conjugate()?,
+            BuiltInFunction::IsCompatible => {
+                let [a, b] = arg.expect_args(func.as_str())?;
+                return Ok(Self::Bool(
+                    a.expect_num()?.is_compatible_with(&b.expect_num()?, int)?,
+                ));
+            }
+            BuiltInFunction::Approx => match arg {
+                Self::List(_) => {
+                    let [value, max_den] = arg.expect_args(func.as_str())?;
+                    value
+                        .expect_num()?
+                        .best_rational_approximation(max_den.expect_num()?, int)?
+                }
+                arg => arg.expect_num()?.make_approximate_decimal(),
+            },
+            BuiltInFunction::Mod => {
+                let [a, b] = arg.expect_args(func.as_str())?;
+                a.expect_num()?.modulo(b.expect_num()?, int)?
+            }
+            BuiltInFunction::Round | BuiltInFunction::Floor | BuiltInFunction::Ceil => {
+                let rounding = match func {
+                    BuiltInFunction::Floor => Rounding::Floor,
+                    BuiltInFunction::Ceil => Rounding::Ceil,
+                    _ => Rounding::Round(context.rounding_mode),
+                };
+                if let Self::List(_) = arg {
+                    let [x, increment] = arg.expect_args(func.as_str())?;
+                    x.expect_num()?
+                        .round_to_increment(increment.expect_num()?, rounding, int)?
+                } else {
+                    arg.expect_num()?.round(rounding, int)?
+                }
+            }
         })))
     }
 
+    /// Applies `f` to each item of a list, either collecting the results
+    /// (`map`) or keeping the items for which `f` returns true (`filter`)
+    fn map_or_filter<I: Interrupt>(
+        arg: Self,
+        func: BuiltInFunction,
+        scope: Option<&Arc<Scope>>,
+        attrs: Attrs,
+        context: &mut crate::Context,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let [f, list] = arg.expect_args(func.as_str())?;
+        let Self::List(items) = list else {
+            return Err(FendError::ExpectedAList);
+        };
+        let mut result = vec![];
+        for item in items {
+            let arg = Expr::Literal(item.clone());
+            let value = f.clone().apply(
+                arg,
+                ApplyMulHandling::OnlyApply,
+                scope.cloned(),
+                attrs,
+                context,
+                int,
+            )?;
+            if func == BuiltInFunction::Map {
+                result.push(value);
+            } else if value.as_bool()? {
+                result.push(item);
+            }
+        }
+        Ok(Self::List(result))
+    }
+
+    /// Returns the average of a list of numbers (or of a single number),
+    /// converted to the unit of the first item
+    fn mean<I: Interrupt>(arg: Self, int: &I) -> Result<Self, FendError> {
+        let items = match arg {
+            Self::List(items) => items,
+            // `mean()`
+            Self::Unit => vec![],
+            value => vec![value],
+        };
+        let count = items.len() as u64;
+        let mut items = items.into_iter();
+        let Some(first) = items.next() else {
+            return Err(FendError::EmptyMean(BuiltInFunction::Mean.as_str()));
+        };
+        let mut sum = first.expect_num()?;
+        for item in items {
+            sum = sum.add(item.expect_num()?, int)?;
+        }
+        Ok(Self::Num(Box::new(sum.div(count.into(), int)?)))
+    }
+
+    /// Returns the geometric or harmonic mean of a list of positive numbers
+    /// (or of a single number), converted to the unit of the first item
+    fn positive_mean<I: Interrupt>(
+        arg: Self,
+        func: BuiltInFunction,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let items = match arg {
+            Self::List(items) => items,
+            Self::Unit => vec![],
+            value => vec![value],
+        };
+        let count = items.len() as u64;
+        let mut items = items.into_iter();
+        let Some(first) = items.next() else {
+            return Err(FendError::EmptyMean(func.as_str()));
+        };
+        let first = first.expect_num()?;
+        let unit = first.unit_of();
+        let mut values = vec![first];
+        for item in items {
+            values.push(item.expect_num()?.convert_to(unit.clone(), int)?);
+        }
+        for value in &values {
+            if value.is_zero() || !value.clone().abs(int)?.equals(value, int)? {
+                return Err(FendError::ExpectedPositiveNumbers(func.as_str()));
+            }
+        }
+        let mut values = values.into_iter();
+        let result = if func == BuiltInFunction::GeometricMean {
+            // the n-th root of the product
+            let mut product = values.next().unwrap();
+            for value in values {
+                product = product.mul(value, int)?;
+            }
+            product.pow(Number::from(1).div(count.into(), int)?, int)?
+        } else {
+            // the reciprocal of the mean of the reciprocals
+            let mut sum = Number::from(1).div(values.next().unwrap(), int)?;
+            for value in values {
+                sum = sum.add(Number::from(1).div(value, int)?, int)?;
+            }
+            Number::from(count).div(sum, int)?
+        };
+        Ok(Self::Num(Box::new(result)))
+    }
+
+    /// Sorts a list of numbers in ascending order, converting units as
+    /// needed to compare them. Equal numbers keep their original order.
+    fn sort<I: Interrupt>(arg: Self, int: &I) -> Result<Self, FendError> {
+        let items = match arg {
+            Self::List(items) => items,
+            Self::Unit => return Err(FendError::ExpectedNumbersToSort),
+            value => vec![value],
+        };
+        let mut items = items
+            .into_iter()
+            .map(|item| match item {
+                Self::Num(n) => Ok(*n),
+                _ => Err(FendError::ExpectedNumbersToSort),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // comparisons can fail (e.g. for incompatible units), so keep the
+        // first error and return it once sorting is done
+        let mut error = None;
+        items.sort_by(|a, b| {
+            a.compare(b, int).unwrap_or_else(|e| {
+                error.get_or_insert(e);
+                Ordering::Equal
+            })
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+        Ok(Self::List(
+            items.into_iter().map(|n| Self::Num(Box::new(n))).collect(),
+        ))
+    }
+
+    /// Returns a list of the numbers from `start` to `end` (inclusive),
+    /// in steps of 1
+    fn range<I: Interrupt>(start: Number, end: Number, int: &I) -> Result<Self, FendError> {
+        if end.compare(&start, int)? == Ordering::Less {
+            return Ok(Self::List(vec![]));
+        }
+        let len = end
+            .sub(start.clone(), int)?
+            .round(Rounding::Floor, int)?
+            .try_as_usize(int)?
+            + 1;
+        if len > MAX_LIST_LENGTH {
+            return Err(FendError::ListTooLong(MAX_LIST_LENGTH));
+        }
+        let mut items = Vec::with_capacity(len);
+        let mut current = start;
+        for _ in 0..len {
+            test_int(int)?;
+            items.push(Self::Num(Box::new(current.clone())));
+            current = current.add(1.into(), int)?;
+        }
+        Ok(Self::List(items))
+    }
+
+    fn apply_registered_function<I: Interrupt>(
+        name: String,
+        arg: Expr,
+        scope: Option<Arc<Scope>>,
+        attrs: Attrs,
+        context: &mut crate::Context,
+        int: &I,
+    ) -> Result<Self, FendError> {
+        let arg = crate::ast::evaluate(arg, scope, attrs, context, int)?
+            .bool_as_num(context)
+            .expect_num()?
+            .try_as_f64(int)?;
+        let function = context
+            .registered_functions
+            .get(&name)
+            .ok_or_else(|| FendError::IdentifierNotFound(Ident::new_string(name.clone())))?;
+        let result = function(arg).map_err(FendError::Wrap)?;
+        if !result.is_finite() {
+            return Err(FendError::NonFiniteFunctionResult(name));
+        }
+        Ok(Self::Num(Box::new(Number::from_f64(result, int)?)))
+    }
+
     pub(crate) fn format_to_plain_string<I: Interrupt>(
         &self,
         indent: usize,
@@ -327,6 +653,9 @@ impl Value {
         Ok(res)
     }
 
+    /// Formats the value into `spans`, and returns whether the formatted
+    /// result is approximate
+    #[allow(clippy::too_many_lines)]
     pub(crate) fn format<I: Interrupt>(
         &self,
         indent: usize,
@@ -334,13 +663,18 @@ impl Value {
         attrs: Attrs,
         ctx: &crate::Context,
         int: &I,
-    ) -> Result<(), FendError> {
+    ) -> Result<bool, FendError> {
+        let mut is_approximate = false;
         match self {
             Self::Num(n) => {
-                n.clone()
-                    .simplify(int)?
-                    .format(ctx, int)?
-                    .spans(spans, attrs);
+                let n = if ctx.auto_simplify {
+                    n.clone().simplify(int)?
+                } else {
+                    n.as_ref().clone()
+                };
+                let formatted = n.format(ctx, int)?;
+                is_approximate = !formatted.is_exact();
+                formatted.spans(spans, attrs);
             }
             Self::BuiltInFunction(name) => {
                 spans.push(Span {
@@ -348,6 +682,12 @@ impl Value {
                     kind: SpanKind::BuiltInFunction,
                 });
             }
+            Self::RegisteredFunction(name) => {
+                spans.push(Span {
+                    string: name.clone(),
+                    kind: SpanKind::BuiltInFunction,
+                });
+            }
             Self::Format(fmt) => {
                 spans.push(Span {
                     string: fmt.to_string(),
@@ -399,7 +739,7 @@ impl Value {
                         spans.push(Span::from_string(" ".to_string()));
                     }
                     spans.push(Span::from_string(format!("{k}: ")));
-                    v.format(indent + 4, spans, attrs, ctx, int)?;
+                    is_approximate |= v.format(indent + 4, spans, attrs, ctx, int)?;
                 }
                 spans.push(Span::from_string("\n}".to_string()));
             }
@@ -416,7 +756,11 @@ impl Value {
                 });
             }
             Self::Bool(b) => spans.push(crate::Span {
-                string: b.to_string(),
+                string: if ctx.boolean_style == crate::BooleanStyle::Numbers {
+                    u8::from(*b).to_string()
+                } else {
+                    b.to_string()
+                },
                 kind: crate::SpanKind::Boolean,
             }),
             Self::Month(m) => spans.push(crate::Span {
@@ -431,8 +775,18 @@ impl Value {
                 string: d.to_string(),
                 kind: crate::SpanKind::Date,
             }),
+            Self::List(items) => {
+                spans.push(Span::from_string("(".to_string()));
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        spans.push(Span::from_string(", ".to_string()));
+                    }
+                    is_approximate |= item.format(indent, spans, attrs, ctx, int)?;
+                }
+                spans.push(Span::from_string(")".to_string()));
+            }
         }
-        Ok(())
+        Ok(is_approximate)
     }
 
     pub(crate) fn get_object_member(self, key: &Ident) -> Result<Self, FendError> {
@@ -451,11 +805,27 @@ impl Value {
     }
 }
 
+fn wrap_function_call(
+    name: &str,
+    lazy_fn: impl FnOnce(Box<Expr>) -> Expr,
+    scope: Option<Arc<Scope>>,
+) -> Value {
+    Value::Fn(
+        Ident::new_str("x"),
+        Box::new(lazy_fn(Box::new(Expr::ApplyFunctionCall(
+            Box::new(Expr::Ident(Ident::new_string(name.to_string()))),
+            Box::new(Expr::Ident(Ident::new_str("x"))),
+        )))),
+        scope,
+    )
+}
+
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Num(n) => write!(f, "{:?}", n),
             Self::BuiltInFunction(name) => write!(f, "built-in function: {}", name.as_str()),
+            Self::RegisteredFunction(name) => write!(f, "registered function: {name}"),
             Self::Format(fmt) => write!(f, "format: {:?}", fmt),
             Self::Dp => write!(f, "dp"),
             Self::Sf => write!(f, "sf"),
@@ -480,6 +850,7 @@ impl fmt::Debug for Value {
             Self::Month(m) => write!(f, "{m}"),
             Self::DayOfWeek(d) => write!(f, "{d}"),
             Self::Date(d) => write!(f, "{d:?}"),
+            Self::List(items) => write!(f, "{items:?}"),
         }
     }
 }
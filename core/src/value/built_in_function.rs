@@ -33,6 +33,20 @@ pub(crate) enum BuiltInFunction {
     Sample,
     Not,
     Conjugate,
+    IsCompatible,
+    Round,
+    Floor,
+    Ceil,
+    Mod,
+    Approx,
+    Range,
+    Map,
+    Filter,
+    Mean,
+    GeometricMean,
+    HarmonicMean,
+    Sort,
+    Reciprocal,
 }
 
 impl BuiltInFunction {
@@ -92,6 +106,20 @@ impl BuiltInFunction {
             Self::Sample => "sample",
             Self::Not => "not",
             Self::Conjugate => "conjugate",
+            Self::IsCompatible => "is_compatible",
+            Self::Round => "round",
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Mod => "mod",
+            Self::Approx => "approx",
+            Self::Range => "range",
+            Self::Map => "map",
+            Self::Filter => "filter",
+            Self::Mean => "mean",
+            Self::GeometricMean => "geometric_mean",
+            Self::HarmonicMean => "harmonic_mean",
+            Self::Sort => "sort",
+            Self::Reciprocal => "reciprocal",
         }
     }
 
@@ -118,6 +146,20 @@ impl BuiltInFunction {
             "sample" => Self::Sample,
             "not" => Self::Not,
             "conjugate" => Self::Conjugate,
+            "is_compatible" => Self::IsCompatible,
+            "round" => Self::Round,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "mod" => Self::Mod,
+            "approx" => Self::Approx,
+            "range" => Self::Range,
+            "map" => Self::Map,
+            "filter" => Self::Filter,
+            "mean" => Self::Mean,
+            "geometric_mean" => Self::GeometricMean,
+            "harmonic_mean" => Self::HarmonicMean,
+            "sort" => Self::Sort,
+            "reciprocal" => Self::Reciprocal,
             _ => return Err(FendError::DeserializationError),
         })
     }
@@ -1,4 +1,7 @@
-use fend_core::{evaluate, Context};
+use fend_core::{
+    evaluate, BooleanStyle, Context, ErrorVerbosity, Locale, NewlinePolicy, Quantity, RoundingMode,
+    UnitDimensionError, UnitSystem,
+};
 
 #[track_caller]
 fn test_serialization_roundtrip(context: &mut Context) {
@@ -26,6 +29,15 @@ fn test_eval_simple(input: &str, expected: &str) {
     test_serialization_roundtrip(&mut context);
 }
 
+// Evaluates the input in an existing context, and returns the main result
+#[track_caller]
+fn eval(input: &str, context: &mut Context) -> String {
+    evaluate(input, context)
+        .unwrap()
+        .get_main_result()
+        .to_string()
+}
+
 #[track_caller]
 fn test_eval(input: &str, expected: &str) {
     let mut context = Context::new();
@@ -154,6 +166,26 @@ fn minus_one_over_zero_indirect() {
     expect_error("-1/(2-2)", Some(DIVISION_BY_ZERO_ERROR));
 }
 
+#[test]
+fn division_by_zero_with_units() {
+    expect_error("5 m / 0", Some(DIVISION_BY_ZERO_ERROR));
+    expect_error("1 / (0 kg)", Some(DIVISION_BY_ZERO_ERROR));
+    expect_error("0 m / 0 s", Some(DIVISION_BY_ZERO_ERROR));
+    expect_error("0 m / (0 m)", Some(DIVISION_BY_ZERO_ERROR));
+}
+
+#[test]
+fn division_by_zero_in_other_bases() {
+    expect_error("0x10 / 0", Some(DIVISION_BY_ZERO_ERROR));
+    expect_error("0b1 / 0b0", Some(DIVISION_BY_ZERO_ERROR));
+}
+
+#[test]
+fn approximate_division_by_zero() {
+    expect_error("sqrt(2) / 0", Some(DIVISION_BY_ZERO_ERROR));
+    expect_error("1 / (pi - pi)", Some(DIVISION_BY_ZERO_ERROR));
+}
+
 #[test]
 fn two_zeroes() {
     test_eval("00", "0");
@@ -219,6 +251,19 @@ fn upper_case_neg_exponent() {
     test_eval("1E-3", "0.001");
 }
 
+#[test]
+fn upper_case_exponent_with_fraction() {
+    test_eval("1.5E3", "1500");
+    test_eval("2E-2", "0.02");
+    test_eval("2.5E+2 m", "250 m");
+}
+
+#[test]
+fn upper_case_hex_digit_is_not_an_exponent() {
+    test_eval("0x1E", "0x1e");
+    test_eval("0x1E to decimal", "30");
+}
+
 #[test]
 fn upper_case_binary_exponent() {
     test_eval("0b10E100 to decimal", "32");
@@ -569,7 +614,49 @@ fn large_simple_subtraction_2() {
 
 #[test]
 fn sqrt_half() {
-    test_eval("sqrt (1/2)", "approx. 0.7071067814");
+    test_eval("sqrt (1/2)", "0.5√2");
+    test_eval("sqrt (1/2) to 10 dp", "approx. 0.7071067814");
+}
+
+#[test]
+fn square_roots_of_fractions() {
+    test_eval_simple("sqrt(4/9) to fraction", "2/3");
+    test_eval_simple("sqrt(4/9) to exact", "2/3");
+    test_eval("sqrt(4/9) == 2/3", "true");
+    test_eval("3 * √(4/9)", "2");
+    test_eval("sqrt(9/16)", "0.75");
+    test_eval_simple("sqrt(9/16) to fraction", "3/4");
+    test_eval("sqrt(1/2)", "0.5√2");
+    test_eval_simple("sqrt(1/2) to exact", "0.5√2");
+}
+
+#[test]
+fn exact_square_roots() {
+    test_eval("sqrt(2) * sqrt(2)", "2");
+    test_eval("sqrt(12) / sqrt(3)", "2");
+    test_eval("sqrt(2) - sqrt(2)", "0");
+    test_eval("sqrt(2)^4", "4");
+    test_eval("√3 * √3 m", "3 m");
+    test_eval("sqrt(8)", "2√2");
+    test_eval_simple("sqrt(8) to exact", "2√2");
+    test_eval_simple("sqrt(2) + sqrt(8) to exact", "3√2");
+    test_eval_simple("sqrt(2) * sqrt(3) to exact", "√6");
+    test_eval_simple("sqrt(6) / sqrt(2) to exact", "√3");
+    test_eval_simple("1 / sqrt(2) to exact", "0.5√2");
+    test_eval_simple("sqrt(5/3) to exact", "√15/3");
+    test_eval_simple("-sqrt(3) to exact", "-√3");
+    test_eval_simple("sqrt(2)^3 to exact", "2√2");
+    test_eval_simple("2^(3/2) to exact", "2√2");
+    test_eval_simple("sqrt(2) i to exact", "√2i");
+    test_eval("sqrt(2)", "√2");
+    test_eval("-sqrt(3)", "-√3");
+    test_eval("sqrt(2) m", "√2 m");
+    test_eval("sqrt(2) to 10 dp", "approx. 1.4142135619");
+    // falls back to an approximation when the coefficient isn't exact or
+    // when combined with other numbers
+    test_eval("sqrt(5/3)", "approx. 1.2909944485");
+    test_eval("sqrt(2) + 1", "approx. 2.4142135619");
+    test_eval("sqrt(2) pi", "approx. 4.4428829367");
 }
 
 #[test]
@@ -584,7 +671,7 @@ fn sqrt_1() {
 
 #[test]
 fn sqrt_2() {
-    test_eval("sqrt 2", "approx. 1.4142135619");
+    test_eval("sqrt 2", "√2");
 }
 
 #[test]
@@ -940,6 +1027,15 @@ fn point_one_e_minus_one() {
     test_eval(".1e-1", "0.01");
 }
 
+#[test]
+fn leading_decimal_point() {
+    test_eval(".5", "0.5");
+    test_eval(".5 kg", "0.5 kg");
+    test_eval("1+.5", "1.5");
+    test_eval("-.5", "-0.5");
+    test_eval("2 * .25", "0.5");
+}
+
 #[test]
 fn leading_zeroes_with_dp() {
     test_eval("001.01000", "1.01");
@@ -1208,12 +1304,37 @@ fn powers_17() {
 
 #[test]
 fn powers_18() {
+    test_eval("5.2*10^15*300^(3/2)", "15600000000000000000√3");
     test_eval(
-        "5.2*10^15*300^(3/2)",
-        "approx. 27019992598076723515.9873962402",
+        "5.2*10^15*300^(3/2) to 10 dp",
+        "approx. 27019992598074485779.0281629278",
     );
 }
 
+#[test]
+fn powers_are_right_associative() {
+    test_eval("2^2^3", "256");
+    test_eval("(2^2)^3", "64");
+    test_eval("2**3**2", "512");
+    test_eval("2**2**3", "256");
+    test_eval("2^3**2", "512");
+    test_eval("2^-1^2", "0.5");
+}
+
+#[test]
+fn superscript_exponents() {
+    test_eval_simple("2²", "4");
+    test_eval_simple("3 m²", "3 m^2");
+    test_eval_simple("10⁻³", "0.001");
+    test_eval_simple("2³²", "4294967296");
+    test_eval_simple("2⁺³", "8");
+    test_eval_simple("(1 + 2)²", "9");
+    test_eval_simple("-2²", "-4");
+    test_eval_simple("5 m/s²", "5 m / s^2");
+    test_eval_simple("1 m² to cm²", "10000 cm^2");
+    expect_error("2⁻x", Some("expected a digit, found 'x'"));
+}
+
 #[test]
 fn pi_to_the_power_of_ten() {
     test_eval("pi^10", "approx. 93648.047476083");
@@ -1221,7 +1342,38 @@ fn pi_to_the_power_of_ten() {
 
 #[test]
 fn zero_to_the_power_of_zero() {
-    expect_error("0^0", None);
+    test_eval("0^0", "1");
+}
+
+#[test]
+fn power_edge_cases() {
+    let cases: &[(&str, Result<&str, &str>)] = &[
+        ("0^0", Ok("1")),
+        ("0.0^0", Ok("1")),
+        ("(-8)^0", Ok("1")),
+        ("0^0.5", Ok("0")),
+        ("0^-1", Err(DIVISION_BY_ZERO_ERROR)),
+        ("0^(-1/2)", Err(DIVISION_BY_ZERO_ERROR)),
+        ("(-8)^(1/3)", Ok("-2")),
+        ("(-8)^(2/3)", Ok("4")),
+        ("(-8)^(-1/3)", Ok("-0.5")),
+        ("(-32)^(1/5)", Ok("-2")),
+        ("cbrt(-27)", Ok("-3")),
+        ("(-2)^(1/3)", Ok("approx. -1.2599210501")),
+        (
+            "(-4)^(1/2)",
+            Err("roots of negative numbers are not supported"),
+        ),
+        ("1/0", Err(DIVISION_BY_ZERO_ERROR)),
+    ];
+    for (input, expected) in cases {
+        let mut context = Context::new();
+        let result = evaluate(input, &mut context);
+        match expected {
+            Ok(expected) => assert_eq!(result.unwrap().get_main_result(), *expected, "{input}"),
+            Err(expected) => assert_eq!(result, Err(expected.to_string()), "{input}"),
+        }
+    }
 }
 
 #[test]
@@ -1246,7 +1398,7 @@ fn exponent_too_large() {
 
 #[test]
 fn i_cubed() {
-    expect_error("i^3", None);
+    test_eval("i^3", "-i");
 }
 
 #[test]
@@ -1524,17 +1676,17 @@ fn digit_separators_14() {
 
 #[test]
 fn digit_separators_15() {
-    test_eval("1,1", "11");
+    expect_error("1,1", None);
 }
 
 #[test]
 fn digit_separators_16() {
-    test_eval("11,1", "111");
+    expect_error("11,1", None);
 }
 
 #[test]
 fn digit_separators_17() {
-    test_eval("1,1,1", "111");
+    expect_error("1,1,1", None);
 }
 
 #[test]
@@ -1544,17 +1696,17 @@ fn digit_separators_18() {
 
 #[test]
 fn digit_separators_19() {
-    test_eval("1,2,3,4,5,6", "123456");
+    expect_error("1,2,3,4,5,6", None);
 }
 
 #[test]
 fn digit_separators_20() {
-    test_eval("1.1,1", "1.11");
+    expect_error("1.1,1", None);
 }
 
 #[test]
 fn digit_separators_21() {
-    test_eval("1,1.1,1", "11.11");
+    expect_error("1,1.1,1", None);
 }
 
 #[test]
@@ -1794,6 +1946,41 @@ fn different_base_38() {
     test_eval("9#5i", "9#5i");
 }
 
+#[test]
+fn negative_base_literals() {
+    test_eval("-0xff", "-0xff");
+    test_eval("-0x10 to decimal", "-16");
+    test_eval("-0b11 to decimal", "-3");
+    test_eval("-16#ff to decimal", "-255");
+    test_eval("- 0b101 to decimal", "-5");
+    test_eval("3 * -0b11", "-9");
+    test_eval("2^-0b11", "0.125");
+    test_eval("-0xff + 0xff", "0x0");
+}
+
+#[test]
+fn mixed_base_arithmetic() {
+    test_eval("0xff + 1", "0x100");
+    test_eval("1 + 0xff", "256");
+    test_eval("0xff + 0xff", "0x1fe");
+    test_eval("0xff + 0b1", "0x100");
+    test_eval("0b1 + 0xff", "0b100000000");
+    test_eval("0x10 * 0o10", "0x80");
+    test_eval("0o10 - 0x1", "0o7");
+}
+
+#[test]
+fn hex_arithmetic_stays_in_hex() {
+    test_eval("0xff + 0x01", "0x100");
+    test_eval("0xff - 0x100", "-0x1");
+    test_eval("0xff * 2", "0x1fe");
+    test_eval("0xff^2", "0xfe01");
+    test_eval("0xff / 2", "0x7f.8");
+    test_eval("0xff + 0x01 to dec", "256");
+    test_eval("0xff + 0x01 to decimal", "256");
+    test_eval("0xff + 0x01 to binary", "100000000");
+}
+
 #[test]
 fn three_electroncharge() {
     test_eval(
@@ -2146,6 +2333,52 @@ fn five_percent_times_100() {
     test_eval("5% * 100", "500%");
 }
 
+#[test]
+fn what_percent_queries() {
+    test_eval("what % is 25 of 200", "12.5%");
+    test_eval("what percent is 5 m of 2 km", "0.25%");
+    test_eval("what % is 1 of 3", "approx. 33.3333333333%");
+    test_eval("x = 4; what % is x of 16", "25%");
+    test_eval("what % is (1 + 2) of 4 * 3", "25%");
+    expect_error("what % is 25 + of 200", Some("expected a token"));
+}
+
+#[test]
+fn increase_and_decrease_queries() {
+    test_eval("increase 200 by 10%", "220");
+    test_eval("decrease 200 by 10%", "180");
+    test_eval("increase 5 kg by 50%", "7.5 kg");
+    test_eval("decrease 5 kg by 20%", "4 kg");
+    test_eval("increase (1 + 1) by 5% + 5%", "2.2");
+    test_eval("increase 200 by 10%; 2", "2");
+    expect_error("increase 200", Some("unknown identifier 'increase'"));
+    expect_error("increase 200 by", Some("expected a token"));
+    expect_error(
+        "increase 200 by 10",
+        Some("expected a percentage like '10%', instead found a plain number"),
+    );
+    expect_error(
+        "decrease 200 by -(0.5)",
+        Some("expected a percentage like '10%', instead found a plain number"),
+    );
+    // like `if`, these queries need parentheses when used as an operand
+    test_eval("2 * (increase 100 by 10%)", "220");
+    expect_error("2 * increase 100 by 10%", None);
+}
+
+#[test]
+fn chained_percentages_are_exact() {
+    test_eval("100 * 20% * 50%", "1000%");
+    test_eval("100 * 20% * 50% to unitless", "10");
+    test_eval("100 * 20% * 50% == 10", "true");
+    test_eval("100 * 20% * 50% - 10", "0%");
+    test_eval("1/3 * 30% * 30%", "3%");
+    test_eval("0.1 * 10% * 10% * 10%", "0.01%");
+    test_eval("7 kg * 15% * 20%", "0.21 kg");
+    let result = evaluate("1/3 * 30% * 30%", &mut Context::new()).unwrap();
+    assert!(!result.is_approximate());
+}
+
 #[test]
 fn units_1() {
     test_eval("0m + 1kph * 1 hr", "1000 m");
@@ -2213,7 +2446,7 @@ fn units_15() {
 
 #[test]
 fn units_22() {
-    test_eval("1psi as kPa as 5dp", "approx. 6.89475 kPa");
+    test_eval("1psi as kPa as 5dp", "approx. 6.89476 kPa");
 }
 
 #[test]
@@ -2314,6 +2547,40 @@ fn units_41() {
     test_eval("5 foot 1 inch 1 inch", "approx. 5.1666666666 feet");
 }
 
+#[test]
+fn pressure_units() {
+    test_eval("1 atm to psi", "approx. 14.6959487755 psi");
+    test_eval("1 atm to psi to atm", "1 atm");
+    test_eval("1 psi to Pa", "approx. 6894.7572931683 Pa");
+    test_eval("1 bar to kPa", "100 kPa");
+    test_eval("1 atm to bar", "1.01325 bar");
+    test_eval("1013.25 mbar to atm", "1 atm");
+    test_eval("760 torr to atm", "1 atm");
+    test_eval("1 atm to mmHg", "760 mmHg");
+    test_eval("1 ksi to MPa", "approx. 6.8947572931 MPa");
+    test_eval("1 kgf/cm^2 to bar", "0.980665 bar");
+}
+
+#[test]
+fn acceleration_units() {
+    test_eval("1 standard_gravity to m/s^2", "9.80665 m / s^2");
+    test_eval("1 kgf / kg to m/s^2", "9.80665 m / s^2");
+    test_eval("100 ft/s^2 to m/s^2", "30.48 m / s^2");
+    test_eval("2 m/s^2 * 3 kg", "6 N");
+}
+
+#[test]
+fn derived_unit_prefixes() {
+    test_eval("1 kN to N", "1000 N");
+    test_eval("1 MPa to kPa", "1000 kPa");
+    test_eval("1 mL to L", "0.001 L");
+    test_eval("1 MWh to kWh", "1000 kWh");
+    test_eval("1 GPa to MPa", "1000 MPa");
+    test_eval("1 mT to uT", "1000 uT");
+    test_eval("1000 mT to tesla", "1 tesla");
+    test_eval("1 T", "1000000000000");
+}
+
 #[test]
 fn plain_adjacent_numbers() {
     expect_error("1 2", None);
@@ -2344,6 +2611,20 @@ fn abs_4_with_coefficient() {
     test_eval("5 (abs 4)", "20");
 }
 
+#[test]
+fn fraction_and_decimal_targets() {
+    test_eval_simple("0.75 as fraction", "3/4");
+    test_eval_simple("0.(3) to fraction", "1/3");
+    test_eval("1/3 as decimal", "approx. 0.3333333333");
+    test_eval("1/7 to decimal", "approx. 0.1428571428");
+    test_eval("0.75 as fraction as decimal", "0.75");
+    test_eval("(1/3 to fraction) to decimal", "approx. 0.3333333333");
+    test_eval("(1.5 to mixed_fraction) to decimal", "1.5");
+    test_eval("1/3 to decimal 3dp", "approx. 0.333");
+    test_eval_simple("1/3 to float to decimal", "0.(3)");
+    test_eval("0x10 to decimal", "16");
+}
+
 #[test]
 fn mixed_fraction_to_improper_fraction() {
     test_eval_simple("1 2/3 to fraction", "5/3");
@@ -2486,7 +2767,7 @@ fn abs_2_i() {
 
 #[test]
 fn abs_1_plus_i() {
-    test_eval("abs (1 + i)", "approx. 1.4142135619");
+    test_eval("abs (1 + i)", "√2");
 }
 
 #[test]
@@ -3050,6 +3331,13 @@ fn tau() {
     test_eval("tau", "approx. 6.2831853071");
 }
 
+#[test]
+fn tau_over_two_is_pi() {
+    test_eval("tau / 2", "approx. 3.1415926535");
+    test_eval("tau / 2 == pi", "true");
+    test_eval("tau == 2pi", "true");
+}
+
 #[test]
 fn sin_tau_over_two() {
     test_eval("sin (tau / 2)", "0");
@@ -3355,6 +3643,38 @@ fn sqrt_minus_two_i() {
     expect_error("sqrt (-2i)", None);
 }
 
+#[test]
+fn sqrt_symbol() {
+    test_eval("√25", "5");
+    test_eval("√4 m", "2 m");
+    test_eval("2√4", "4");
+    test_eval("-√4", "-2");
+    test_eval("√√16", "2");
+    test_eval("√9 + 1", "4");
+    // `√` binds like unary minus, i.e. more tightly than `%`
+    // but less tightly than `^`
+    test_eval("√4^2", "4");
+    test_eval("√25%", "5%");
+    test_eval("√25% to unitless", "0.05");
+    test_eval("√(25%) to unitless", "0.5");
+    expect_error("√", None);
+}
+
+#[test]
+fn cube_and_fourth_root_symbols() {
+    test_eval("∛27", "3");
+    test_eval("∛(-8)", "-2");
+    test_eval("∛(20 + 7)", "3");
+    test_eval("2∛8", "4");
+    test_eval("∛2", "approx. 1.2599210501");
+    test_eval("∜16", "2");
+    test_eval("∜(8 + 8)", "2");
+    test_eval("∜16 + 1", "3");
+    test_eval("∜2", "approx. 1.1892071147");
+    expect_error("∛", None);
+    expect_error("∜", None);
+}
+
 #[test]
 fn cbrt_i() {
     expect_error("cbrt i", None);
@@ -3858,6 +4178,18 @@ fn name_million_dozen() {
     test_eval("million dozen", "12000000");
 }
 
+#[test]
+fn lone_units() {
+    test_eval("kg", "1 kg");
+    test_eval("meter", "1 meter");
+    test_eval("kg to g", "1000 g");
+    test_eval("km + 1 m", "1.001 km");
+    // variables and functions still take precedence
+    test_eval("x = 5; x", "5");
+    test_eval("kg = 3; kg", "3");
+    test_eval_simple("sin", "sin");
+}
+
 #[test]
 fn lone_prefix_yotta() {
     test_eval("yotta", "1000000000000000000000000");
@@ -4451,6 +4783,16 @@ fn two_km_sq_to_hectares() {
     test_eval("2 km^2 to hectare", "200 hectares");
 }
 
+#[test]
+fn acres_and_hectares() {
+    test_eval("1 acre to m^2", "4046.8564224 m^2");
+    test_eval("1 acre to ft^2", "43560 ft^2");
+    test_eval("1 hectare to m^2", "10000 m^2");
+    test_eval("2 acres to ha", "0.80937128448 ha");
+    test_eval("2 hectares to acres", "approx. 4.9421076293 acres");
+    test_eval("2 hectares to acres to ha", "2 ha");
+}
+
 #[test]
 fn kg_to_unitless() {
     expect_error(
@@ -4599,6 +4941,22 @@ fn degrees_kelvin_as_alias() {
     test_eval("6°K", "6 K");
 }
 
+#[test]
+fn temperatures_without_degree_symbol() {
+    test_eval("20 degC to degF", "68 °F");
+    assert_eq!(
+        evaluate("20 degC to degF", &mut Context::new())
+            .unwrap()
+            .get_main_result(),
+        evaluate("20 °C to °F", &mut Context::new())
+            .unwrap()
+            .get_main_result(),
+    );
+    test_eval("300 K to degC", "26.85 °C");
+    test_eval("68 degF to K", "293.15 K");
+    test_eval("20 degC - 5 degC", "15 \u{394}°C");
+}
+
 #[test]
 fn fahrenheit_squared_plus_kelvin_squared() {
     test_eval("(1°F)^2 + 1 K^2", "4.24 °F^2");
@@ -4674,11 +5032,66 @@ fn radians_to_degrees() {
     test_eval("pi radians to °", "180°");
 }
 
+#[test]
+fn mixed_angle_unit_arithmetic() {
+    test_eval("90° + pi/2", "180°");
+    test_eval("90° + pi/2 radians", "180°");
+    test_eval("180° - pi", "0°");
+    test_eval("90° + pi/2 to rad", "approx. 3.1415926535 rad");
+    test_eval("pi/2 + 90°", "approx. 3.1415926535");
+    test_eval("1 rad + 90°", "approx. 2.5707963267 rad");
+    test_eval("30° + 30 arcmin", "30.5°");
+    test_eval("sin(60° + pi/6)", "1");
+}
+
 #[test]
 fn minus_40_fahrenheit_to_celsius() {
     test_eval("-40 F to C", "-40 °C");
 }
 
+#[test]
+fn temperature_differences() {
+    test_eval("10°C - 5°C", "5 Δ°C");
+    test_eval("50°F - 10°C", "0 Δ°F");
+    test_eval("(10°C - 5°C) to Δ°F", "9 Δ°F");
+    test_eval("10 Δ°C to Δ°F", "18 Δ°F");
+    test_eval("10 ΔC to ΔF", "18 Δ°F");
+    test_eval("5 Δ°C to K", "5 K");
+    test_eval("2 millicelsius - 1 millicelsius", "0.001 Δ°C");
+    test_eval("20°C + 18 Δ°F", "30 °C");
+    test_eval("20°C - 9 Δ°F", "15 °C");
+    test_eval("5 Δ°C + 10°C", "15 °C");
+    test_eval("1 Δ°C + 1 Δ°F", "approx. 1.5555555555 Δ°C");
+    expect_error(
+        "5 Δ°C - 10°C",
+        Some("cannot subtract an absolute temperature from a temperature difference"),
+    );
+}
+
+#[test]
+fn only_builtin_temperature_differences_are_differences() {
+    expect_error(
+        "5 'Δx' - 10°C",
+        Some("cannot convert from °C to Δx: units 'kelvin' and 'Δx' are incompatible"),
+    );
+}
+
+#[test]
+fn absolute_temperature_conversions_are_unchanged() {
+    test_eval("10°C to °F", "50 °F");
+}
+
+#[test]
+fn temperature_differences_only_convert_to_differences() {
+    let msg = "cannot convert a temperature difference to an absolute temperature (use e.g. Δ°F or K instead)";
+    expect_error("(10 °C - 5 °C) to °C", Some(msg));
+    expect_error("10 Δ°C to °F", Some(msg));
+    expect_error("(10 °C - 5 °C) to °F", Some(msg));
+    test_eval("(10 °C - 5 °C) to Δ°F", "9 Δ°F");
+    test_eval("10 Δ°C to K", "10 K");
+    test_eval("5 Δ°C to mK", "5000 mK");
+}
+
 #[test]
 fn gigabits_to_gigabytes() {
     test_eval("25Gib/s to GB/s", "3.3554432 GB / s");
@@ -5162,6 +5575,53 @@ fn custom_base_unit_in_calculation_2() {
     test_eval("5 'pigeons' per meter / 'pigeons'", "5 meters");
 }
 
+#[test]
+fn per_applies_to_whole_quantity() {
+    test_eval("1 L per 100 km", "0.01 L / km");
+    test_eval("5 per 2 h", "2.5 h^-1");
+    test_eval("per hour", "1 hour^-1");
+    // otherwise `per` has the same precedence as `/`
+    test_eval("6 per 2 * 3", "9");
+    test_eval("6 / 2 per 3", "1");
+}
+
+#[test]
+fn fuel_economy() {
+    test_eval_simple(
+        "1 gallon per 100 miles to L per 100 km",
+        "approx. 2.3521458333 L / 100 km",
+    );
+    test_eval_simple("10 km/L to L per 100 km", "10 L / 100 km");
+    // mpg and L/100km are reciprocal
+    test_eval_simple("30 mpg to L per 100 km", "approx. 7.8404861111 L / 100 km");
+    test_eval("7 L per 100 km to mpg", "approx. 33.6020833333 mpg");
+    test_eval("30 mpg to km/L", "approx. 12.7543112229 km / L");
+    test_eval_simple("1 km/h to m per 10 s", "approx. 2.7777777777 m / 10 s");
+    test_eval("2 s to Hz", "0.5 Hz");
+    expect_error(
+        "30 mpg to kg per 100 km",
+        Some("cannot convert from mpg to kg / 100 km: units 'meter^-2' and 'kilogram / meter' are incompatible"),
+    );
+    expect_error(
+        "2 m to 3 m",
+        Some("right-hand side of unit conversion has a numerical value"),
+    );
+}
+
+#[test]
+fn speed_and_pace() {
+    test_eval("60 mph to seconds per mile", "60 seconds / mile");
+    test_eval("30 mph to seconds per mile", "120 seconds / mile");
+    test_eval("4 minutes per mile to mph", "15 mph");
+    test_eval("5 min per km to kph", "12 kph");
+    test_eval("10 km/h to minutes per km", "6 minutes / km");
+    test_eval("60 mph to seconds per mile to mph", "60 mph");
+    expect_error(
+        "60 mph to seconds per kg",
+        Some("cannot convert from mph to seconds / kg: units 'meter / second' and 'second / kilogram' are incompatible"),
+    );
+}
+
 #[test]
 fn five_k() {
     test_eval("5k", "5000");
@@ -5179,7 +5639,7 @@ fn mixed_case_meter() {
 
 #[test]
 fn asin_minus_1() {
-    expect_error("asin -1.1", Some("-1.1 must lie in the interval (-1, 1)"));
+    expect_error("asin -1.1", Some("-1.1 must lie in the interval [-1, 1]"));
 }
 
 #[test]
@@ -5250,6 +5710,137 @@ fn not_one() {
     expect_error("not 1", None);
 }
 
+#[test]
+fn comparisons() {
+    test_eval("1 < 2", "true");
+    test_eval("2 <= 2", "true");
+    test_eval("3 > 4", "false");
+    test_eval("3 >= 4", "false");
+    test_eval("1 m == 100 cm", "true");
+    test_eval("1 m != 1 ft", "true");
+    test_eval("10°C == 50°F", "true");
+    test_eval("0.1 + 0.2 == 0.3", "true");
+    test_eval("5! == 120", "true");
+    test_eval("1 << 2 < 5", "true");
+    test_eval("i == i", "true");
+    test_eval("true == false", "false");
+    test_eval("\"a\" < \"b\"", "true");
+    test_eval("1 < 2 and 3 < 4", "true");
+    test_eval("1 > 2 or false", "false");
+    test_eval("true xor true", "false");
+    expect_error(
+        "1 m < 1 s",
+        Some("cannot convert from s to m: units 'second' and 'meter' are incompatible"),
+    );
+    expect_error("i < 2", Some("complex numbers cannot be ordered"));
+    expect_error("true < false", Some("invalid operands for comparison"));
+    expect_error("1 == true", Some("cannot compare a bool with a number"));
+}
+
+#[test]
+fn unicode_comparisons() {
+    for (unicode, ascii) in [
+        ("1 ≤ 2", "1 <= 2"),
+        ("2 ≤ 1", "2 <= 1"),
+        ("3 ≥ 4", "3 >= 4"),
+        ("4 ≥ 4", "4 >= 4"),
+        ("1 m ≠ 1 ft", "1 m != 1 ft"),
+        ("1 ≠ 1", "1 != 1"),
+    ] {
+        let mut context = Context::new();
+        let unicode_result = evaluate(unicode, &mut context).unwrap();
+        let ascii_result = evaluate(ascii, &mut context).unwrap();
+        assert_eq!(
+            unicode_result.get_main_result(),
+            ascii_result.get_main_result(),
+            "{unicode}"
+        );
+    }
+    test_eval("1 ≤ 2", "true");
+    test_eval("5! ≠ 120", "false");
+}
+
+#[test]
+fn conditional_expressions() {
+    test_eval("x = 3; if x > 0 then x else -x", "3");
+    test_eval("x = -3; if x > 0 then x else -x", "3");
+    test_eval("if 1 > 2 then 1 else if 2 > 1 then 2 else 3", "2");
+    test_eval("f = x: if x < 0 then -x else x; f (-5)", "5");
+    test_eval("(if true then 1 kg else 2 kg) to g", "1000 g");
+    // only the branch that's taken is evaluated
+    test_eval("if true then 1 else 1/0", "1");
+    test_eval("if false then 1/0 else 2", "2");
+    test_eval("false and 1/0 == 1", "false");
+    expect_error(
+        "if true then 1 else (y = 2); y",
+        Some("unknown identifier 'y'"),
+    );
+    expect_error("if 1 then 2 else 3", Some("expected a bool (found number)"));
+    expect_error("if true then 1", None);
+}
+
+#[test]
+fn conditional_branches_must_be_compatible() {
+    test_eval("if true then 1 m else 1 ft", "1 m");
+    test_eval("if false then 1 kg else 500 g", "500 g");
+    test_eval_simple("if true then \"a\" else \"b\"", "a");
+    test_eval("f = n: if n < 2 then n else f(n - 1) + f(n - 2); f 6", "8");
+    let msg = "both branches of an if expression must have compatible types and units";
+    expect_error("if true then 1 m else 1 s", Some(msg));
+    expect_error("if false then 1 m else 1 s", Some(msg));
+    expect_error("if true then 1 else 1 kg", Some(msg));
+    expect_error("if true then 1 else \"a\"", Some(msg));
+    expect_error("if 1 < 2 then true else 3", Some(msg));
+    expect_error("f = x: if x > 0 then x m else x s; f 1", Some(msg));
+}
+
+#[test]
+fn boolean_literals_cannot_be_redefined() {
+    expect_error("true = 5", Some("expected an identifier"));
+    expect_error("\\false.1", Some("expected an identifier"));
+    test_eval("(x: not x) true", "false");
+    test_eval("f = x: not x; f false", "true");
+    test_eval("true to bool", "true");
+}
+
+#[test]
+fn booleans_are_not_numbers() {
+    expect_error("true + 1", Some("expected a number (found bool)"));
+    expect_error("2 - true", Some("expected a number (found bool)"));
+    expect_error("3 * (1 > 2)", Some("expected a number (found bool)"));
+    expect_error("-true", Some("expected a number (found bool)"));
+    expect_error("false == 0", Some("cannot compare a bool with a number"));
+    expect_error("\"a\" + 1", Some("expected a number"));
+}
+
+#[test]
+fn numeric_booleans() {
+    let mut ctx = Context::new();
+    ctx.set_boolean_style(BooleanStyle::Numbers);
+    assert_eq!(eval("true", &mut ctx), "1");
+    assert_eq!(eval("2 > 1", &mut ctx), "1");
+    assert_eq!(eval("1 m == 2 m", &mut ctx), "0");
+    assert_eq!(eval("true + true", &mut ctx), "2");
+    assert_eq!(eval("(1 < 2) + (2 < 3) + (3 < 1)", &mut ctx), "2");
+    assert_eq!(eval("3 * false", &mut ctx), "0");
+    assert_eq!(eval("-true", &mut ctx), "-1");
+    assert_eq!(eval("true == 1", &mut ctx), "1");
+    assert_eq!(eval("false != 0", &mut ctx), "0");
+    assert_eq!(eval("+true", &mut ctx), "1");
+    assert_eq!(eval("/true", &mut ctx), "1");
+    assert_eq!(eval("abs(true)", &mut ctx), "1");
+    assert_eq!(eval("mean(true, false)", &mut ctx), "0.5");
+    assert_eq!(eval("true to decimal", &mut ctx), "1");
+    assert_eq!(eval("true to percent", &mut ctx), "100 percent");
+    assert_eq!(eval("2 true", &mut ctx), "2");
+    assert_eq!(eval("not(true)", &mut ctx), "0");
+    // logical operators still work on booleans
+    assert_eq!(eval("true and false", &mut ctx), "0");
+    assert_eq!(eval("if 2 > 1 then 5 else 6", &mut ctx), "5");
+    ctx.set_boolean_style(BooleanStyle::Words);
+    assert_eq!(eval("2 > 1", &mut ctx), "true");
+}
+
 #[test]
 fn sqm() {
     test_eval("5 sqm", "5 m^2");
@@ -5271,6 +5862,228 @@ fn modulo() {
     }
 }
 
+#[test]
+fn list_functions() {
+    test_eval_simple("range(1, 5)", "(1, 2, 3, 4, 5)");
+    test_eval_simple("range(-1, 1)", "(-1, 0, 1)");
+    test_eval_simple("range(0.5, 3)", "(0.5, 1.5, 2.5)");
+    test_eval_simple("range(5, 1)", "()");
+    test_eval_simple("map(square, range(1, 5))", "(1, 4, 9, 16, 25)");
+    test_eval_simple("map(x: x + 1, (1, 2, 3))", "(2, 3, 4)");
+    test_eval_simple("filter(x: x > 2, range(1, 5))", "(3, 4, 5)");
+    test_eval_simple(
+        "filter(x: x mod 2 == 0, map(square, range(1, 6)))",
+        "(4, 16, 36)",
+    );
+    expect_error("map(sqrt, 4)", Some("expected a list"));
+    expect_error(
+        "range(1, 100000)",
+        Some("lists can contain at most 10000 items"),
+    );
+}
+
+#[test]
+fn mean_function() {
+    test_eval("mean(1, 2, 3)", "2");
+    test_eval("mean(range(1, 4))", "2.5");
+    test_eval("mean(5)", "5");
+    test_eval("avg(1 kg, 3 kg)", "2 kg");
+    test_eval("avg(1 kg, 500 g)", "0.75 kg");
+    expect_error(
+        "mean(range(3, 1))",
+        Some("cannot take the mean of an empty list"),
+    );
+    expect_error("mean()", Some("cannot take the mean of an empty list"));
+    expect_error("avg()", Some("cannot take the mean of an empty list"));
+    expect_error(
+        "mean(1 kg, 2 m)",
+        Some("cannot convert from m to kg: units 'meter' and 'kilogram' are incompatible"),
+    );
+}
+
+#[test]
+fn durations_to_hms() {
+    test_eval_simple("3661 seconds to hms", "1:01:01");
+    test_eval_simple("100.5 minutes to hms", "1:40:30");
+    test_eval_simple("2 days + 90.5 s to hms", "48:01:30.5");
+    test_eval_simple("100 hours to hms", "100:00:00");
+    test_eval_simple("9.25 s to hms", "0:00:09.25");
+    test_eval_simple("0 s to hms", "0:00:00");
+    test_eval_simple("-75 s to hms", "-0:01:15");
+    test_eval_simple("1/3 s to hms", "approx. 0:00:00.3333333333");
+    expect_error(
+        "5 kg to hms",
+        Some("cannot convert from kg to s: units 'kilogram' and 'second' are incompatible"),
+    );
+    expect_error(
+        "1e30 s to hms",
+        Some("duration is too large to be shown as hours, minutes and seconds"),
+    );
+}
+
+#[test]
+fn geometric_and_harmonic_means() {
+    test_eval("geometric_mean(1, 4, 16)", "4");
+    test_eval("geometric_mean(2 m, 8 m)", "4 m");
+    test_eval("geometric_mean(4 m^2, 1 m^2)", "2 m^2");
+    test_eval("(2 m * 8 m)^(1/2)", "4 m");
+    test_eval("harmonic_mean(60 km/h, 40 km/h)", "48 km / h");
+    test_eval("harmonic_mean(1, 2, 4)", "approx. 1.7142857142");
+    test_eval("harmonic_mean(range(2, 2))", "2");
+    expect_error(
+        "geometric_mean(1, 0)",
+        Some("geometric_mean is only defined for positive numbers"),
+    );
+    expect_error(
+        "harmonic_mean(-1, 2)",
+        Some("harmonic_mean is only defined for positive numbers"),
+    );
+    expect_error(
+        "geometric_mean(range(3, 1))",
+        Some("cannot take the geometric_mean of an empty list"),
+    );
+    expect_error(
+        "harmonic_mean()",
+        Some("cannot take the harmonic_mean of an empty list"),
+    );
+}
+
+#[test]
+fn sort_function() {
+    test_eval("sort(3, 1, 2)", "(1, 2, 3)");
+    test_eval(
+        "sort(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0)",
+        "(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10)",
+    );
+    test_eval("sort(-1.5, 1/3, -2)", "(-2, -1.5, approx. 0.3333333333)");
+    test_eval("sort(map(x: 10 - x, range(1, 5)))", "(5, 6, 7, 8, 9)");
+    test_eval_simple("sort(5)", "(5)");
+    test_eval_simple("sort(range(3, 1))", "()");
+    test_eval("sort(1 m, 50 cm, 2 ft, 1 km)", "(50 cm, 2 ft, 1 m, 1 km)");
+    // equal elements keep their order
+    test_eval("sort(1 m, 100 cm, 0.5 m)", "(0.5 m, 1 m, 100 cm)");
+    test_eval("sort(100 cm, 1 m, 0.5 m)", "(0.5 m, 100 cm, 1 m)");
+    expect_error(
+        "sort(1 m, 2 kg)",
+        Some("cannot convert from m to kg: units 'meter' and 'kilogram' are incompatible"),
+    );
+    expect_error("sort(3, 1 + i)", Some("complex numbers cannot be ordered"));
+    expect_error("sort(\"a\", 1)", Some("sort expects one or more numbers"));
+    expect_error("sort(\"a\")", Some("sort expects one or more numbers"));
+    expect_error("sort(true)", Some("sort expects one or more numbers"));
+    expect_error("sort()", Some("sort expects one or more numbers"));
+}
+
+#[test]
+fn modulo_function() {
+    for a in 0..10 {
+        for b in 1..10 {
+            let operator = evaluate(&format!("{a} mod {b}"), &mut Context::new()).unwrap();
+            let function = evaluate(&format!("mod({a}, {b})"), &mut Context::new()).unwrap();
+            assert_eq!(operator.get_main_result(), function.get_main_result());
+        }
+    }
+    test_eval("mod(mod(10, 4), 3)", "2");
+    test_eval("2 * mod(7, 3)", "2");
+    test_eval("0b1001010 mod 5 == mod(0b1001010, 5)", "true");
+    expect_error("mod(5, 0)", Some("modulo by zero"));
+    expect_error(
+        "-7 mod 3",
+        Some("modulo is only supported for positive integers"),
+    );
+    expect_error(
+        "mod(-7, 3)",
+        Some("modulo is only supported for positive integers"),
+    );
+    expect_error("mod(7)", Some("'mod' expects 2 arguments"));
+}
+
+#[test]
+fn chained_conversions() {
+    test_eval("1 km to m to cm", "100000 cm");
+    test_eval("100 km to miles to m", "100000 m");
+    test_eval_simple("1 km to m to hex", "3e8 m");
+    test_eval("1 km as m in cm", "100000 cm");
+    test_eval("1 km to m to cm to 2dp", "100000 cm");
+    expect_error("1 km to m to kg", None);
+    // a conversion inside parentheses is evaluated first
+    expect_error(
+        "1 km to (m to cm)",
+        Some("right-hand side of unit conversion has a numerical value"),
+    );
+}
+
+#[test]
+fn exact_round_trip_conversions() {
+    test_eval("1 m to inches to m", "1 m");
+    test_eval("1.5 m to inches to m", "1.5 m");
+    test_eval("5 m to inches to m to inches to m", "5 m");
+    test_eval("0.1 m to inches to cm to feet to m", "0.1 m");
+    test_eval("1 km to miles to km", "1 km");
+    test_eval("100 kg to lb to kg", "100 kg");
+    test_eval("(1/3) m to inches to m == (1/3) m", "true");
+    test_eval("(1 m to inches) * 0.0254", "1 inch");
+}
+
+#[test]
+fn best_rational_approximation() {
+    test_eval_simple("approx(pi, 1)", "3");
+    test_eval_simple("approx(pi, 10)", "22/7");
+    test_eval_simple("approx(pi, 100)", "311/99");
+    test_eval_simple("approx(pi, 1000)", "355/113");
+    test_eval_simple("approx(pi, 100000)", "312689/99532");
+    test_eval_simple("approx(-pi, 1000)", "-355/113");
+    test_eval_simple("approx(e, 10)", "19/7");
+    test_eval_simple("approx(e, 1000)", "1457/536");
+    test_eval_simple("approx(sqrt 2, 100)", "140/99");
+    test_eval_simple("approx(0.333, 10)", "1/3");
+    test_eval_simple("approx(1/3, 1000)", "1/3");
+    test_eval_simple("approx(2.5 m, 1)", "2 m");
+    test_eval("approx(pi, 1000) == 355/113", "true");
+    expect_error(
+        "approx(pi, 0)",
+        Some("the maximum denominator must be at least 1"),
+    );
+    expect_error("approx(pi, 1, 2)", Some("'approx' expects 2 arguments"));
+}
+
+#[test]
+fn exact_and_approx_functions() {
+    test_eval_simple("exact(1/3)", "1/3");
+    test_eval_simple("exact(2/3 m)", "2/3 m");
+    test_eval("exact(0.5)", "0.5");
+    test_eval("exact(sqrt 4)", "2");
+    test_eval_simple("exact(pi)", "\u{3c0}");
+    test_eval_simple("approx(1/3)", "approx. 0.3333333333");
+    test_eval_simple("approx(1/2)", "approx. 0.5");
+    test_eval_simple("approx(1/3 to fraction)", "approx. 0.3333333333");
+    test_eval_simple("approx(approx(pi, 1000))", "approx. 3.1415929203");
+    expect_error(
+        "exact(sin 1)",
+        Some("cannot make an approximate value exact"),
+    );
+    expect_error(
+        "exact(approx(1/3))",
+        Some("cannot make an approximate value exact"),
+    );
+}
+
+#[test]
+fn floor_division() {
+    test_eval("7 // 2", "3");
+    test_eval("-7 // 2", "-4");
+    test_eval("7 // -2", "-4");
+    test_eval("-7 // -2", "3");
+    test_eval("6 // 3", "2");
+    test_eval("7.5 // 2", "3");
+    test_eval("7 // 2 * 2", "6");
+    test_eval("pi // 1", "3");
+    test_eval("0x10 // 3", "0x5");
+    test_eval("7 m // (20 cm)", "35");
+    test_eval("7 m // 2", "3 m");
+    expect_error("1 // 0", Some("division by zero"));
+}
+
 #[test]
 fn modulo_zero() {
     expect_error("5 mod 0", Some("modulo by zero"));
@@ -5319,7 +6132,7 @@ fn five_dollars() {
 
 #[test]
 fn dollar_prefix() {
-    test_eval_simple("$200/3 to 2dp", "approx. $66.66");
+    test_eval_simple("$200/3 to 2dp", "approx. $66.67");
 }
 
 #[test]
@@ -5363,6 +6176,16 @@ fn multiple_variables() {
     test_eval("a = 3; b = 2a; c = a * b; c + a", "21");
 }
 
+#[test]
+fn statement_errors_abort_remaining_statements() {
+    expect_error("x = 5; y = 1/0; x + y", Some("division by zero"));
+    expect_error("x = 5; y = foo; x + y", Some("unknown identifier 'foo'"));
+    let mut ctx = Context::new();
+    assert!(evaluate("x = 1; y = 1/0; x = 2", &mut ctx).is_err());
+    // earlier statements still take effect
+    assert_eq!(evaluate("x", &mut ctx).unwrap().get_main_result(), "1");
+}
+
 #[test]
 fn mixed_frac() {
     test_eval_simple("4/3 to mixed_frac", "1 1/3");
@@ -5370,7 +6193,8 @@ fn mixed_frac() {
 
 #[test]
 fn farad_conversion() {
-    test_eval("1 farad to A^2 kg^-1 m^-2 s^4", "1 A^2 s^4 kg^-1 m^-2");
+    test_eval_simple("1 farad to A^2 kg^-1 m^-2 s^4", "1 A^2 s^4 kg^-1 m^-2");
+    test_eval("1 A^2 s^4 kg^-1 m^-2", "1 farad");
 }
 
 #[test]
@@ -5492,12 +6316,12 @@ fn planck() {
 
 #[test]
 fn implicit_unit_fudging() {
-    test_eval("5'1 to m to 2dp", "approx. 1.54 m");
+    test_eval("5'1 to m to 2dp", "approx. 1.55 m");
 }
 
 #[test]
 fn implicit_unit_fudging_2() {
-    test_eval("0'1 to m to 2dp", "approx. 0.02 m");
+    test_eval("0'1 to m to 2dp", "approx. 0.03 m");
 }
 
 #[test]
@@ -5657,3 +6481,1573 @@ fn combination_test() {
     test_eval("10 nCr 3", "120");
     test_eval("10 choose 3", "120");
 }
+
+#[test]
+fn symbolic_identifiers() {
+    let mut ctx = Context::new();
+    ctx.set_symbolic_identifiers(true);
+    assert_eq!(
+        evaluate("x + x", &mut ctx).unwrap().get_main_result(),
+        "2 x"
+    );
+    assert_eq!(
+        evaluate("3x * x", &mut ctx).unwrap().get_main_result(),
+        "3 x^2"
+    );
+    assert_eq!(evaluate("2x / x", &mut ctx).unwrap().get_main_result(), "2");
+    assert!(evaluate("x + y", &mut ctx).is_err());
+    // variables still take precedence over symbols
+    evaluate("y = 4", &mut ctx).unwrap();
+    assert_eq!(evaluate("y + y", &mut ctx).unwrap().get_main_result(), "8");
+}
+
+#[test]
+fn symbolic_identifiers_disabled_by_default() {
+    expect_error("x + x", Some("unknown identifier 'x'"));
+}
+
+#[test]
+fn evaluation_diagnostics() {
+    let mut ctx = Context::new();
+    let (result, diagnostics) = fend_core::evaluate_with_diagnostics("2^7 + 1", &mut ctx);
+    assert_eq!(result.unwrap().get_main_result(), "129");
+    assert_eq!(diagnostics.token_count(), 5);
+
+    let (result, diagnostics) = fend_core::evaluate_with_diagnostics("1 +", &mut ctx);
+    assert!(result.is_err());
+    assert_eq!(diagnostics.token_count(), 2);
+}
+
+#[test]
+fn angle_suffix_without_space() {
+    test_eval("90deg", "90 degs");
+    test_eval("90deg to °", "90°");
+    test_eval("90 deg to °", "90°");
+    test_eval("90° to deg", "90 degs");
+    test_eval("90deg - 90°", "0 degs");
+    test_eval("sin (90deg)", "1");
+    test_eval("100grad to °", "90°");
+    test_eval("pi/2 rad to °", "90°");
+    test_eval("2rad to rad", "2 rad");
+}
+
+#[test]
+fn base_conversion_with_precision() {
+    test_eval_simple("0.1 to binary 8dp", "approx. 0.0001101");
+    test_eval_simple("0.1 to binary 8 dp", "approx. 0.0001101");
+    test_eval_simple("0.5 to binary 4dp", "0.1");
+    test_eval_simple("0.1 to hex 4dp", "approx. 0.199a");
+    test_eval_simple("1/3 to hex 6dp", "approx. 0.555555");
+    test_eval_simple("0.1 to octal 5dp", "approx. 0.06315");
+    test_eval_simple("0.1 to hex float", "0.1(9)");
+}
+
+#[test]
+fn load_definitions() {
+    let mut ctx = Context::new();
+    ctx.load_definitions(
+        "# custom definitions\n\
+         unit smoot smoots = 1.7018 m\n\
+         \n\
+         taxrate = 0.15\n\
+         double = x: 2x\n",
+    )
+    .unwrap();
+    assert_eq!(
+        evaluate("364.4 smoots to m", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "620.13592 m"
+    );
+    assert_eq!(
+        evaluate("1 kilosmoot to km", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "1.7018 km"
+    );
+    assert_eq!(
+        evaluate("200 * taxrate", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "30"
+    );
+    assert_eq!(
+        evaluate("double 21", &mut ctx).unwrap().get_main_result(),
+        "42"
+    );
+}
+
+#[test]
+fn load_definitions_reports_failing_line() {
+    let mut ctx = Context::new();
+    assert_eq!(
+        ctx.load_definitions("a = 1\n# comment\nb = 2 +\nzz = 3"),
+        Err("line 3: expected a token".to_string())
+    );
+    // lines before the error are still applied
+    assert_eq!(evaluate("a", &mut ctx).unwrap().get_main_result(), "1");
+    assert!(evaluate("zz", &mut ctx).is_err());
+    assert!(ctx.load_definitions("unit foo").is_err());
+    assert!(ctx.load_definitions("unit foo = 1 +").is_err());
+}
+
+#[test]
+fn define_unit() {
+    let mut ctx = Context::new();
+    ctx.define_unit("beardsecond", "beardseconds", "5 nm")
+        .unwrap();
+    assert_eq!(
+        evaluate("1 m to beardseconds", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "200000000 beardseconds"
+    );
+    ctx.define_unit("widget", "", "!").unwrap();
+    assert_eq!(
+        evaluate("3 widget + 2 widget", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "5 widget"
+    );
+    assert!(ctx.define_unit("bad", "", "'text'").is_err());
+}
+
+#[test]
+fn recursive_unit_definitions() {
+    let mut ctx = Context::new();
+    assert_eq!(
+        ctx.define_unit("foo", "", "2 foo"),
+        Err("the unit 'foo' is defined in terms of itself".to_string())
+    );
+    ctx.define_unit("foo", "", "2 m").unwrap();
+    ctx.define_unit("bar", "", "3 foo").unwrap();
+    assert_eq!(
+        ctx.define_unit("foo", "", "2 bar"),
+        Err("the unit 'foo' is defined in terms of itself".to_string())
+    );
+    assert!(ctx.define_unit("foo", "", "2 foo").is_err());
+    assert!(ctx.load_definitions("unit baz = 2 baz").is_err());
+    // the previous definitions are kept
+    assert_eq!(
+        evaluate("1 bar to m", &mut ctx).unwrap().get_main_result(),
+        "6 m"
+    );
+    assert_eq!(
+        evaluate("1 kilofoo to m", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "2000 m"
+    );
+}
+
+#[test]
+fn define_unit_with_exact_fraction() {
+    let mut ctx = Context::new();
+    ctx.define_unit("myinch", "myinches", "127/5000 m").unwrap();
+    ctx.define_unit("third", "thirds", "1/3 m").unwrap();
+    let eval = |input: &str, ctx: &mut Context| {
+        let result = evaluate(input, ctx).unwrap();
+        (
+            result.get_main_result().to_string(),
+            result.is_approximate(),
+        )
+    };
+    assert_eq!(
+        eval("1 myinch to m", &mut ctx),
+        ("0.0254 m".to_string(), false)
+    );
+    assert_eq!(
+        eval("12 myinches to ft", &mut ctx),
+        ("1 ft".to_string(), false)
+    );
+    assert_eq!(
+        eval("1 m to thirds", &mut ctx),
+        ("3 thirds".to_string(), false)
+    );
+    assert_eq!(
+        eval("10 thirds to m to fraction", &mut ctx),
+        ("10/3 m".to_string(), false)
+    );
+    // the scale is stored exactly, so converting back and forth is lossless
+    assert_eq!(
+        eval("1 m to myinches to m", &mut ctx),
+        ("1 m".to_string(), false)
+    );
+    assert_eq!(
+        eval("1 m to myinches to fraction", &mut ctx),
+        ("5000/127 myinches".to_string(), false)
+    );
+}
+
+#[test]
+fn autocomplete() {
+    let mut ctx = Context::new();
+    let suggestions = ctx.autocomplete("me");
+    assert!(suggestions.contains(&"meter".to_string()));
+    assert!(suggestions.contains(&"mega".to_string()));
+    assert!(suggestions.iter().all(|s| s.starts_with("me")));
+    assert_eq!(ctx.autocomplete("sqr"), vec!["sqrt"]);
+
+    evaluate("meow = 5", &mut ctx).unwrap();
+    ctx.define_unit("megasmoot", "", "1.7018 Mm").unwrap();
+    let suggestions = ctx.autocomplete("me");
+    assert_eq!(suggestions[..2], ["megasmoot", "meow"]);
+    assert_eq!(suggestions, ctx.autocomplete("me"));
+    assert!(ctx.autocomplete("zzzz").is_empty());
+}
+
+#[test]
+fn imperial_unit_system() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("1 gallon to L", &mut ctx), "3.785411784 L");
+    ctx.set_unit_system(fend_core::UnitSystem::Imperial);
+    assert_eq!(eval("1 gallon to L", &mut ctx), "4.54609 L");
+    assert_eq!(eval("2 gallons to L", &mut ctx), "9.09218 L");
+    assert_eq!(eval("1 pint to mL", &mut ctx), "568.26125 mL");
+    assert_eq!(eval("20 floz to pints", &mut ctx), "1 pint");
+    assert_eq!(eval("1 cwt to lbs", &mut ctx), "112 lbs");
+    // units that don't differ are unaffected
+    assert_eq!(eval("1 mile to km", &mut ctx), "1.609344 km");
+    ctx.set_unit_system(fend_core::UnitSystem::UsCustomary);
+    assert_eq!(eval("1 cwt to lbs", &mut ctx), "100 lbs");
+}
+
+#[test]
+fn register_function() {
+    let mut ctx = Context::new();
+    ctx.register_function("double", |x| Ok(2.0 * x));
+    ctx.register_function("fail", |_| Err("custom failure".into()));
+    ctx.register_function("infinite", |x| Ok(x / 0.0));
+    ctx.register_function("sqrt", |x| Ok(x.sqrt() + 100.0));
+    let mut eval = |input: &str| evaluate(input, &mut ctx).map(|r| r.get_main_result().to_string());
+    assert_eq!(eval("double 21"), Ok("approx. 42".to_string()));
+    assert_eq!(eval("double(1.5) + 1"), Ok("approx. 4".to_string()));
+    assert_eq!(eval("double (50%)"), Ok("approx. 1".to_string()));
+    assert_eq!(eval("(2 double) 4"), Ok("approx. 16".to_string()));
+    // registered functions shadow built-in functions
+    assert_eq!(eval("sqrt 16"), Ok("approx. 104".to_string()));
+    assert_eq!(eval("sqrt 2"), Ok("approx. 101.4142135623".to_string()));
+    assert_eq!(
+        eval("double (5 kg)"),
+        Err("expected a unitless number".to_string())
+    );
+    assert_eq!(eval("fail 1"), Err("custom failure".to_string()));
+    assert_eq!(
+        eval("infinite 1"),
+        Err("function 'infinite' returned a non-finite value".to_string())
+    );
+    // variables still take precedence
+    assert_eq!(eval("double = 5; double"), Ok("5".to_string()));
+}
+
+#[test]
+fn integer_powers_of_complex_numbers() {
+    test_eval("i^2", "-1");
+    test_eval("(1+i)^2", "2i");
+    test_eval("i^-1", "-i");
+    test_eval("(1+i)^-1", "0.5 - 0.5i");
+    expect_error("i^0.5", None);
+}
+
+#[test]
+fn imaginary_unit_symbol_j() {
+    let mut ctx = Context::new();
+    ctx.set_imaginary_unit_symbol(fend_core::ImaginaryUnitSymbol::J);
+    assert_eq!(eval("2 + 3j", &mut ctx), "2 + 3j");
+    assert_eq!(eval("j^2", &mut ctx), "-1");
+    assert_eq!(eval("(1 + j) * (1 - j)", &mut ctx), "2");
+    assert_eq!(eval("3 - 2i", &mut ctx), "3 - 2j");
+    assert_eq!(eval("pi j", &mut ctx), "\u{3c0}j");
+    // `J` is still joules
+    assert_eq!(eval("5 J to kJ", &mut ctx), "0.005 kJ");
+}
+
+#[test]
+fn j_is_joules_by_default() {
+    test_eval("5 j", "5 J");
+}
+
+#[test]
+fn is_compatible() {
+    test_eval("is_compatible(1 m, 1 ft)", "true");
+    test_eval("is_compatible(1 m, 1 s)", "false");
+    test_eval("is_compatible(1 kWh, 1 J)", "true");
+    test_eval("is_compatible(5, 2)", "true");
+    expect_error(
+        "is_compatible(1 m, 1 ft, 1 s)",
+        Some("'is_compatible' expects 2 arguments"),
+    );
+    expect_error(
+        "is_compatible 1 m",
+        Some("'is_compatible' expects 2 arguments"),
+    );
+}
+
+#[test]
+fn comma_separated_values() {
+    test_eval("(1, 2)", "(1, 2)");
+    test_eval("(1 m, 2 + 3)", "(1 m, 5)");
+    test_eval("1,000", "1000");
+}
+
+#[test]
+fn comma_separated_values_without_spaces() {
+    test_eval("(1,2)", "(1, 2)");
+    test_eval("mean(1,2,3)", "2");
+    test_eval("sort(3,1,2)", "(1, 2, 3)");
+    test_eval("mod(7,3)", "1");
+    test_eval("is_compatible(1,2)", "true");
+    test_eval_simple("approx(pi,10)", "22/7");
+    test_eval("(1,234)", "1234");
+    test_eval("1,000,000", "1000000");
+    test_eval("1,234.5", "1234.5");
+}
+
+// Unoptimised builds use a lot more stack space per level of nesting than
+// release builds, so these tests run on a thread with a larger stack
+fn with_large_stack(f: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn deeply_nested_input() {
+    with_large_stack(deeply_nested_input_impl);
+}
+
+fn deeply_nested_input_impl() {
+    let too_deep = [
+        "(".repeat(10000),
+        "-".repeat(10000) + "1",
+        "1+".repeat(10000) + "1",
+        "2^".repeat(10000) + "2",
+        "sqrt ".repeat(10000) + "4",
+        "x:".repeat(10000) + "x",
+        "a=".repeat(10000) + "1",
+        "1;".repeat(10000),
+        "(1,".repeat(10000),
+    ];
+    for input in too_deep {
+        expect_error(&input, Some("expression is too deeply nested"));
+    }
+    test_eval(&format!("{}1{}", "(".repeat(70), ")".repeat(70)), "1");
+    test_eval(&format!("{}1{}", "(".repeat(120), ")".repeat(120)), "1");
+    test_eval(&("1+".repeat(50) + "1"), "51");
+    test_eval(&("-".repeat(100) + "1"), "1");
+    test_eval(&format!("{}-4{}", "abs(".repeat(50), ")".repeat(50)), "4");
+}
+
+#[test]
+fn long_flat_expressions() {
+    with_large_stack(long_flat_expressions_impl);
+}
+
+fn long_flat_expressions_impl() {
+    let sum = (1..=100)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join("+");
+    test_eval(&sum, "5050");
+    test_eval(&("1+".repeat(129) + "1"), "130");
+    test_eval(&("1*".repeat(199) + "1"), "1");
+    test_eval(&("x = 1; ".repeat(200) + "x"), "1");
+    test_eval(&("2-".repeat(69) + "2"), "-136");
+    test_eval(&("1;".repeat(69) + "2"), "2");
+    test_eval(&("1 m + ".repeat(70) + "1 m"), "71 m");
+    test_eval(&("true and ".repeat(70) + "true"), "true");
+    test_eval(&("1+".repeat(1000) + "1"), "1001");
+    test_eval(&format!("f = x: {}x; f 2", "x+".repeat(500)), "1002");
+    expect_error(
+        &("1+".repeat(5000) + "1"),
+        Some("expression is too deeply nested"),
+    );
+}
+
+#[test]
+fn unusual_characters() {
+    expect_error("\u{1f642}", Some("unexpected character '\u{1f642}'"));
+    expect_error("\u{221e}", Some("infinity is not a representable value"));
+    expect_error("1 / ∞", Some("infinity is not a representable value"));
+    expect_error("(,)", Some("expected a value, instead found ','"));
+    expect_error("1,,", Some("unexpected input found"));
+    expect_error("\\", Some("expected a token"));
+    expect_error("\u{0}", None);
+}
+
+#[test]
+fn scientific_notation() {
+    test_eval_simple("12300 to scientific", "1.23e4");
+    test_eval_simple("12300 kg to sci", "1.23e4 kg");
+    test_eval_simple("-12300 kg to sci", "-1.23e4 kg");
+    test_eval_simple("0.00015 m to sci", "1.5e-4 m");
+    test_eval_simple("1.5e-20 kg/s to sci", "1.5e-20 kg / s");
+    test_eval_simple("1 to sci", "1e0");
+    test_eval_simple("10 to sci", "1e1");
+    test_eval_simple("0.1 to sci", "1e-1");
+    test_eval_simple("0 kg to sci", "0 kg");
+    test_eval_simple("1/3 to sci", "approx. 3.3333333333e-1");
+    test_eval_simple("1200 ft to m to sci", "3.6576e2 m");
+    // the format is kept when converting to other units
+    test_eval_simple("12300 kg to sci to g", "1.23e7 g");
+    // `e` is a digit in hexadecimal
+    test_eval_simple("0xff to sci", "0xff");
+}
+
+#[test]
+fn tiny_numbers_and_negative_zero() {
+    test_eval("1e-300", "1e-300");
+    test_eval("-1e-300", "-1e-300");
+    test_eval("1.5e-60 kg", "1.5e-60 kg");
+    test_eval("1e-51", "1e-51");
+    test_eval(
+        "1e-50",
+        "0.00000000000000000000000000000000000000000000000001",
+    );
+    test_eval("1e-300 * pi", "approx. 0");
+    test_eval("-1e-300 * pi", "approx. 0");
+    test_eval("-0.001 to 2dp", "approx. 0");
+    test_eval("-0.0", "0");
+    test_eval("-0.0 * 1.5", "0");
+    test_eval("0 * -1", "0");
+    test_eval("-(0.1 - 0.1)", "0");
+}
+
+#[test]
+fn enumerate_variables_and_functions() {
+    let mut ctx = Context::new();
+    ctx.register_function("double", |x| Ok(2.0 * x));
+    evaluate("f = x: 2x", &mut ctx).unwrap();
+    evaluate("a = 1/4", &mut ctx).unwrap();
+    evaluate("b = 5 kg", &mut ctx).unwrap();
+    assert_eq!(
+        ctx.variables(false),
+        Ok(vec![
+            ("a".to_string(), "0.25".to_string()),
+            ("b".to_string(), "5 kg".to_string()),
+        ])
+    );
+    assert_eq!(
+        ctx.variables(true),
+        Ok(vec![
+            ("_".to_string(), "5 kg".to_string()),
+            ("a".to_string(), "0.25".to_string()),
+            ("ans".to_string(), "5 kg".to_string()),
+            ("b".to_string(), "5 kg".to_string()),
+        ])
+    );
+    assert_eq!(ctx.functions(false), vec!["double", "f"]);
+    let all_functions = ctx.functions(true);
+    assert!(all_functions.contains(&"sqrt".to_string()));
+    assert!(all_functions.contains(&"f".to_string()));
+    assert!(all_functions.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn max_digits() {
+    let mut ctx = Context::new();
+    ctx.set_max_digits(20);
+    let res = evaluate("1/7 to 50dp", &mut ctx).unwrap();
+    assert_eq!(res.get_main_result(), "approx. 0.1428571428\u{2026}");
+    assert_eq!(
+        res.get_other_info().collect::<Vec<_>>(),
+        ["output truncated to 20 characters"]
+    );
+    let res = evaluate("30!", &mut ctx).unwrap();
+    assert_eq!(res.get_main_result(), "26525285981219105863\u{2026}");
+    assert_eq!(res.get_main_result_spans().count(), 1);
+    // results that fit are unchanged
+    let res = evaluate("20!", &mut ctx).unwrap();
+    assert_eq!(res.get_main_result(), "2432902008176640000");
+    assert_eq!(res.get_other_info().count(), 0);
+    let res = evaluate("12345678901234567890", &mut ctx).unwrap();
+    assert_eq!(res.get_main_result(), "12345678901234567890");
+    // the default limit is much larger
+    let res = evaluate("1/7 to 5000dp", &mut Context::new()).unwrap();
+    assert_eq!(res.get_other_info().count(), 0);
+}
+
+#[test]
+fn structured_version() {
+    let version = fend_core::version();
+    assert_eq!(version.to_string(), fend_core::get_version());
+    assert_eq!(
+        format!("{}.{}.{}", version.major, version.minor, version.patch),
+        env!("CARGO_PKG_VERSION")
+    );
+    assert!(version.pre.is_empty());
+    test_eval_simple("version", &format!("{version}"));
+}
+
+#[test]
+fn version_release_date() {
+    let version = fend_core::version();
+    let date = version.release_date;
+    assert_eq!(date.to_string().len(), 10);
+    assert!((1..=12).contains(&date.month));
+    assert!((1..=31).contains(&date.day));
+    // the date needs to match the changelog entry for this version
+    let changelog = include_str!("../../CHANGELOG.md");
+    assert!(changelog.contains(&format!("### v{version} ({date})")));
+}
+
+#[test]
+fn rounding_functions() {
+    test_eval("round 2.5", "2");
+    test_eval("round (-2.5)", "-2");
+    test_eval("floor 2.5", "2");
+    test_eval("floor (-2.5)", "-3");
+    test_eval("ceil 2.1", "3");
+    test_eval("ceil (-2.5)", "-2");
+    test_eval("floor pi", "3");
+    test_eval("round 17.3 kg", "17 kg");
+}
+
+#[test]
+fn rounding_to_significant_figures() {
+    test_eval("round 12345 to 2 sf", "12000");
+    test_eval("round 12345 m to 2 sig figs", "12000 m");
+    test_eval("round 0.012345 kg to 2 significant figures", "0.012 kg");
+    test_eval("round (-12345 m) to 3 sf", "-12300 m");
+    test_eval("floor 12345 m to 2 sf", "12000 m");
+    test_eval("ceil 12345 m to 2 sf", "13000 m");
+    test_eval("round 99.9 to 2 sf", "100");
+    test_eval("round pi to 3 sf", "3.14");
+    // values that are already short enough, and zero, are unchanged
+    test_eval("round 12 m to 5 sf", "12 m");
+    test_eval("round 0 kg to 2 sf", "0 kg");
+    expect_error(
+        "round 12345 to 0 sf",
+        Some("cannot format a number with zero significant figures"),
+    );
+    // without `round`, only the formatting changes
+    test_eval("12345 m to 2 sig figs", "approx. 12000 m");
+}
+
+#[test]
+fn rounding_to_increment() {
+    test_eval("round 17 to nearest 5", "15");
+    test_eval("17 to nearest 5", "15");
+    test_eval("round(17, 5)", "15");
+    test_eval("floor(19, 5)", "15");
+    test_eval("ceil 11 to nearest 5", "15");
+    test_eval("ceil 17 minutes to nearest 15 minutes", "30 minutes");
+    test_eval("floor 17 minutes to nearest 15 minutes", "15 minutes");
+    test_eval("ceil(17 min, 0.25 hours)", "30 mins");
+    test_eval("round 1.234 m to nearest 1 cm", "1.23 m");
+    expect_error(
+        "round 17 to nearest 0",
+        Some("cannot round to an increment of zero"),
+    );
+    expect_error(
+        "round 17 kg to nearest 5 m",
+        Some("cannot convert from m to kg: units 'meter' and 'kilogram' are incompatible"),
+    );
+}
+
+#[test]
+fn space_digit_separator() {
+    let mut ctx = Context::new();
+    assert!(evaluate("1 000 000", &mut ctx).is_err());
+    ctx.set_space_digit_separator(true);
+    assert_eq!(eval("1 000 000", &mut ctx), "1000000");
+    assert_eq!(eval("12 345.5 + 1", &mut ctx), "12346.5");
+    assert_eq!(eval("1\u{202f}000 km", &mut ctx), "1000 km");
+    assert_eq!(eval("-2 500", &mut ctx), "-2500");
+    assert_eq!(eval("2 000 km + 1 m", &mut ctx), "2000.001 km");
+    // groups need to be exactly three digits long
+    for input in ["1 2", "1 0000", "1234 567", "1 000 2"] {
+        assert!(evaluate(input, &mut ctx).is_err(), "{input}");
+    }
+    // only decimal numbers can be grouped with spaces
+    assert!(evaluate("0x10 100", &mut ctx).is_err());
+    assert_eq!(eval("(1, 234)", &mut ctx), "(1, 234)");
+}
+
+#[test]
+fn rounding_modes() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("round 2.5", &mut ctx), "2");
+    assert_eq!(eval("round 3.5", &mut ctx), "4");
+    assert_eq!(eval("2.5 to 0dp", &mut ctx), "approx. 2");
+    assert_eq!(eval("3.5 to 0dp", &mut ctx), "approx. 4");
+    for (mode, expected) in [
+        (RoundingMode::HalfUp, ["3", "4", "-3", "0.67"]),
+        (RoundingMode::HalfEven, ["2", "4", "-2", "0.67"]),
+        (RoundingMode::TowardZero, ["2", "3", "-2", "0.66"]),
+        (RoundingMode::AwayFromZero, ["3", "4", "-3", "0.67"]),
+    ] {
+        ctx.set_rounding_mode(mode);
+        assert_eq!(eval("round 2.5", &mut ctx), expected[0], "{mode:?}");
+        assert_eq!(eval("round 3.5", &mut ctx), expected[1], "{mode:?}");
+        assert_eq!(eval("round (-2.5)", &mut ctx), expected[2], "{mode:?}");
+        let approx = |s: &str| format!("approx. {s}");
+        assert_eq!(eval("2.5 to 0dp", &mut ctx), approx(expected[0]));
+        assert_eq!(eval("3.5 to 0dp", &mut ctx), approx(expected[1]));
+        assert_eq!(eval("-2.5 to 0dp", &mut ctx), approx(expected[2]));
+        assert_eq!(eval("2/3 to 2dp", &mut ctx), approx(expected[3]));
+    }
+    ctx.set_rounding_mode(RoundingMode::AwayFromZero);
+    assert_eq!(eval("round 2.1", &mut ctx), "3");
+    assert_eq!(eval("9.991 to 2dp", &mut ctx), "approx. 10");
+    assert_eq!(eval("0.1 to 2dp", &mut ctx), "0.1");
+    // approximations shown with the default precision are truncated
+    assert_eq!(eval("2/3", &mut ctx), "approx. 0.6666666666");
+}
+
+#[test]
+fn compound_units_shown_as_named_units() {
+    // energy
+    test_eval("5 N * 2 m", "10 J");
+    test_eval("10 kg m^2 / s^2", "10 J");
+    test_eval("2 W * 3 s", "6 J");
+    // force
+    test_eval("2 kg * 3 m / s^2", "6 N");
+    test_eval("1 Pa * 1 m^2", "1 N");
+    test_eval("1 g * 1 km / s^2", "1 N");
+    test_eval("1 V * 2 A", "2 W");
+    test_eval("6 V / (2 A)", "3 ohms");
+    // units with a different scale are left unchanged
+    test_eval("1 kW * 1 h", "1 kW h");
+    test_eval("3 m * 2 m", "6 m^2");
+    // explicit conversions are respected
+    test_eval_simple("10 J to N m", "10 N m");
+}
+
+#[test]
+fn input_history() {
+    let mut ctx = Context::new();
+    evaluate("1 + 1", &mut ctx).unwrap();
+    assert!(ctx.input_history().is_empty());
+
+    ctx.set_input_history_limit(3);
+    for input in ["1 + 1", "1 + 1", "", "  ", "2 + 2", "1 + 1", "foo bar"] {
+        let _ = evaluate(input, &mut ctx);
+    }
+    // consecutive duplicates and blank inputs are skipped, and the
+    // history is limited to the most recent entries
+    assert_eq!(ctx.input_history(), ["2 + 2", "1 + 1", "foo bar"]);
+    // the previous result is unaffected by the failed evaluation
+    assert_eq!(evaluate("ans", &mut ctx).unwrap().get_main_result(), "2");
+
+    // previews don't modify the history
+    struct Never;
+    impl fend_core::Interrupt for Never {
+        fn should_interrupt(&self) -> bool {
+            false
+        }
+    }
+    let _ = fend_core::evaluate_preview_with_interrupt("3 + 3", &mut ctx, &Never);
+    assert_eq!(ctx.input_history().last().unwrap(), "ans");
+
+    ctx.set_input_history_limit(1);
+    assert_eq!(ctx.input_history(), ["ans"]);
+    ctx.clear_input_history();
+    assert!(ctx.input_history().is_empty());
+}
+
+#[test]
+fn clear_history() {
+    let mut ctx = Context::new();
+    ctx.set_input_history_limit(10);
+    evaluate("x = 5", &mut ctx).unwrap();
+    evaluate("2 + 2", &mut ctx).unwrap();
+    assert_eq!(evaluate("ans", &mut ctx).unwrap().get_main_result(), "4");
+    ctx.clear_history();
+    assert!(ctx.input_history().is_empty());
+    assert_eq!(
+        evaluate("ans", &mut ctx),
+        Err("unknown identifier 'ans'".to_string())
+    );
+    assert_eq!(
+        evaluate("_", &mut ctx),
+        Err("unknown identifier '_'".to_string())
+    );
+    // other variables and settings are kept
+    assert_eq!(evaluate("x", &mut ctx).unwrap().get_main_result(), "5");
+    assert_eq!(ctx.input_history(), ["ans", "_", "x"]);
+}
+
+#[test]
+fn interrupt_deeply_nested_parse() {
+    // interrupts after being polled a fixed number of times
+    struct InterruptAfter(std::cell::Cell<u32>);
+    impl fend_core::Interrupt for InterruptAfter {
+        fn should_interrupt(&self) -> bool {
+            let remaining = self.0.get();
+            self.0.set(remaining.saturating_sub(1));
+            remaining == 0
+        }
+    }
+
+    // backtracking makes this take minutes to parse
+    let input = format!("{}{}", "(1 ".repeat(30), ")".repeat(30));
+    let int = InterruptAfter(std::cell::Cell::new(10_000));
+    let start = std::time::Instant::now();
+    let result = fend_core::evaluate_with_interrupt(&input, &mut Context::new(), &int);
+    assert_eq!(result.unwrap_err(), "interrupted");
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+}
+
+#[test]
+fn progress_reporting() {
+    struct RecordProgress(std::cell::RefCell<Vec<fend_core::Progress>>);
+    impl fend_core::Interrupt for RecordProgress {
+        fn should_interrupt(&self) -> bool {
+            false
+        }
+
+        fn report_progress(&self, progress: fend_core::Progress) {
+            self.0.borrow_mut().push(progress);
+        }
+    }
+
+    let mut ctx = Context::new();
+    let int = RecordProgress(std::cell::RefCell::new(vec![]));
+    fend_core::evaluate_with_interrupt("100!", &mut ctx, &int).unwrap();
+    let progress = int.0.take();
+    assert_eq!(progress.len(), 99);
+    assert_eq!(progress[0].step(), 0);
+    assert_eq!(progress[0].total(), Some(100));
+    assert_eq!(progress[98].step(), 98);
+    assert_eq!(progress[50].fraction(), Some(0.5));
+
+    fend_core::evaluate_with_interrupt("1/7 to 50 dp", &mut ctx, &int).unwrap();
+    let progress = int.0.take();
+    assert!(!progress.is_empty());
+    assert!(progress.iter().all(|p| p.total() == Some(50)));
+    assert_eq!(progress.last().unwrap().fraction(), Some(1.0));
+}
+
+#[test]
+fn canonical_unit_name() {
+    let mut ctx = Context::new();
+    let canonical_name = |name: &str, ctx: &Context| ctx.canonical_unit_name(name);
+    assert_eq!(canonical_name("metre", &ctx).as_deref(), Some("meter"));
+    assert_eq!(canonical_name("metres", &ctx).as_deref(), Some("meter"));
+    assert_eq!(canonical_name("meter", &ctx).as_deref(), Some("meter"));
+    assert_eq!(canonical_name("m", &ctx).as_deref(), Some("meter"));
+    assert_eq!(canonical_name("feet", &ctx).as_deref(), Some("foot"));
+    assert_eq!(canonical_name("ft", &ctx).as_deref(), Some("foot"));
+    assert_eq!(canonical_name("litre", &ctx).as_deref(), Some("liter"));
+    assert_eq!(canonical_name("hr", &ctx).as_deref(), Some("hour"));
+    assert_eq!(canonical_name("Pa", &ctx).as_deref(), Some("pascal"));
+    assert_eq!(canonical_name("C", &ctx).as_deref(), Some("celsius"));
+    assert_eq!(canonical_name("bar", &ctx).as_deref(), Some("bar"));
+    assert_eq!(canonical_name("xyz", &ctx), None);
+    ctx.define_unit("smoot", "smoots", "1.7018 m").unwrap();
+    assert_eq!(canonical_name("smoots", &ctx).as_deref(), Some("smoot"));
+}
+
+#[test]
+fn evaluate_as_type() {
+    let mut ctx = Context::new();
+    assert_eq!(ctx.evaluate_as::<f64>("1/4"), Ok(0.25));
+    assert_eq!(ctx.evaluate_as::<f64>("50%"), Ok(0.5));
+    assert_eq!(
+        ctx.evaluate_as::<f64>("2 m"),
+        Err("expected a unitless number".to_string())
+    );
+    assert_eq!(ctx.evaluate_as::<i64>("-42"), Ok(-42));
+    assert_eq!(ctx.evaluate_as::<i64>("-(2^63)"), Ok(i64::MIN));
+    assert_eq!(
+        ctx.evaluate_as::<i64>("1.5"),
+        Err("cannot convert fraction to integer".to_string())
+    );
+    assert_eq!(
+        ctx.evaluate_as::<i64>("3 kg"),
+        Err("cannot convert number with unit to integer".to_string())
+    );
+    assert!(ctx.evaluate_as::<i64>("2^70").is_err());
+    assert_eq!(
+        ctx.evaluate_as::<(f64, String)>("2.5 km"),
+        Ok((2.5, "km".to_string()))
+    );
+    assert_eq!(
+        ctx.evaluate_as::<(f64, String)>("5 m/s"),
+        Ok((5.0, "m / s".to_string()))
+    );
+    assert_eq!(
+        ctx.evaluate_as::<(f64, String)>("10 N m"),
+        Ok((10.0, "J".to_string()))
+    );
+    ctx.set_auto_simplify(false);
+    assert_eq!(
+        ctx.evaluate_as::<(f64, String)>("10 N m"),
+        Ok((10.0, "N m".to_string()))
+    );
+    ctx.set_auto_simplify(true);
+    assert_eq!(
+        ctx.evaluate_as::<(f64, String)>("5"),
+        Ok((5.0, String::new()))
+    );
+    assert_eq!(ctx.evaluate_as::<bool>("1 < 2"), Ok(true));
+    assert!(ctx.evaluate_as::<bool>("1").is_err());
+    assert_eq!(
+        ctx.evaluate_as::<String>("1/3"),
+        Ok("approx. 0.3333333333".to_string())
+    );
+    assert!(ctx.evaluate_as::<f64>("1 +").is_err());
+}
+
+#[test]
+fn define_prefix() {
+    let mut ctx = Context::new();
+    ctx.define_prefix("bi", "2").unwrap();
+    assert_eq!(eval("3 bimeters", &mut ctx), "3 bimeters");
+    assert_eq!(eval("3 bimeters to m", &mut ctx), "6 m");
+    assert_eq!(eval("1 biliter to ml", &mut ctx), "2000 ml");
+    ctx.define_prefix("halb", "1/2").unwrap();
+    assert_eq!(eval("1 halbliter to ml", &mut ctx), "500 ml");
+    assert_eq!(eval("1 m to halbmeters", &mut ctx), "2 halbmeters");
+    ctx.define_unit("smoot", "smoots", "1.7018 m").unwrap();
+    assert_eq!(eval("1 bismoot to m", &mut ctx), "3.4036 m");
+    // collisions with existing units and prefixes
+    assert!(ctx.define_prefix("bi", "3").is_err());
+    assert!(ctx.define_prefix("kilo", "10").is_err());
+    assert!(ctx.define_prefix("hundred", "100").is_err());
+    assert!(ctx.define_prefix("smoot", "10").is_err());
+    assert_eq!(
+        ctx.define_prefix("meter", "10"),
+        Err("'meter' is already defined as a unit or prefix".to_string())
+    );
+    assert!(ctx.define_prefix("tri", "3 m").is_err());
+}
+
+#[test]
+fn thousands_style() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("1234567", &mut ctx), "1234567");
+    ctx.set_thousands_style(fend_core::ThousandsStyle::Western);
+    assert_eq!(eval("1234567", &mut ctx), "1,234,567");
+    assert_eq!(eval("123456789", &mut ctx), "123,456,789");
+    ctx.set_thousands_style(fend_core::ThousandsStyle::Indian);
+    assert_eq!(eval("1234567", &mut ctx), "12,34,567");
+    assert_eq!(eval("123456789", &mut ctx), "12,34,56,789");
+    assert_eq!(eval("123", &mut ctx), "123");
+    assert_eq!(eval("-1234567.891 kg", &mut ctx), "-12,34,567.891 kg");
+    assert_eq!(eval("1234567/3 to exact", &mut ctx), "4,11,522 1/3");
+    assert_eq!(eval("1.5e-300", &mut ctx), "1.5e-300");
+    // only decimal results are grouped
+    assert_eq!(eval("1234567 to hex", &mut ctx), "12d687");
+}
+
+#[test]
+fn digit_separator_char() {
+    let mut ctx = Context::new();
+    ctx.set_digit_separator_char('\'').unwrap();
+    // grouping still needs to be enabled
+    assert_eq!(eval("1000000", &mut ctx), "1000000");
+    ctx.set_thousands_style(fend_core::ThousandsStyle::Western);
+    assert_eq!(eval("1000000", &mut ctx), "1'000'000");
+    assert_eq!(eval("-1234567.891 kg", &mut ctx), "-1'234'567.891 kg");
+    assert_eq!(eval("1234567/3 to exact", &mut ctx), "411'522 1/3");
+    ctx.set_digit_separator_char(' ').unwrap();
+    assert_eq!(eval("1234567", &mut ctx), "1 234 567");
+    ctx.set_digit_separator_char('_').unwrap();
+    assert_eq!(eval("1234567", &mut ctx), "1_234_567");
+    assert_eq!(
+        ctx.set_digit_separator_char('.'),
+        Err("'.' cannot be used as both the digit separator and the decimal separator".to_string())
+    );
+    assert_eq!(
+        ctx.set_digit_separator_char('-'),
+        Err("'-' cannot be used as a digit separator".to_string())
+    );
+    for separator in ['5', 'e', '+', '/', '('] {
+        assert!(ctx.set_digit_separator_char(separator).is_err());
+    }
+    assert_eq!(eval("1234567", &mut ctx), "1_234_567");
+}
+
+#[test]
+fn zero_display() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("5 m - 5 m", &mut ctx), "0 m");
+    assert_eq!(eval("5 m - 500 cm", &mut ctx), "0 m");
+    ctx.set_zero_display(fend_core::ZeroDisplay::DropUnit);
+    assert_eq!(eval("5 m - 5 m", &mut ctx), "0");
+    assert_eq!(eval("5 m - 500 cm", &mut ctx), "0");
+    assert_eq!(eval("5 m - 4 m", &mut ctx), "1 m");
+    assert_eq!(eval("0 kg", &mut ctx), "0");
+    ctx.set_zero_display(fend_core::ZeroDisplay::KeepUnit);
+    assert_eq!(eval("5 m - 5 m", &mut ctx), "0 m");
+}
+
+#[test]
+fn define_constant() {
+    let mut ctx = Context::new();
+    ctx.define_constant("golden", "(1 + sqrt 5) / 2").unwrap();
+    ctx.define_constant("g0", "9.80665 m/s^2").unwrap();
+    let eval = |input: &str, ctx: &mut Context| {
+        evaluate(input, ctx).map(|res| res.get_main_result().to_string())
+    };
+    assert_eq!(eval("golden", &mut ctx).unwrap(), "approx. 1.6180339886");
+    assert_eq!(eval("2 golden", &mut ctx).unwrap(), "approx. 3.2360679772");
+    assert_eq!(eval("g0 * 2 s", &mut ctx).unwrap(), "19.6133 m / s");
+    assert_eq!(
+        eval("golden = 2", &mut ctx).unwrap_err(),
+        "cannot assign to 'golden' because it is a constant"
+    );
+    assert_eq!(eval("golden", &mut ctx).unwrap(), "approx. 1.6180339886");
+    // function parameters can still shadow constants
+    assert_eq!(eval("(golden: golden * 2) 3", &mut ctx).unwrap(), "6");
+    // constants are kept when the context is cloned
+    assert_eq!(
+        eval("g0 to m/s^2", &mut ctx.clone()).unwrap(),
+        "9.80665 m / s^2"
+    );
+    assert_eq!(
+        ctx.define_constant("word", "\"abc\"").unwrap_err(),
+        "expected a number"
+    );
+}
+
+#[test]
+fn error_verbosity() {
+    let mut ctx = Context::new();
+    assert_eq!(
+        evaluate("1 + ¶", &mut ctx),
+        Err("unexpected character '¶'".to_string())
+    );
+    ctx.set_error_verbosity(ErrorVerbosity::Verbose);
+    assert_eq!(
+        evaluate("1 + ¶", &mut ctx),
+        Err("unexpected character '¶' (at position 4)".to_string())
+    );
+    // errors during evaluation have no position
+    assert_eq!(
+        evaluate("1/0", &mut ctx),
+        Err("division by zero".to_string())
+    );
+    ctx.set_error_verbosity(ErrorVerbosity::Terse);
+    assert_eq!(evaluate("1 + ¶", &mut ctx), Err("error".to_string()));
+    assert_eq!(evaluate("1/0", &mut ctx), Err("error".to_string()));
+    assert_eq!(
+        ctx.evaluate_json("1 + ¶"),
+        r#"{"ok": false, "result": null, "approximate": false, "components": [], "other_info": [], "error": "error"}"#
+    );
+}
+
+#[test]
+fn result_cache() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    let mut ctx = Context::new();
+    ctx.set_result_cache_size(2);
+    ctx.register_function("counted", |x| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        Ok(x)
+    });
+    assert_eq!(eval("counted 2", &mut ctx), "approx. 2");
+    assert_eq!(eval("counted 2", &mut ctx), "approx. 2");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    // cached results still update `ans`
+    assert_eq!(eval("5", &mut ctx), "5");
+    assert_eq!(eval("counted 2", &mut ctx), "approx. 2");
+    assert_eq!(eval("ans * 3", &mut ctx), "approx. 6");
+    assert_eq!(eval("ans * 3", &mut ctx), "approx. 18");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+
+    // changing a setting or a variable clears the cache
+    ctx.set_rounding_mode(RoundingMode::HalfUp);
+    assert_eq!(eval("counted 2", &mut ctx), "approx. 2");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    assert_eq!(eval("a = 1", &mut ctx), "1");
+    assert_eq!(eval("counted a", &mut ctx), "approx. 1");
+    assert_eq!(eval("a = 2", &mut ctx), "2");
+    assert_eq!(eval("counted a", &mut ctx), "approx. 2");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 4);
+
+    // the least recently used result is removed when the cache is full
+    assert_eq!(eval("counted 3", &mut ctx), "approx. 3");
+    assert_eq!(eval("counted 4", &mut ctx), "approx. 4");
+    assert_eq!(eval("counted 3", &mut ctx), "approx. 3");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 6);
+    assert_eq!(eval("counted a", &mut ctx), "approx. 2");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 7);
+
+    ctx.set_result_cache_size(0);
+    assert_eq!(eval("counted 3", &mut ctx), "approx. 3");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 8);
+}
+
+#[test]
+fn integer_digit_limit() {
+    let mut ctx = Context::new();
+    let factorial = "93326215443944152681699238856266700490715968264381621468592963895217599993229915608941463976156518286253697920827223758251185210916864000000000000000000000000";
+    assert_eq!(eval("100!", &mut ctx), factorial);
+    ctx.set_integer_digit_limit(Some(20)).unwrap();
+    assert_eq!(eval("100!", &mut ctx), "approx. 9.3326215443e157");
+    assert!(evaluate("100!", &mut ctx).unwrap().is_approximate());
+    // the exact value is still available
+    assert_eq!(eval("ans to exact", &mut ctx), factorial);
+    assert_eq!(eval("-100! kg", &mut ctx), "approx. -9.3326215443e157 kg");
+    assert_eq!(eval("2^64", &mut ctx), "18446744073709551616");
+    assert_eq!(eval("2^70", &mut ctx), "approx. 1.1805916207e21");
+    assert_eq!(eval("10^21", &mut ctx), "1e21");
+    assert_eq!(eval("2^70 + 0.5", &mut ctx), "1180591620717411303424.5");
+    assert_eq!(eval("2^70 to hex", &mut ctx), "400000000000000000");
+    assert_eq!(
+        ctx.set_integer_digit_limit(Some(0)),
+        Err("the integer digit limit must be at least 1".to_string())
+    );
+    ctx.set_integer_digit_limit(Some(1)).unwrap();
+    assert_eq!(eval("0", &mut ctx), "0");
+    assert_eq!(eval("5", &mut ctx), "5");
+    assert_eq!(eval("12", &mut ctx), "1.2e1");
+    ctx.set_integer_digit_limit(None).unwrap();
+    assert_eq!(eval("100!", &mut ctx), factorial);
+}
+
+#[test]
+fn format_number_base() {
+    let mut ctx = Context::new();
+    let bases = ctx.format_number_base("255").unwrap();
+    assert_eq!(bases.binary(), Some("11111111"));
+    assert_eq!(bases.octal(), Some("377"));
+    assert_eq!(bases.decimal(), "255");
+    assert_eq!(bases.hexadecimal(), Some("ff"));
+    assert_eq!(ctx.format_number_base("0xff").unwrap(), bases);
+    let bases = ctx.format_number_base("-10").unwrap();
+    assert_eq!(bases.binary(), Some("-1010"));
+    assert_eq!(bases.hexadecimal(), Some("-a"));
+    // non-integers are only formatted in decimal
+    for input in ["1.5", "pi", "i"] {
+        let bases = ctx.format_number_base(input).unwrap();
+        assert_eq!(bases.binary(), None);
+        assert_eq!(bases.octal(), None);
+        assert_eq!(bases.hexadecimal(), None);
+    }
+    assert_eq!(ctx.format_number_base("1.5").unwrap().decimal(), "1.5");
+    assert_eq!(
+        ctx.format_number_base("\"a\"").unwrap_err(),
+        "expected a number"
+    );
+}
+
+#[test]
+fn evaluate_json() {
+    let mut ctx = Context::new();
+    assert_eq!(
+        ctx.evaluate_json("2 km"),
+        r#"{"ok": true, "result": "2 km", "approximate": false, "components": [{"number": "2", "unit": "km"}], "other_info": [], "error": null}"#
+    );
+    assert_eq!(
+        ctx.evaluate_json("pi"),
+        r#"{"ok": true, "result": "approx. 3.1415926535", "approximate": true, "components": [{"number": "3.1415926535", "unit": ""}], "other_info": [], "error": null}"#
+    );
+    assert_eq!(
+        ctx.evaluate_json("\"a\\\"b\""),
+        r#"{"ok": true, "result": "a\"b", "approximate": false, "components": [], "other_info": [], "error": null}"#
+    );
+    assert_eq!(
+        ctx.evaluate_json("1/0"),
+        r#"{"ok": false, "result": null, "approximate": false, "components": [], "other_info": [], "error": "division by zero"}"#
+    );
+}
+
+#[test]
+fn complex_output() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("1 + i", &mut ctx), "1 + i");
+    ctx.set_complex_output(fend_core::ComplexOutput::Polar);
+    assert_eq!(eval("1 + i", &mut ctx), "approx. 1.4142135619∠0.7853981633");
+    assert_eq!(
+        eval("-1 - i", &mut ctx),
+        "approx. 1.4142135619∠-2.3561944901"
+    );
+    assert_eq!(eval("3 + 4i", &mut ctx), "approx. 5∠0.927295218");
+    assert_eq!(eval("2i", &mut ctx), "2∠0.5π");
+    assert_eq!(
+        eval("(1 + i) m", &mut ctx),
+        "approx. (1.4142135619∠0.7853981633) m"
+    );
+    assert_eq!(eval("(1 + i) to 2dp", &mut ctx), "approx. 1.41∠0.79");
+    // real numbers are unaffected
+    assert_eq!(eval("-2", &mut ctx), "-2");
+    ctx.set_complex_output(fend_core::ComplexOutput::Rectangular);
+    assert_eq!(eval("1 + i", &mut ctx), "1 + i");
+}
+
+#[test]
+fn result_is_approximate() {
+    let mut ctx = Context::new();
+    let is_approximate =
+        |input: &str, ctx: &mut Context| evaluate(input, ctx).unwrap().is_approximate();
+    assert!(!is_approximate("2+2", &mut ctx));
+    assert!(!is_approximate("1.5 m to cm", &mut ctx));
+    assert!(is_approximate("pi", &mut ctx));
+    assert!(is_approximate("sin 1", &mut ctx));
+    assert!(is_approximate("1/3", &mut ctx));
+    assert!(!is_approximate("1/3 to fraction", &mut ctx));
+    assert!(is_approximate("approx. 2", &mut ctx));
+    assert!(!is_approximate("\"hello\"", &mut ctx));
+    assert!(!is_approximate("(1, 2)", &mut ctx));
+    assert!(is_approximate("(1, 1/3)", &mut ctx));
+    assert!(!is_approximate("", &mut ctx));
+}
+
+#[test]
+fn locale() {
+    let mut ctx = Context::new();
+    // digits aren't grouped until a locale is set
+    assert_eq!(eval("1234567.5", &mut ctx), "1234567.5");
+    ctx.set_space_digit_separator(true);
+    ctx.set_locale(Locale::EnGb);
+    assert_eq!(eval("2 meters", &mut ctx), "2 metres");
+    assert_eq!(eval("1 kilometer", &mut ctx), "1 kilometre");
+    assert_eq!(eval("3 km to meters", &mut ctx), "3,000 metres");
+    assert_eq!(eval("1234567.5", &mut ctx), "1,234,567.5");
+    // unrelated settings are left alone
+    assert_eq!(eval("1 000 000", &mut ctx), "1,000,000");
+    assert_eq!(eval("1 gallon to litres", &mut ctx), "4.54609 litres");
+    assert_eq!(eval("5 m", &mut ctx), "5 m");
+    assert_eq!(eval("1 meter / second", &mut ctx), "1 metre / second");
+
+    ctx.set_unit_system(UnitSystem::UsCustomary);
+    assert_eq!(
+        evaluate("1 gallon to liters", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "3.785411784 litres"
+    );
+
+    // the separators can't be the same, so swap them in steps
+    ctx.set_decimal_separator(',').unwrap_err();
+    ctx.set_digit_separator_char(' ').unwrap();
+    ctx.set_decimal_separator(',').unwrap();
+    ctx.set_digit_separator_char('.').unwrap();
+    assert_eq!(eval("1234567.5", &mut ctx), "1.234.567,5");
+    assert_eq!(eval("1234567.5 to scientific", &mut ctx), "1,2345675e6");
+    assert_eq!(
+        ctx.set_decimal_separator('.'),
+        Err("'.' cannot be used as both the digit separator and the decimal separator".to_string())
+    );
+    assert_eq!(
+        ctx.set_decimal_separator('_'),
+        Err("'_' cannot be used as a decimal separator".to_string())
+    );
+
+    ctx.set_locale(Locale::EnUs);
+    assert_eq!(
+        evaluate("2 meters", &mut ctx).unwrap().get_main_result(),
+        "2 meters"
+    );
+    assert_eq!(eval("1234567.5", &mut ctx), "1,234,567.5");
+    assert_eq!(
+        evaluate("1 gallon to liters", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "3.785411784 liters"
+    );
+}
+
+#[test]
+fn locale_from_language_tag() {
+    assert_eq!(Locale::from_language_tag("en-GB"), Locale::EnGb);
+    assert_eq!(Locale::from_language_tag("en_GB.UTF-8"), Locale::EnGb);
+    assert_eq!(Locale::from_language_tag("en-US"), Locale::EnUs);
+    assert_eq!(Locale::from_language_tag("xx-YY"), Locale::EnUs);
+    assert_eq!(Locale::from_language_tag(""), Locale::EnUs);
+}
+
+#[test]
+fn error_positions() {
+    struct Never;
+    impl fend_core::Interrupt for Never {
+        fn should_interrupt(&self) -> bool {
+            false
+        }
+    }
+    let position = |input: &str| {
+        let mut ctx = Context::new();
+        fend_core::evaluate_with_error_details(input, &mut ctx, &Never)
+            .unwrap_err()
+            .position()
+    };
+    assert_eq!(position("1 @ 2"), Some(2));
+    assert_eq!(position("2 + \"abc"), Some(4));
+    assert_eq!(position("1 + )"), Some(4));
+    assert_eq!(position("2 * * 3"), Some(4));
+    assert_eq!(position("1.2.3"), Some(3));
+    assert_eq!(position("1 +"), Some(3));
+    assert_eq!(position("1 m to"), Some(6));
+    assert_eq!(position(r"\x"), Some(2));
+    assert_eq!(position("@debug 1 @ 2"), Some(9));
+    assert_eq!(position("1/0"), None);
+    assert_eq!(position("unknown_ident"), None);
+
+    let mut ctx = Context::new();
+    let err = fend_core::evaluate_with_error_details("1 @ 2", &mut ctx, &Never).unwrap_err();
+    assert_eq!(err.message(), "unexpected character '@'");
+    assert_eq!(err.to_string(), "unexpected character '@'");
+    assert_eq!(
+        evaluate("1 @ 2", &mut ctx).unwrap_err(),
+        "unexpected character '@'"
+    );
+}
+
+#[test]
+fn explain() {
+    let context = Context::new();
+    let explanation = context.explain("2 km + 300 m").unwrap();
+    assert_eq!(explanation.parsed(), "((2 km)+(300 m))");
+    assert_eq!(
+        explanation.steps(),
+        ["300 m = 0.3 km", "2 km + 300 m = 2.3 km"]
+    );
+    assert_eq!(explanation.result(), "2.3 km");
+
+    let explanation = context.explain("(1 + 2) * 3 km to m").unwrap();
+    assert_eq!(
+        explanation.steps(),
+        ["1 + 2 = 3", "3 * 3 = 9", "9 km to m = 9000 m"]
+    );
+    assert_eq!(explanation.result(), "9000 m");
+
+    // explaining is a dry run
+    let mut context = Context::new();
+    let explanation = context.explain("x = 5; x * 2").unwrap();
+    assert_eq!(explanation.steps(), ["5 * 2 = 10"]);
+    assert_eq!(explanation.result(), "10");
+    assert!(evaluate("x", &mut context).is_err());
+
+    assert!(context.explain("1 +").is_err());
+}
+
+#[test]
+fn unit_dimension() {
+    let mut ctx = Context::new();
+    let dimension = |name: &str, ctx: &mut Context| {
+        ctx.unit_dimension(name).map(|dims| {
+            dims.into_iter()
+                .map(|(dim, exp)| format!("{dim}: {exp}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+    };
+    assert_eq!(
+        dimension("N", &mut ctx).unwrap(),
+        "length: 1, mass: 1, time: -2"
+    );
+    assert_eq!(
+        dimension("joules", &mut ctx).unwrap(),
+        "length: 2, mass: 1, time: -2"
+    );
+    assert_eq!(dimension("km", &mut ctx).unwrap(), "length: 1");
+    assert_eq!(dimension("Hz", &mut ctx).unwrap(), "time: -1");
+    assert_eq!(dimension("°C", &mut ctx).unwrap(), "temperature: 1");
+    assert_eq!(dimension("percent", &mut ctx).unwrap(), "");
+    assert_eq!(
+        dimension("notaunit", &mut ctx),
+        Err(UnitDimensionError::UnknownUnit)
+    );
+
+    ctx.define_unit("widget", "widgets", "!").unwrap();
+    ctx.define_unit("wph", "", "widgets / hour").unwrap();
+    assert_eq!(dimension("wph", &mut ctx).unwrap(), "time: -1, widget: 1");
+    ctx.define_unit("root_hertz", "", "Hz^0.5").unwrap();
+    assert_eq!(
+        dimension("root_hertz", &mut ctx),
+        Err(UnitDimensionError::Other(
+            "dimensions with non-integer exponents are not supported".to_string()
+        ))
+    );
+}
+
+#[test]
+fn powers_of_values_with_units() {
+    test_eval("(5 m)^2", "25 m^2");
+    test_eval("(2 s)^3", "8 s^3");
+    test_eval("(5 m)^-2", "0.04 m^-2");
+    test_eval("(4 m^2)^0.5", "2 m");
+    test_eval("sqrt((3 m)^2 + (4 m)^2)", "5 m");
+    expect_error(
+        "(5 m)^0.5",
+        Some("units can only be raised to integer powers"),
+    );
+    expect_error(
+        "(8 m)^(1/3)",
+        Some("units can only be raised to integer powers"),
+    );
+    test_eval("m^(1/3)", "1 m^(1/3)");
+    test_eval("1 V / sqrt(Hz)", "1 V / Hz^0.5");
+    test_eval("sqrt(m) * sqrt(m)", "1 m");
+}
+
+#[test]
+fn canonical_input() {
+    let context = Context::new();
+    let canonical = |input: &str| context.canonical_input(input).unwrap();
+    assert_eq!(canonical("2*3"), "2 * 3");
+    assert_eq!(canonical("1+2*3"), "1 + (2 * 3)");
+    assert_eq!(canonical("(1+2)*3"), "(1 + 2) * 3");
+    assert_eq!(canonical("-2^2"), "-(2 ^ 2)");
+    assert_eq!(canonical("2^3^2"), "2 ^ (3 ^ 2)");
+    assert_eq!(canonical("sin pi/2"), "(sin pi) / 2");
+    assert_eq!(canonical("2 km + 300 m"), "(2 km) + (300 m)");
+    assert_eq!(canonical("5 kg to lb"), "(5 kg) to lb");
+    assert_eq!(canonical("x = 5; x^2"), "x = 5; x ^ 2");
+    assert_eq!(
+        canonical("if 1 < 2 then 3 else 4"),
+        "if (1 < 2) then 3 else 4"
+    );
+    assert!(context.canonical_input("1 +").is_err());
+
+    // the input is only parsed, not evaluated
+    assert_eq!(canonical("foo * 2"), "foo * 2");
+}
+
+#[test]
+fn underscore_is_previous_answer() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("5", &mut ctx), "5");
+    assert_eq!(eval("_", &mut ctx), "5");
+    assert_eq!(eval("_ + 1", &mut ctx), "6");
+    assert_eq!(eval("2 * _", &mut ctx), "12");
+    // underscores within numbers and identifiers are unaffected
+    assert_eq!(eval("1_000 + _", &mut ctx), "1012");
+    assert_eq!(eval("tax_rate = _ / 1000; tax_rate", &mut ctx), "1.012");
+}
+
+#[test]
+fn newline_policy() {
+    let mut ctx = Context::new();
+    ctx.set_max_digits(20);
+    let combined =
+        |input: &str, ctx: &mut Context| evaluate(input, ctx).unwrap().get_combined_result();
+    // by default, only the main result is returned
+    assert_eq!(combined("30!", &mut ctx), "26525285981219105863\u{2026}");
+
+    ctx.set_newline_policy(NewlinePolicy::Newlines);
+    assert_eq!(
+        combined("30!", &mut ctx),
+        "26525285981219105863\u{2026}\noutput truncated to 20 characters"
+    );
+    assert_eq!(combined("1 + 1", &mut ctx), "2");
+
+    ctx.set_newline_policy(NewlinePolicy::Semicolons);
+    assert_eq!(
+        combined("30!", &mut ctx),
+        "26525285981219105863\u{2026}; output truncated to 20 characters"
+    );
+    assert_eq!(combined("1 + 1", &mut ctx), "2");
+
+    ctx.set_newline_policy(NewlinePolicy::MainResultOnly);
+    assert_eq!(combined("30!", &mut ctx), "26525285981219105863\u{2026}");
+}
+
+#[test]
+fn undefined_and_infinite_results() {
+    for (input, error) in [
+        ("1/0", "division by zero"),
+        ("0/0", "division by zero"),
+        ("(1 m)/(0 m)", "division by zero"),
+        ("0^-1", "division by zero"),
+        ("0^(-0.5)", "division by zero"),
+        ("5 // 0", "division by zero"),
+        ("1 mod 0", "modulo by zero"),
+        ("tan(pi/2)", "division by zero"),
+        ("ln 0", "0 must lie in the interval (0, \u{221e})"),
+        ("log10 0", "0 must lie in the interval (0, \u{221e})"),
+        ("log2 (-1)", "-1 must lie in the interval (0, \u{221e})"),
+        ("asin 2", "2 must lie in the interval [-1, 1]"),
+        ("acos (-2)", "-2 must lie in the interval [-1, 1]"),
+        ("atanh 1", "1 must lie in the interval (-1, 1)"),
+        ("acosh 0", "0 must lie in the interval [1, \u{221e})"),
+        ("sqrt(-1)", "roots of negative numbers are not supported"),
+        ("sinh 1000", "number is too large to approximate"),
+        ("cosh (-1000)", "number is too large to approximate"),
+        ("sin (10^400)", "number is too large to approximate"),
+    ] {
+        assert_eq!(evaluate(input, &mut Context::new()), Err(error.to_string()));
+    }
+    // large results that still fit into a float are converted correctly
+    test_eval("sinh 50 > 2.59e21", "true");
+    test_eval("sinh 50 < 2.6e21", "true");
+    test_eval("asin 1", "approx. 1.5707963267");
+}
+
+#[test]
+fn concentration_units() {
+    test_eval("0.5 mol/L to mmol/mL", "0.5 mmol / mL");
+    test_eval("0.5 mol/L to mol/m^3", "500 mol / m^3");
+    test_eval("250 millimolar to molar", "0.25 molar");
+    test_eval("1 mmol/L to micromolar", "1000 micromolar");
+    test_eval("0.5 molar * 200 mL to mmol", "100 mmol");
+    test_eval("2 mol / (4 kg) to molal", "0.5 molal");
+}
+
+#[test]
+fn times_ten_to_the_power_notation() {
+    test_eval("1.5×10^3", "1500");
+    test_eval("1.5 × 10^3", "1500");
+    test_eval("1.5×10^-3", "0.0015");
+    test_eval("1.5×10³", "1500");
+    test_eval("1.5×10^3 m", "1500 m");
+    test_eval("1.5×10^-3 m", "0.0015 m");
+    test_eval("2.5×10^3 m to km", "2.5 km");
+    test_eval("6.022×10^23 / mol", "602200000000000000000000 mol^-1");
+}
+
+#[test]
+fn reciprocal_function() {
+    test_eval("inv(4)", "0.25");
+    test_eval("inv(2/3)", "1.5");
+    test_eval("reciprocal(0.5)", "2");
+    test_eval("inv pi", "approx. 0.3183098861");
+    test_eval("inv(4 m)", "0.25 m^-1");
+    test_eval("inv(2 Hz)", "0.5 s");
+    test_eval("inv(4 MHz)", "0.00000025 s");
+    test_eval("inv(0.5 km^-1)", "2 km");
+    test_eval("inv(4 m/s)", "0.25 s / m");
+    test_eval("inv(inv(2 Hz)) to Hz", "2 Hz");
+    test_eval("inv(2 rpm)", "0.5 rpm^-1");
+    test_eval("inv(2 rad/s)", "0.5 s / rad");
+    expect_error("inv(0)", Some("division by zero"));
+    expect_error("inv(0 Hz)", Some("division by zero"));
+}
+
+#[test]
+fn define_unit_in_terms_of_derived_units() {
+    let mut ctx = Context::new();
+    ctx.define_unit("horsepower", "horsepower", "745.7 W")
+        .unwrap();
+    ctx.define_unit("hp", "", "horsepower").unwrap();
+    ctx.define_unit("donkeypower", "", "1/3 hp").unwrap();
+    assert_eq!(eval("1 hp to W", &mut ctx), "745.7 W");
+    assert_eq!(eval("2 horsepower to kW", &mut ctx), "1.4914 kW");
+    assert_eq!(eval("1 kilohorsepower to W", &mut ctx), "745700 W");
+    assert_eq!(eval("745.7 W to hp", &mut ctx), "1 hp");
+    assert_eq!(eval("1 hp * 1 hour to kWh", &mut ctx), "0.7457 kWh");
+    assert_eq!(eval("3 donkeypower to W", &mut ctx), "745.7 W");
+}
+
+#[test]
+fn list_prefixes() {
+    let mut ctx = Context::new();
+    let prefixes = ctx.list_prefixes().unwrap();
+    assert_eq!(prefixes.first().unwrap().0, "quecto");
+    assert_eq!(prefixes.last().unwrap().0, "quecca");
+    assert!((prefixes.last().unwrap().1 / 1e30 - 1.0).abs() < 1e-10);
+    assert!(prefixes.windows(2).all(|w| w[0].1 <= w[1].1));
+    let factor = |prefixes: &[(String, f64)], name: &str| {
+        prefixes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, factor)| *factor)
+    };
+    assert_eq!(factor(&prefixes, "kilo"), Some(1000.0));
+    assert_eq!(factor(&prefixes, "k"), Some(1000.0));
+    assert_eq!(factor(&prefixes, "deka"), Some(10.0));
+    assert_eq!(factor(&prefixes, "Ki"), Some(1024.0));
+    assert_eq!(factor(&prefixes, "half"), Some(0.5));
+    assert_eq!(factor(&prefixes, "meter"), None);
+    assert_eq!(factor(&prefixes, "bi"), None);
+
+    ctx.define_prefix("bi", "2").unwrap();
+    assert_eq!(factor(&ctx.list_prefixes().unwrap(), "bi"), Some(2.0));
+
+    eval("x = 3", &mut ctx);
+    ctx.define_prefix("tri", "x").unwrap();
+    assert_eq!(factor(&ctx.list_prefixes().unwrap(), "tri"), Some(3.0));
+    eval("x = 3 m", &mut ctx);
+    assert_eq!(
+        ctx.list_prefixes(),
+        Err("the prefix 'tri' could not be evaluated: expected a unitless number".to_string())
+    );
+}
+
+#[test]
+fn auto_simplify() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("m * m", &mut ctx), "1 m^2");
+    assert_eq!(eval("2 m * m", &mut ctx), "2 m^2");
+    assert_eq!(eval("5 N * 2 m", &mut ctx), "10 J");
+    assert_eq!(eval("3 m / m", &mut ctx), "3");
+
+    ctx.set_auto_simplify(false);
+    assert_eq!(eval("m * m", &mut ctx), "1 m m");
+    assert_eq!(eval("2 m * m", &mut ctx), "2 m m");
+    assert_eq!(eval("5 N * 2 m", &mut ctx), "10 N m");
+    assert_eq!(eval("3 m / m", &mut ctx), "3 m / m");
+    assert_eq!(eval("2 m * m to m^2", &mut ctx), "2 m^2");
+
+    ctx.set_auto_simplify(true);
+    assert_eq!(eval("2 m * m", &mut ctx), "2 m^2");
+}
+
+#[test]
+fn quantities() {
+    let mut ctx = Context::new();
+    let format = |q: &Quantity, ctx: &Context| q.format(ctx).unwrap();
+
+    assert_eq!(format(&Quantity::from(-42), &ctx), "-42");
+    let half = Quantity::try_from(2.5).unwrap();
+    assert!(half.is_exact());
+    assert_eq!(format(&half, &ctx), "2.5");
+    assert_eq!(half.to_f64().unwrap(), 2.5);
+    assert_eq!(
+        Quantity::try_from(f64::INFINITY).unwrap_err(),
+        "infinity is not a representable value"
+    );
+    assert_eq!(
+        Quantity::try_from(f64::NAN).unwrap_err(),
+        "result is undefined"
+    );
+
+    let speed = half.with_unit("km/h", &ctx).unwrap();
+    assert_eq!(format(&speed, &ctx), "2.5 km / h");
+    assert_eq!(
+        speed.to_f64_with_unit(&ctx).unwrap(),
+        (2.5, "km / h".to_string())
+    );
+    assert_eq!(speed.to_f64().unwrap_err(), "expected a unitless number");
+    assert_eq!(
+        Quantity::from(1).with_unit("notaunit", &ctx).unwrap_err(),
+        "unknown identifier 'notaunit'"
+    );
+
+    ctx.set_variable("speed", speed);
+    let distance = ctx.evaluate_as::<Quantity>("speed * 2 hours to m").unwrap();
+    assert_eq!(format(&distance, &ctx), "5000 m");
+    assert_eq!(
+        evaluate("speed to m/s", &mut ctx)
+            .unwrap()
+            .get_main_result(),
+        "approx. 0.6944444444 m / s"
+    );
+    assert!(ctx.evaluate_as::<Quantity>("sqrt(2)").unwrap().is_exact());
+    assert!(!ctx.evaluate_as::<Quantity>("sin 1").unwrap().is_exact());
+    assert!(ctx.evaluate_as::<Quantity>("true").is_err());
+}
+
+#[test]
+fn implicit_multiplication_with_variables() {
+    let mut ctx = Context::new();
+    assert_eq!(eval("x = 5", &mut ctx), "5");
+    assert_eq!(eval("3 x", &mut ctx), "15");
+    assert_eq!(eval("3x", &mut ctx), "15");
+    assert_eq!(eval("-3 x", &mut ctx), "-15");
+    assert_eq!(eval("2 x^2", &mut ctx), "50");
+    assert_eq!(eval("d = 2 m", &mut ctx), "2 m");
+    assert_eq!(eval("3 d to cm", &mut ctx), "600 cm");
+
+    // numbers followed by functions multiply the function's result
+    assert_eq!(eval("f = y: y + 1", &mut ctx), "\\y.(y+1)");
+    assert_eq!(eval("3 f 2", &mut ctx), "9");
+    assert_eq!(eval("3 f(2)", &mut ctx), "9");
+    assert_eq!(eval("x f 2", &mut ctx), "15");
+    assert_eq!(eval("3 sqrt 4", &mut ctx), "6");
+    assert_eq!(eval("3 sin 2", &mut ctx), "approx. 2.7278922804");
+    assert_eq!(eval("3 sin pi", &mut ctx), "0");
+    assert_eq!(
+        eval("5 feet 10 inches", &mut ctx),
+        "approx. 5.8333333333 feet"
+    );
+}